@@ -0,0 +1,386 @@
+//! Example of decoding the input of a transaction to an unknown contract, by looking up its
+//! selector in a local signature database seeded from JSON ABIs and ranking the candidate
+//! signatures by how cleanly they decode the input.
+
+use std::{collections::HashMap, fmt::Write, fs, path::Path};
+
+use alloy::{
+    dyn_abi::{DynSolType, DynSolValue, JsonAbiExt, Specifier},
+    hex,
+    json_abi::{Function, JsonAbi, Param},
+    primitives::{address, Selector, U256},
+};
+use eyre::{ensure, OptionExt, Result};
+use serde_json::Value;
+
+/// A signature of a selector in the database.
+#[derive(Clone, Debug)]
+pub struct Candidate {
+    /// The function, with the names of the parameters if it comes from an ABI.
+    pub function: Function,
+    /// Where the signature comes from, e.g. the path of the ABI.
+    pub source: String,
+}
+
+impl Candidate {
+    /// Returns whether every parameter is named.
+    fn is_named(&self) -> bool {
+        fn named(params: &[Param]) -> bool {
+            params.iter().all(|param| !param.name.is_empty() && named(&param.components))
+        }
+        named(&self.function.inputs)
+    }
+}
+
+/// How well a candidate signature decodes an input.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Fit {
+    /// The input is exactly the encoding of the decoded arguments.
+    Exact,
+    /// The input decodes, but is not the canonical encoding of the decoded arguments, e.g. it has
+    /// trailing bytes or non-zero padding.
+    Loose {
+        /// The number of bytes that differ from the canonical encoding.
+        mismatched_bytes: usize,
+    },
+    /// The input does not decode.
+    Failed(String),
+}
+
+impl Fit {
+    /// Returns the rank of the fit, lower is better.
+    const fn rank(&self) -> (u8, usize) {
+        match self {
+            Self::Exact => (0, 0),
+            Self::Loose { mismatched_bytes } => (1, *mismatched_bytes),
+            Self::Failed(_) => (2, 0),
+        }
+    }
+}
+
+/// The input decoded with a candidate signature.
+#[derive(Clone, Debug)]
+pub struct DecodedCall {
+    /// The candidate signature.
+    pub candidate: Candidate,
+    /// How well the signature decodes the input.
+    pub fit: Fit,
+    /// The arguments as a struct named after the function, with the parameter names as fields,
+    /// unless decoding failed.
+    pub args: Option<DynSolValue>,
+}
+
+impl DecodedCall {
+    /// Renders the arguments as an indented tree of names, types and values.
+    pub fn render(&self) -> String {
+        let mut out = format!("{} [{:?}]\n", self.candidate.function.signature(), self.fit);
+        if let Some(DynSolValue::CustomStruct { prop_names, tuple, .. }) = &self.args {
+            for (name, value) in prop_names.iter().zip(tuple) {
+                render_value(&mut out, name, value, 1);
+            }
+        }
+        out
+    }
+}
+
+/// Renders `value` named `name` at `depth`, with its fields or elements below it.
+fn render_value(out: &mut String, name: &str, value: &DynSolValue, depth: usize) {
+    let indent = "  ".repeat(depth);
+    match value {
+        DynSolValue::CustomStruct { name: ty, prop_names, tuple } => {
+            let _ = writeln!(out, "{indent}{name}: {ty}");
+            for (name, value) in prop_names.iter().zip(tuple) {
+                render_value(out, name, value, depth + 1);
+            }
+        }
+        DynSolValue::Array(values) | DynSolValue::FixedArray(values) => {
+            let ty = value.sol_type_name().unwrap_or_default();
+            let _ = writeln!(out, "{indent}{name}: {ty} ({} elements)", values.len());
+            for (i, value) in values.iter().enumerate() {
+                render_value(out, &format!("[{i}]"), value, depth + 1);
+            }
+        }
+        value => {
+            let ty = value.sol_type_name().unwrap_or_default();
+            let formatted = match value {
+                DynSolValue::Address(address) => address.to_string(),
+                DynSolValue::Bool(bool) => bool.to_string(),
+                DynSolValue::Int(int, _) => int.to_string(),
+                DynSolValue::Uint(uint, _) => uint.to_string(),
+                DynSolValue::FixedBytes(word, size) => hex::encode_prefixed(&word[..*size]),
+                DynSolValue::Bytes(bytes) => hex::encode_prefixed(bytes),
+                DynSolValue::String(string) => format!("{string:?}"),
+                DynSolValue::Function(function) => function.to_string(),
+                _ => format!("{value:?}"),
+            };
+            let _ = writeln!(out, "{indent}{name}: {ty} = {formatted}");
+        }
+    }
+}
+
+/// Names the fields of `value` after `params`, recursing into nested tuples and arrays of tuples.
+///
+/// Tuples become structs named after the `internalType` of the parameter when it is a struct, and
+/// parameters without a name are named after their position.
+fn name_value(value: DynSolValue, name: &str, params: &[Param]) -> DynSolValue {
+    match value {
+        DynSolValue::Tuple(values) => {
+            let prop_names = (0..values.len())
+                .map(|i| match params.get(i) {
+                    Some(param) if !param.name.is_empty() => param.name.clone(),
+                    _ => format!("arg{i}"),
+                })
+                .collect();
+            let tuple = values
+                .into_iter()
+                .enumerate()
+                .map(|(i, value)| match params.get(i) {
+                    Some(param) => {
+                        let name = param
+                            .internal_type
+                            .as_ref()
+                            .and_then(|ty| ty.as_struct())
+                            .map_or("tuple", |(_, name)| name);
+                        name_value(value, name, &param.components)
+                    }
+                    None => value,
+                })
+                .collect();
+            DynSolValue::CustomStruct { name: name.to_string(), prop_names, tuple }
+        }
+        DynSolValue::Array(values) => DynSolValue::Array(
+            values.into_iter().map(|value| name_value(value, name, params)).collect(),
+        ),
+        DynSolValue::FixedArray(values) => DynSolValue::FixedArray(
+            values.into_iter().map(|value| name_value(value, name, params)).collect(),
+        ),
+        value => value,
+    }
+}
+
+/// Reads the ABI of a JSON file that is either a bare ABI or an artifact with an `abi` field.
+///
+/// The bytecode of artifacts is not parsed, as it may be unlinked.
+fn read_abi(path: &Path) -> Result<Option<JsonAbi>> {
+    let json: Value = serde_json::from_str(&fs::read_to_string(path)?)?;
+    let abi = match json {
+        Value::Array(_) => json,
+        Value::Object(mut object) => match object.remove("abi") {
+            Some(abi) => abi,
+            None => return Ok(None),
+        },
+        _ => return Ok(None),
+    };
+    Ok(serde_json::from_value(abi).ok())
+}
+
+/// A local database of function signatures by selector.
+#[derive(Debug, Default)]
+pub struct SignatureDatabase {
+    selectors: HashMap<Selector, Vec<Candidate>>,
+}
+
+impl SignatureDatabase {
+    /// Returns the number of distinct signatures.
+    pub fn len(&self) -> usize {
+        self.selectors.values().map(Vec::len).sum()
+    }
+
+    /// Returns whether the database is empty.
+    pub fn is_empty(&self) -> bool {
+        self.selectors.is_empty()
+    }
+
+    /// Returns the candidate signatures of `selector`.
+    pub fn candidates(&self, selector: Selector) -> &[Candidate] {
+        self.selectors.get(&selector).map_or(&[], Vec::as_slice)
+    }
+
+    /// Adds a function, keeping a single candidate per signature.
+    ///
+    /// A candidate with named parameters replaces one without, so ABIs take precedence over bare
+    /// signatures.
+    pub fn add_function(&mut self, function: Function, source: &str) {
+        let candidate = Candidate { function, source: source.to_string() };
+        let candidates = self.selectors.entry(candidate.function.selector()).or_default();
+        let signature = candidate.function.signature();
+        match candidates.iter_mut().find(|existing| existing.function.signature() == signature) {
+            Some(existing) if !existing.is_named() && candidate.is_named() => *existing = candidate,
+            Some(_) => {}
+            None => candidates.push(candidate),
+        }
+    }
+
+    /// Adds the functions of `abi`.
+    pub fn add_abi(&mut self, abi: &JsonAbi, source: &str) {
+        for function in abi.functions() {
+            self.add_function(function.clone(), source);
+        }
+    }
+
+    /// Adds a bare signature such as `transfer(address,uint256)`, as found in selector lists.
+    pub fn add_signature(&mut self, signature: &str, source: &str) -> Result<()> {
+        self.add_function(Function::parse(signature)?, source);
+        Ok(())
+    }
+
+    /// Adds the ABIs of the JSON files in `dir`, either bare ABIs or artifacts with an `abi` field,
+    /// and returns how many were found.
+    pub fn add_dir(&mut self, dir: &Path) -> Result<usize> {
+        let mut count = 0;
+        for entry in fs::read_dir(dir)? {
+            let path = entry?.path();
+            if path.extension().is_none_or(|extension| extension != "json") {
+                continue;
+            }
+            // Other JSON files, e.g. keystores, have no ABI and are skipped.
+            let Some(abi) = read_abi(&path)? else { continue };
+            self.add_abi(&abi, &path.display().to_string());
+            count += 1;
+        }
+        Ok(count)
+    }
+
+    /// Decodes `input` with every candidate signature of its selector, best fit first.
+    ///
+    /// Each signature is parsed into a tuple `DynSolType` and decoded independently of the ABI it
+    /// comes from, and the input is re-encoded to tell exact decodings from merely successful
+    /// ones. Among equal fits, candidates with named parameters come first.
+    pub fn decode(&self, input: &[u8]) -> Result<Vec<DecodedCall>> {
+        ensure!(input.len() >= 4, "the input is shorter than a selector");
+        let (selector, data) = input.split_at(4);
+
+        let mut decoded: Vec<DecodedCall> = self
+            .candidates(Selector::from_slice(selector))
+            .iter()
+            .map(|candidate| {
+                let signature = candidate.function.signature();
+                let params = &signature[signature.find('(').unwrap_or_default()..];
+                let (fit, args) = match DynSolType::parse(params)
+                    .and_then(|ty| ty.abi_decode_sequence(data))
+                {
+                    Ok(args) => {
+                        let encoded = args.abi_encode_sequence().unwrap_or_default();
+                        let mismatched_bytes =
+                            encoded.iter().zip(data).filter(|(a, b)| a != b).count()
+                                + encoded.len().abs_diff(data.len());
+                        let fit = if mismatched_bytes == 0 {
+                            Fit::Exact
+                        } else {
+                            Fit::Loose { mismatched_bytes }
+                        };
+                        let args =
+                            name_value(args, &candidate.function.name, &candidate.function.inputs);
+                        (fit, Some(args))
+                    }
+                    Err(err) => (Fit::Failed(err.to_string()), None),
+                };
+                DecodedCall { candidate: candidate.clone(), fit, args }
+            })
+            .collect();
+
+        decoded.sort_by_key(|call| (call.fit.rank(), !call.candidate.is_named()));
+        Ok(decoded)
+    }
+}
+
+/// Bare signatures, as in a dump of a public selector database, including known collisions of
+/// `transfer(address,uint256)` and `approve(address,uint256)`.
+const SIGNATURES: &[&str] = &[
+    "swapExactTokensForTokens(uint256,uint256,address[],address,uint256)",
+    "transfer(address,uint256)",
+    "many_msg_babbage(bytes1)",
+    "func_2093253501(bytes)",
+    "transfer(bytes4[9],bytes5[6],int48[11])",
+    "join_tg_invmru_haha_fd06787(address,bool)",
+    "approve(address,uint256)",
+    "sign_szabo_bytecode(bytes16,uint128)",
+];
+
+#[tokio::main]
+async fn main() -> Result<()> {
+    // Seed the database with the ABIs and artifacts of the benchmarks and of the examples.
+    let root = std::env::current_dir()?;
+    let mut db = SignatureDatabase::default();
+    let mut abis = db.add_dir(&root.join("benches/artifacts"))?;
+    for example in fs::read_dir(root.join("examples"))? {
+        let example = example?.path();
+        for dir in ["abi", "artifacts"] {
+            let dir = example.join("examples").join(dir);
+            if dir.is_dir() {
+                abis += db.add_dir(&dir)?;
+            }
+        }
+    }
+    for signature in SIGNATURES {
+        db.add_signature(signature, "selector list")?;
+    }
+    println!("Loaded {} signatures from {abis} ABIs\n", db.len());
+    ensure!(!db.is_empty(), "no signatures loaded");
+
+    // The transaction of `decode_input.rs`, which is only in the selector list, so the parameters
+    // have no names.
+    let input = hex::decode("0x38ed173900000000000000000000000000000000000000000001a717cc0a3e4f84c00000000000000000000000000000000000000000000000000000000000000283568400000000000000000000000000000000000000000000000000000000000000a0000000000000000000000000201f129111c60401630932d9f9811bd5b5fff34e000000000000000000000000000000000000000000000000000000006227723d000000000000000000000000000000000000000000000000000000000000000200000000000000000000000095ad61b0a150d79219dcf64e1e6cc01f0b64c4ce000000000000000000000000dac17f958d2ee523a2206206994597c13d831ec7")?;
+    let decoded = db.decode(&input)?;
+    let best = decoded.first().ok_or_eyre("no candidate for the selector")?;
+    print!("{}", best.render());
+    assert_eq!(best.fit, Fit::Exact);
+    let Some(DynSolValue::CustomStruct { tuple, .. }) = &best.args else {
+        unreachable!("decoded calls are structs")
+    };
+    let DynSolValue::Array(path) = &tuple[2] else { unreachable!("the path is an array") };
+    assert_eq!(
+        path.last().and_then(DynSolValue::as_address),
+        Some(address!("dAC17F958D2ee523a2206206994597C13D831ec7"))
+    );
+
+    // A transfer, whose selector collides with signatures that decode loosely or not at all.
+    let transfer = Function::parse("transfer(address,uint256)")?;
+    let input = transfer.abi_encode_input(&[
+        DynSolValue::Address(address!("d8dA6BF26964aF9D7eEd9e03E53415D37aA96045")),
+        DynSolValue::Uint(U256::from(1_000_000), 256),
+    ])?;
+    println!();
+    let decoded = db.decode(&input)?;
+    for call in &decoded {
+        println!("{:?}: {}", call.fit, call.candidate.function.signature());
+    }
+    print!("{}", decoded[0].render());
+    assert_eq!(decoded.len(), 5);
+    assert_eq!(decoded[0].fit, Fit::Exact);
+    assert!(decoded[0].candidate.is_named(), "the named ABI signature ranks first");
+    assert!(matches!(decoded[1].fit, Fit::Loose { .. }));
+    assert!(matches!(decoded[4].fit, Fit::Failed(_)));
+
+    // A Uniswap V4 swap, with nested structs named after their `internalType`.
+    let pool_manager = read_abi(&root.join("benches/artifacts/UniV4PoolManager.json"))?
+        .ok_or_eyre("no pool manager ABI")?;
+    let swap = pool_manager.function("swap").and_then(|f| f.first()).ok_or_eyre("no swap")?;
+    let args = [
+        "(0x0000000000000000000000000000000000000000,0xA0b86991c6218b36c1d19D4a2e9Eb0cE3606eB48,500,10,0x0000000000000000000000000000000000000000)",
+        "(true,-1000000000000000000,4295128740)",
+        "0x",
+    ];
+    let values = swap
+        .inputs
+        .iter()
+        .zip(args)
+        .map(|(param, arg)| Ok(param.resolve()?.coerce_str(arg)?))
+        .collect::<Result<Vec<_>>>()?;
+    let input = swap.abi_encode_input(&values)?;
+    println!();
+    let decoded = db.decode(&input)?;
+    print!("{}", decoded[0].render());
+    let Some(DynSolValue::CustomStruct { tuple, .. }) = &decoded[0].args else {
+        unreachable!("decoded calls are structs")
+    };
+    assert!(
+        matches!(&tuple[0], DynSolValue::CustomStruct { name, .. } if name == "PoolKey"),
+        "the pool key is named after its struct"
+    );
+
+    // An unknown selector has no candidates.
+    assert!(db.decode(&hex!("deadbeef"))?.is_empty());
+
+    Ok(())
+}
@@ -0,0 +1,582 @@
+//! Example of recursively decoding calldata that wraps further calldata, such as Safe
+//! `execTransaction`, `MultiSend`, `Multicall3.aggregate3`, Universal Router `execute` and
+//! `FlashBotsMultiCall` payloads, into a tree rendered as indented text or JSON.
+
+use std::{collections::HashMap, fmt::Write};
+
+use alloy::{
+    dyn_abi::{DynSolValue, JsonAbiExt},
+    hex,
+    json_abi::{ContractObject, Function, JsonAbi, Param},
+    primitives::{address, Address, Bytes, Selector, U256},
+    sol,
+    sol_types::{SolCall, SolValue},
+};
+use eyre::Result;
+use serde::Serialize;
+
+sol! {
+    #[allow(missing_docs)]
+    #[sol(abi)]
+    interface ISafe {
+        function execTransaction(
+            address to,
+            uint256 value,
+            bytes calldata data,
+            uint8 operation,
+            uint256 safeTxGas,
+            uint256 baseGas,
+            uint256 gasPrice,
+            address gasToken,
+            address refundReceiver,
+            bytes memory signatures
+        ) external payable returns (bool success);
+    }
+
+    #[allow(missing_docs)]
+    #[sol(abi)]
+    interface IMultiSend {
+        function multiSend(bytes memory transactions) external payable;
+    }
+
+    #[allow(missing_docs)]
+    #[sol(abi)]
+    interface IMulticall3 {
+        struct Call3 {
+            address target;
+            bool allowFailure;
+            bytes callData;
+        }
+
+        struct Result {
+            bool success;
+            bytes returnData;
+        }
+
+        function aggregate3(Call3[] calldata calls) external payable returns (Result[] memory returnData);
+    }
+
+    #[allow(missing_docs)]
+    #[sol(abi)]
+    interface IUniversalRouter {
+        function execute(bytes calldata commands, bytes[] calldata inputs, uint256 deadline) external payable;
+    }
+
+    #[allow(missing_docs)]
+    #[sol(abi)]
+    interface IERC20 {
+        function approve(address spender, uint256 amount) external returns (bool);
+    }
+
+    #[allow(missing_docs)]
+    #[sol(abi)]
+    interface IUniswapV2Pair {
+        function swap(uint256 amount0Out, uint256 amount1Out, address to, bytes calldata data) external;
+    }
+
+    // The inputs of the Universal Router commands are ABI-encoded without a selector.
+    #[allow(missing_docs, non_snake_case)]
+    #[sol(abi)]
+    interface IUniversalRouterCommands {
+        struct PermitDetails {
+            address token;
+            uint160 amount;
+            uint48 expiration;
+            uint48 nonce;
+        }
+
+        struct PermitSingle {
+            PermitDetails details;
+            address spender;
+            uint256 sigDeadline;
+        }
+
+        function V3_SWAP_EXACT_IN(address recipient, uint256 amountIn, uint256 amountOutMin, bytes path, bool payerIsUser);
+        function V3_SWAP_EXACT_OUT(address recipient, uint256 amountOut, uint256 amountInMax, bytes path, bool payerIsUser);
+        function PERMIT2_TRANSFER_FROM(address token, address recipient, uint160 amount);
+        function SWEEP(address token, address recipient, uint256 amountMin);
+        function TRANSFER(address token, address recipient, uint256 value);
+        function PAY_PORTION(address token, address recipient, uint256 bips);
+        function V2_SWAP_EXACT_IN(address recipient, uint256 amountIn, uint256 amountOutMin, address[] path, bool payerIsUser);
+        function V2_SWAP_EXACT_OUT(address recipient, uint256 amountOut, uint256 amountInMax, address[] path, bool payerIsUser);
+        function PERMIT2_PERMIT(PermitSingle permitSingle, bytes signature);
+        function WRAP_ETH(address recipient, uint256 amountMin);
+        function UNWRAP_WETH(address recipient, uint256 amountMin);
+    }
+
+    #[allow(missing_docs)]
+    interface IFlashBotsMultiCall {
+        function uniswapWeth(
+            uint256 _wethAmountToFirstMarket,
+            uint256 _ethAmountToCoinbase,
+            address[] memory _targets,
+            bytes[] memory _payloads
+        ) external payable;
+    }
+}
+
+/// The Universal Router commands by type, whose inputs are declared as the parameters of the
+/// functions of `IUniversalRouterCommands`.
+///
+/// See <https://docs.uniswap.org/contracts/universal-router/technical-reference>.
+const UNIVERSAL_ROUTER_COMMANDS: &[(u8, &str)] = &[
+    (0x00, "V3_SWAP_EXACT_IN"),
+    (0x01, "V3_SWAP_EXACT_OUT"),
+    (0x02, "PERMIT2_TRANSFER_FROM"),
+    (0x04, "SWEEP"),
+    (0x05, "TRANSFER"),
+    (0x06, "PAY_PORTION"),
+    (0x08, "V2_SWAP_EXACT_IN"),
+    (0x09, "V2_SWAP_EXACT_OUT"),
+    (0x0a, "PERMIT2_PERMIT"),
+    (0x0b, "WRAP_ETH"),
+    (0x0c, "UNWRAP_WETH"),
+];
+
+/// The maximum depth of nested calls, beyond which `bytes` are left undecoded.
+const MAX_DEPTH: usize = 16;
+
+/// A decoded call.
+#[derive(Clone, Debug, Serialize)]
+pub struct DecodedCall {
+    /// The signature of the function.
+    pub signature: String,
+    /// The decoded arguments.
+    pub args: Vec<DecodedArg>,
+}
+
+/// A decoded argument or field of a tuple.
+#[derive(Clone, Debug, Serialize)]
+pub struct DecodedArg {
+    /// The name of the parameter, or its position if it has none.
+    pub name: String,
+    /// The Solidity type of the parameter.
+    #[serde(rename = "type")]
+    pub ty: String,
+    /// The decoded value.
+    pub value: DecodedValue,
+}
+
+/// A decoded value.
+#[derive(Clone, Debug, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum DecodedValue {
+    /// A value that does not contain calldata, formatted.
+    Value(String),
+    /// A tuple, with its fields.
+    Tuple(Vec<DecodedArg>),
+    /// An array, or a list of calls packed in `bytes`.
+    Array(Vec<Self>),
+    /// `bytes` containing the calldata of a known function.
+    Call(DecodedCall),
+}
+
+/// The format of a `bytes` parameter that contains calls without being ABI-encoded calldata.
+#[derive(Clone, Copy, Debug)]
+pub enum Payload {
+    /// The transactions of `MultiSend`, packed as `operation`, `to`, `value`, `data.length` and
+    /// `data`.
+    MultiSendTransactions,
+    /// The inputs of Universal Router commands, ABI-encoded according to the command type in the
+    /// same position of the `commands` argument.
+    UniversalRouterInputs,
+}
+
+/// Decodes calldata, recursing into the `bytes` arguments that contain calldata.
+#[derive(Debug, Default)]
+pub struct NestedDecoder {
+    functions: HashMap<Selector, Vec<Function>>,
+    payloads: HashMap<(Selector, String), Payload>,
+    commands: HashMap<u8, Function>,
+}
+
+impl NestedDecoder {
+    /// Creates a decoder that knows the Universal Router commands.
+    pub fn new() -> Result<Self> {
+        let mut decoder = Self::default();
+        let abi = IUniversalRouterCommands::abi::contract();
+        for (command, name) in UNIVERSAL_ROUTER_COMMANDS {
+            let function = abi.function(name).and_then(|functions| functions.first());
+            let function = function.ok_or_else(|| eyre::eyre!("no inputs for {name}"))?;
+            decoder.commands.insert(*command, function.clone());
+        }
+        Ok(decoder)
+    }
+
+    /// Registers the functions of `abi`.
+    pub fn register_abi(&mut self, abi: &JsonAbi) {
+        for function in abi.functions() {
+            let functions = self.functions.entry(function.selector()).or_default();
+            if !functions.contains(function) {
+                functions.push(function.clone());
+            }
+        }
+    }
+
+    /// Registers the format of the parameter `param` of the function with `selector`, for
+    /// `bytes` that contain calls in another format than ABI-encoded calldata.
+    pub fn register_payload(&mut self, selector: Selector, param: &str, payload: Payload) {
+        self.payloads.insert((selector, param.to_string()), payload);
+    }
+
+    /// Decodes `input` if it is the calldata of a registered function.
+    ///
+    /// A candidate function is only accepted if the input is exactly the encoding of the decoded
+    /// arguments, so that arbitrary `bytes` are not mistaken for calldata.
+    pub fn decode(&self, input: &[u8]) -> Option<DecodedCall> {
+        self.decode_call(input, 0)
+    }
+
+    /// Decodes `input` at `depth` of nesting.
+    fn decode_call(&self, input: &[u8], depth: usize) -> Option<DecodedCall> {
+        if depth > MAX_DEPTH || input.len() < 4 || (input.len() - 4) % 32 != 0 {
+            return None;
+        }
+        let (selector, data) = input.split_at(4);
+        self.functions.get(selector)?.iter().find_map(|function| {
+            let values = function.abi_decode_input(data).ok()?;
+            if function.abi_encode_input(&values).ok()? != input {
+                return None;
+            }
+            Some(self.decode_args(function, &values, depth))
+        })
+    }
+
+    /// Decodes the arguments of `function`.
+    fn decode_args(
+        &self,
+        function: &Function,
+        values: &[DynSolValue],
+        depth: usize,
+    ) -> DecodedCall {
+        let args = function
+            .inputs
+            .iter()
+            .zip(values)
+            .enumerate()
+            .map(|(i, (param, value))| {
+                let payload = self.payloads.get(&(function.selector(), param.name.clone()));
+                let decoded = payload
+                    .and_then(|payload| self.decode_payload(*payload, value, values, depth))
+                    .unwrap_or_else(|| self.decode_value(value, param, depth));
+                arg(param, i, decoded)
+            })
+            .collect();
+        DecodedCall { signature: function.signature(), args }
+    }
+
+    /// Decodes `value` of type `param`, decoding the `bytes` that contain calldata as calls.
+    fn decode_value(&self, value: &DynSolValue, param: &Param, depth: usize) -> DecodedValue {
+        match value {
+            DynSolValue::Bytes(bytes) => self.decode_call(bytes, depth + 1).map_or_else(
+                || DecodedValue::Value(hex::encode_prefixed(bytes)),
+                DecodedValue::Call,
+            ),
+            DynSolValue::Tuple(values) => DecodedValue::Tuple(
+                param
+                    .components
+                    .iter()
+                    .zip(values)
+                    .enumerate()
+                    .map(|(i, (param, value))| {
+                        arg(param, i, self.decode_value(value, param, depth))
+                    })
+                    .collect(),
+            ),
+            DynSolValue::Array(values) | DynSolValue::FixedArray(values) => DecodedValue::Array(
+                values.iter().map(|value| self.decode_value(value, param, depth)).collect(),
+            ),
+            value => DecodedValue::Value(format_value(value)),
+        }
+    }
+
+    /// Decodes a `bytes` argument in the format of `payload`, or returns `None` if it is
+    /// malformed.
+    fn decode_payload(
+        &self,
+        payload: Payload,
+        value: &DynSolValue,
+        args: &[DynSolValue],
+        depth: usize,
+    ) -> Option<DecodedValue> {
+        match payload {
+            Payload::MultiSendTransactions => {
+                let mut transactions = value.as_bytes()?;
+                let mut decoded = Vec::new();
+                while !transactions.is_empty() {
+                    let header = transactions.get(..85)?;
+                    let len = usize::try_from(U256::from_be_slice(&header[53..85])).ok()?;
+                    let data = transactions.get(85..85usize.checked_add(len)?)?;
+                    let data = self.decode_call(data, depth + 1).map_or_else(
+                        || DecodedValue::Value(hex::encode_prefixed(data)),
+                        DecodedValue::Call,
+                    );
+                    let field = |name: &str, ty: &str, value| DecodedArg {
+                        name: name.to_string(),
+                        ty: ty.to_string(),
+                        value,
+                    };
+                    decoded.push(DecodedValue::Tuple(vec![
+                        field("operation", "uint8", DecodedValue::Value(header[0].to_string())),
+                        field(
+                            "to",
+                            "address",
+                            DecodedValue::Value(Address::from_slice(&header[1..21]).to_string()),
+                        ),
+                        field(
+                            "value",
+                            "uint256",
+                            DecodedValue::Value(U256::from_be_slice(&header[21..53]).to_string()),
+                        ),
+                        field("data", "bytes", data),
+                    ]));
+                    transactions = &transactions[85 + len..];
+                }
+                Some(DecodedValue::Array(decoded))
+            }
+            Payload::UniversalRouterInputs => {
+                let commands = args.iter().find_map(DynSolValue::as_bytes)?;
+                let DynSolValue::Array(inputs) = value else { return None };
+                if commands.len() != inputs.len() {
+                    return None;
+                }
+                let decoded = commands
+                    .iter()
+                    .zip(inputs)
+                    .map(|(command, input)| {
+                        // The high bit allows the command to revert, the next one is reserved.
+                        let input = input.as_bytes().unwrap_or_default();
+                        let decoded = self.commands.get(&(command & 0x3f)).and_then(|function| {
+                            let values = function.abi_decode_input(input).ok()?;
+                            Some(self.decode_args(function, &values, depth + 1))
+                        });
+                        decoded.map_or_else(
+                            || DecodedValue::Value(hex::encode_prefixed(input)),
+                            DecodedValue::Call,
+                        )
+                    })
+                    .collect();
+                Some(DecodedValue::Array(decoded))
+            }
+        }
+    }
+}
+
+/// Returns the decoded argument for `param` at position `i`.
+fn arg(param: &Param, i: usize, value: DecodedValue) -> DecodedArg {
+    let name = if param.name.is_empty() { i.to_string() } else { param.name.clone() };
+    DecodedArg { name, ty: param.selector_type().into_owned(), value }
+}
+
+/// Formats a value that is not a tuple, an array or `bytes`.
+fn format_value(value: &DynSolValue) -> String {
+    match value {
+        DynSolValue::Address(address) => address.to_string(),
+        DynSolValue::Bool(bool) => bool.to_string(),
+        DynSolValue::Int(int, _) => int.to_string(),
+        DynSolValue::Uint(uint, _) => uint.to_string(),
+        DynSolValue::FixedBytes(word, size) => hex::encode_prefixed(&word[..*size]),
+        DynSolValue::String(string) => format!("{string:?}"),
+        DynSolValue::Function(function) => function.to_string(),
+        value => format!("{value:?}"),
+    }
+}
+
+impl DecodedCall {
+    /// Renders the call as an indented tree.
+    pub fn render(&self) -> String {
+        let mut out = format!("{}\n", self.signature);
+        render_args(&mut out, &self.args, 1);
+        out
+    }
+
+    /// Renders the call as JSON.
+    pub fn to_json(&self) -> Result<String> {
+        Ok(serde_json::to_string_pretty(self)?)
+    }
+}
+
+/// Renders `args` at `depth`.
+fn render_args(out: &mut String, args: &[DecodedArg], depth: usize) {
+    for arg in args {
+        render_value(out, &format!("{}: {}", arg.name, arg.ty), &arg.value, depth);
+    }
+}
+
+/// Renders `value` labeled `label` at `depth`, with its fields, elements or arguments below it.
+fn render_value(out: &mut String, label: &str, value: &DecodedValue, depth: usize) {
+    let indent = "  ".repeat(depth);
+    match value {
+        DecodedValue::Value(value) => {
+            let _ = writeln!(out, "{indent}{label} = {value}");
+        }
+        DecodedValue::Call(call) => {
+            let _ = writeln!(out, "{indent}{label} = {}", call.signature);
+            render_args(out, &call.args, depth + 1);
+        }
+        DecodedValue::Tuple(fields) => {
+            let _ = writeln!(out, "{indent}{label}");
+            render_args(out, fields, depth + 1);
+        }
+        DecodedValue::Array(values) => {
+            let _ = writeln!(out, "{indent}{label} ({} elements)", values.len());
+            for (i, value) in values.iter().enumerate() {
+                render_value(out, &format!("[{i}]"), value, depth + 1);
+            }
+        }
+    }
+}
+
+/// Packs `transactions` as the input of `MultiSend.multiSend`.
+fn pack_multi_send(transactions: &[(u8, Address, U256, Vec<u8>)]) -> Bytes {
+    let mut packed = Vec::new();
+    for (operation, to, value, data) in transactions {
+        packed.push(*operation);
+        packed.extend_from_slice(to.as_slice());
+        packed.extend_from_slice(&value.to_be_bytes::<32>());
+        packed.extend_from_slice(&U256::from(data.len()).to_be_bytes::<32>());
+        packed.extend_from_slice(data);
+    }
+    packed.into()
+}
+
+#[tokio::main]
+async fn main() -> Result<()> {
+    let weth = address!("C02aaA39b223FE8D0A0e5C4F27eAD9083C756Cc2");
+    let dai = address!("6B175474E89094C44Da98b954EedeAC495271d0F");
+    let uniswap_pair = address!("A478c2975Ab1Ea89e8196811F51A7B7Ade33eB11");
+    let sushi_pair = address!("C3D03e4F041Fd4cD388c549Ee2A29a9E5075882f");
+    let executor = address!("5FbDB2315678afecb367f032d93F642f64180aa3");
+    let multicall3 = address!("cA11bde05977b3631167028862bE2a173976CA11");
+    let multi_send = address!("38869bf66a61cF6bDB996A6aE40D5853Fd43B526");
+    let router = address!("66a9893cC07D91D95644AEDD05D03f95e1dBA8Af");
+    let safe = address!("d8dA6BF26964aF9D7eEd9e03E53415D37aA96045");
+
+    // The arbitrage of `simulation_uni_v2.rs`, with the swaps as payloads of `FlashBotsMultiCall`.
+    let swap = |amount0_out, amount1_out, to| {
+        let swap = IUniswapV2Pair::swapCall {
+            amount0Out: amount0_out,
+            amount1Out: amount1_out,
+            to,
+            data: Bytes::new(),
+        };
+        Bytes::from(swap.abi_encode())
+    };
+    let arbitrage = IFlashBotsMultiCall::uniswapWethCall {
+        _wethAmountToFirstMarket: U256::from(10).pow(U256::from(18)),
+        _ethAmountToCoinbase: U256::ZERO,
+        _targets: vec![uniswap_pair, sushi_pair],
+        _payloads: vec![
+            swap(U256::from(3_500_000_000u64), U256::ZERO, sushi_pair),
+            swap(U256::ZERO, U256::from(1_010_000_000u64), executor),
+        ],
+    }
+    .abi_encode();
+
+    // A Universal Router swap of ETH to DAI, which wraps the ETH first.
+    let swap_to_dai = IUniversalRouter::executeCall {
+        commands: vec![0x0b, 0x08].into(),
+        inputs: vec![
+            (Address::with_last_byte(2), U256::from(10).pow(U256::from(17))).abi_encode_params(),
+            (
+                Address::with_last_byte(1),
+                U256::from(10).pow(U256::from(17)),
+                U256::ZERO,
+                vec![weth, dai],
+                false,
+            )
+                .abi_encode_params(),
+        ]
+        .into_iter()
+        .map(Bytes::from)
+        .collect(),
+        deadline: U256::from(1_700_000_000),
+    }
+    .abi_encode();
+
+    // Both batched with `Multicall3`, after an approval, in a `MultiSend` executed by a Safe.
+    let batch = IMulticall3::aggregate3Call {
+        calls: vec![
+            IMulticall3::Call3 {
+                target: executor,
+                allowFailure: false,
+                callData: arbitrage.into(),
+            },
+            IMulticall3::Call3 { target: router, allowFailure: true, callData: swap_to_dai.into() },
+        ],
+    }
+    .abi_encode();
+    let approve = IERC20::approveCall { spender: router, amount: U256::MAX }.abi_encode();
+    let transactions = IMultiSend::multiSendCall {
+        transactions: pack_multi_send(&[
+            (0, weth, U256::ZERO, approve),
+            (0, multicall3, U256::from(10).pow(U256::from(17)), batch),
+        ]),
+    }
+    .abi_encode();
+    let input = ISafe::execTransactionCall {
+        to: multi_send,
+        value: U256::ZERO,
+        data: transactions.into(),
+        operation: 1,
+        safeTxGas: U256::ZERO,
+        baseGas: U256::ZERO,
+        gasPrice: U256::ZERO,
+        gasToken: Address::ZERO,
+        refundReceiver: Address::ZERO,
+        signatures: Bytes::from([safe.into_word().0.as_slice(), &[0; 32], &[1]].concat()),
+    }
+    .abi_encode();
+
+    // Register the ABIs, from `sol!` bindings and from a JSON file.
+    let mut decoder = NestedDecoder::new()?;
+    decoder.register_abi(&ISafe::abi::contract());
+    decoder.register_abi(&IMultiSend::abi::contract());
+    decoder.register_abi(&IMulticall3::abi::contract());
+    decoder.register_abi(&IUniversalRouter::abi::contract());
+    decoder.register_abi(&IERC20::abi::contract());
+    decoder.register_abi(&IUniswapV2Pair::abi::contract());
+    let path =
+        std::env::current_dir()?.join("examples/advanced/examples/abi/FlashBotsMultiCall.json");
+    let artifact: ContractObject = serde_json::from_str(&std::fs::read_to_string(path)?)?;
+    decoder.register_abi(&artifact.abi.unwrap_or_default());
+    decoder.register_payload(
+        IMultiSend::multiSendCall::SELECTOR.into(),
+        "transactions",
+        Payload::MultiSendTransactions,
+    );
+    decoder.register_payload(
+        IUniversalRouter::executeCall::SELECTOR.into(),
+        "inputs",
+        Payload::UniversalRouterInputs,
+    );
+
+    let call = decoder.decode(&input).ok_or_else(|| eyre::eyre!("unknown calldata"))?;
+    let rendered = call.render();
+    println!("{rendered}");
+
+    // Every level is decoded, down to the swaps of the arbitrage and the router commands.
+    for expected in [
+        "data: bytes = multiSend(bytes)",
+        "data: bytes = aggregate3((address,bool,bytes)[])",
+        "callData: bytes = uniswapWeth(uint256,uint256,address[],bytes[])",
+        "[1] = swap(uint256,uint256,address,bytes)",
+        "[0] = WRAP_ETH(address,uint256)",
+        "[1] = V2_SWAP_EXACT_IN(address,uint256,uint256,address[],bool)",
+    ] {
+        assert!(rendered.contains(expected), "missing `{expected}`");
+    }
+    // `bytes` that are not calldata, such as the signatures or empty data, are left as is.
+    assert!(rendered.contains("signatures: bytes = 0x000000000000000000000000d8da6bf2"));
+    assert!(rendered.contains("data: bytes = 0x\n"));
+
+    let json = call.to_json()?;
+    println!("{json}");
+    let json: serde_json::Value = serde_json::from_str(&json)?;
+    assert_eq!(
+        json["args"][2]["value"]["call"]["signature"], "multiSend(bytes)",
+        "the JSON has the same tree"
+    );
+
+    Ok(())
+}
@@ -0,0 +1,489 @@
+//! Example of a differential tester replaying transactions on Anvil and on a local `revm` fork.
+//!
+//! The transactions are first mined by Anvil, whose receipts, logs and storage changes, taken from
+//! the `prestateTracer` of `debug_traceTransaction`, serve as the reference. They are then replayed
+//! on a `foundry_fork_db` fork of the block Anvil started from, with an EVM configured by the
+//! function under test for each block Anvil mined, as `configure_evm` of `foundry_fork_db.rs`
+//! does. Every difference in status, gas used, created address, logs or storage is reported as a
+//! divergence, which catches configuration mistakes such as a wrong hardfork or chain id.
+
+use std::{
+    collections::{BTreeMap, BTreeSet},
+    fmt,
+    sync::Arc,
+};
+
+use alloy::{
+    consensus::BlockHeader,
+    eips::BlockId,
+    network::{
+        AnyNetwork, AnyRpcBlock, AnyTransactionReceipt, ReceiptResponse, TransactionBuilder,
+    },
+    node_bindings::Anvil,
+    primitives::{Address, Log, TxHash, U256},
+    providers::{ext::DebugApi, Provider, ProviderBuilder},
+    rpc::types::{
+        trace::geth::{
+            DiffMode, GethDebugTracingOptions, GethTrace, PreStateConfig, PreStateFrame,
+        },
+        TransactionRequest,
+    },
+    sol,
+    sol_types::SolCall,
+};
+use alloy_evm::{eth::EthEvmContext, EthEvm, Evm, FromRecoveredTx};
+use eyre::{OptionExt, Result};
+use foundry_fork_db::{cache::BlockchainDbMeta, BlockchainDb, SharedBackend};
+use revm::{
+    context::{BlockEnv, CfgEnv, Evm as RevmEvm, TxEnv},
+    context_interface::{
+        block::BlobExcessGasAndPrice,
+        result::{ExecutionResult, Output},
+    },
+    database::{CacheDB, WrapDatabaseRef},
+    handler::{instructions::EthInstructions, EthPrecompiles},
+    inspector::NoOpInspector,
+    primitives::hardfork::SpecId,
+    state::EvmState,
+    DatabaseCommit,
+};
+
+sol! {
+    #[allow(missing_docs)]
+    // solc v0.8.26; solc Counter.sol --via-ir --optimize --bin
+    #[sol(bytecode="6080806040523460135760df908160198239f35b600080fdfe6080806040526004361015601257600080fd5b60003560e01c9081633fb5c1cb1460925781638381f58a146079575063d09de08a14603c57600080fd5b3460745760003660031901126074576000546000198114605e57600101600055005b634e487b7160e01b600052601160045260246000fd5b600080fd5b3460745760003660031901126074576020906000548152f35b34607457602036600319011260745760043560005500fea2646970667358221220e978270883b7baed10810c4079c941512e93a7ba1cd1108c781d4bc738d9090564736f6c634300081a0033")]
+    contract Counter {
+        uint256 public number;
+
+        function setNumber(uint256 newNumber) public {
+            number = newNumber;
+        }
+
+        function increment() public {
+            number++;
+        }
+    }
+}
+
+sol! {
+    #[allow(missing_docs)]
+    // solc v0.8.26; solc EventMultiplexer.sol --via-ir --optimize --bin
+    #[sol(bytecode = "60808060405234601557610207908161001b8239f35b600080fdfe6080604052600436101561001257600080fd5b60003560e01c80634350913814610156578063a5f3c23b14610108578063adefc37b146100ba5763bbe93d911461004857600080fd5b346100b557610056366101bb565b818102919060008212600160ff1b82141661009f57818305149015171561009f57337fd7a123d4c8e44db3186e04b9c96c102287276929c930f2e8abcaa555ef5dcacc600080a3005b634e487b7160e01b600052601160045260246000fd5b600080fd5b346100b5576100c8366101bb565b906000828203921281831281169183139015161761009f57337f32e913bf2ad35da1e845597618bb9f3f80642a68dd39f30a093a7838aa61fb27600080a3005b346100b557610116366101bb565b906000828201928312911290801582169115161761009f57337f6da406ea462447ed7804b4a4dc69c67b53d3d45a50381ae3e9cf878c9d7c23df600080a3005b346100b557610164366101bb565b9081156101a557600160ff1b811460001983141661009f5705337f1c1e8bbe327890ea8d3f5b22370a56c3fcef7ff82f306161f64647fe5d285881600080a3005b634e487b7160e01b600052601260045260246000fd5b60409060031901126100b557600435906024359056fea2646970667358221220d876fbacf1e90fc174532f3525420c446351b467f788f9d7a726a7d55045909664736f6c634300081a0033")]
+    contract EventMultiplexer {
+        event Add(address indexed sender, int256 indexed value);
+        event Sub(address indexed sender, int256 indexed value);
+        event Mul(address indexed sender, int256 indexed value);
+        event Div(address indexed sender, int256 indexed value);
+
+        function add(int256 a, int256 b) public {
+            emit Add(msg.sender, a + b);
+        }
+
+        function sub(int256 a, int256 b) public {
+            emit Sub(msg.sender, a - b);
+        }
+
+        function mul(int256 a, int256 b) public {
+            emit Mul(msg.sender, a * b);
+        }
+
+        function div(int256 a, int256 b) public {
+            emit Div(msg.sender, a / b);
+        }
+    }
+}
+
+/// The database of the local fork.
+pub type ForkDb = CacheDB<WrapDatabaseRef<SharedBackend>>;
+
+/// The outcome of a transaction on one of the backends.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct Outcome {
+    /// Whether the transaction succeeded.
+    pub success: bool,
+    /// The gas used by the transaction.
+    pub gas_used: u64,
+    /// The address of the contract created by the transaction.
+    pub contract_address: Option<Address>,
+    /// The logs emitted by the transaction.
+    pub logs: Vec<Log>,
+    /// The new values of the storage slots changed by the transaction.
+    pub storage: BTreeMap<(Address, U256), U256>,
+}
+
+impl Outcome {
+    /// Returns the outcome of a transaction mined with `receipt`, which changed the storage as
+    /// reported by the `prestateTracer` in diff mode.
+    pub fn from_receipt(receipt: &AnyTransactionReceipt, diff: &DiffMode) -> Self {
+        let mut storage = BTreeMap::new();
+        for (address, account) in &diff.post {
+            for (slot, value) in &account.storage {
+                storage.insert((*address, (*slot).into()), (*value).into());
+            }
+        }
+        // Slots cleared by the transaction are only listed in the pre-state.
+        for (address, account) in &diff.pre {
+            for slot in account.storage.keys() {
+                storage.entry((*address, (*slot).into())).or_insert(U256::ZERO);
+            }
+        }
+
+        Self {
+            success: receipt.status(),
+            gas_used: receipt.gas_used,
+            contract_address: receipt.contract_address,
+            logs: receipt.inner.inner.logs().iter().map(|log| log.inner.clone()).collect(),
+            storage,
+        }
+    }
+
+    /// Returns the outcome of a transaction executed by `revm` with `result`, which changed the
+    /// accounts in `state`.
+    pub fn from_result(result: &ExecutionResult, state: &EvmState) -> Self {
+        let storage = state
+            .iter()
+            .flat_map(|(address, account)| {
+                account
+                    .changed_storage_slots()
+                    .map(move |(slot, value)| ((*address, *slot), value.present_value))
+            })
+            .collect();
+        let contract_address = match result {
+            ExecutionResult::Success { output: Output::Create(_, address), .. } => *address,
+            _ => None,
+        };
+
+        Self {
+            success: result.is_success(),
+            gas_used: result.gas_used(),
+            contract_address,
+            logs: result.logs().to_vec(),
+            storage,
+        }
+    }
+
+    /// Returns the divergences of the `revm` outcome from this Anvil outcome.
+    pub fn diff(&self, revm: &Self) -> Vec<Divergence> {
+        let mut divergences = Vec::new();
+        if self.success != revm.success {
+            divergences.push(Divergence::Status { anvil: self.success, revm: revm.success });
+        }
+        if self.gas_used != revm.gas_used {
+            divergences.push(Divergence::GasUsed { anvil: self.gas_used, revm: revm.gas_used });
+        }
+        if self.contract_address != revm.contract_address {
+            divergences.push(Divergence::ContractAddress {
+                anvil: self.contract_address,
+                revm: revm.contract_address,
+            });
+        }
+        if self.logs.len() != revm.logs.len() {
+            divergences
+                .push(Divergence::LogCount { anvil: self.logs.len(), revm: revm.logs.len() });
+        }
+        for (index, (anvil, revm)) in self.logs.iter().zip(&revm.logs).enumerate() {
+            if anvil != revm {
+                divergences.push(Divergence::Log {
+                    index,
+                    anvil: anvil.clone(),
+                    revm: revm.clone(),
+                });
+            }
+        }
+        let slots: BTreeSet<_> = self.storage.keys().chain(revm.storage.keys()).collect();
+        for (address, slot) in slots {
+            let key = (*address, *slot);
+            let (anvil, revm) = (self.storage.get(&key).copied(), revm.storage.get(&key).copied());
+            if anvil != revm {
+                divergences.push(Divergence::Storage {
+                    address: *address,
+                    slot: *slot,
+                    anvil,
+                    revm,
+                });
+            }
+        }
+        divergences
+    }
+}
+
+/// A difference between the outcomes of a transaction on Anvil and on `revm`.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Divergence {
+    /// `revm` rejected the transaction, which Anvil mined.
+    Rejected {
+        /// The error of `revm`.
+        error: String,
+    },
+    /// The transaction succeeded on one backend only.
+    Status {
+        /// Whether the transaction succeeded on Anvil.
+        anvil: bool,
+        /// Whether the transaction succeeded on `revm`.
+        revm: bool,
+    },
+    /// The gas used differs.
+    GasUsed {
+        /// The gas used on Anvil.
+        anvil: u64,
+        /// The gas used on `revm`.
+        revm: u64,
+    },
+    /// The created contract differs.
+    ContractAddress {
+        /// The contract created on Anvil.
+        anvil: Option<Address>,
+        /// The contract created on `revm`.
+        revm: Option<Address>,
+    },
+    /// The number of logs differs.
+    LogCount {
+        /// The number of logs on Anvil.
+        anvil: usize,
+        /// The number of logs on `revm`.
+        revm: usize,
+    },
+    /// A log differs.
+    Log {
+        /// The index of the log in the transaction.
+        index: usize,
+        /// The log on Anvil.
+        anvil: Log,
+        /// The log on `revm`.
+        revm: Log,
+    },
+    /// A storage slot was changed differently, or changed on one backend only.
+    Storage {
+        /// The account of the slot.
+        address: Address,
+        /// The slot.
+        slot: U256,
+        /// The new value on Anvil, if changed.
+        anvil: Option<U256>,
+        /// The new value on `revm`, if changed.
+        revm: Option<U256>,
+    },
+}
+
+impl fmt::Display for Divergence {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Rejected { error } => write!(f, "rejected by revm: {error}"),
+            Self::Status { anvil, revm } => {
+                write!(f, "success: anvil {anvil}, revm {revm}")
+            }
+            Self::GasUsed { anvil, revm } => write!(f, "gas used: anvil {anvil}, revm {revm}"),
+            Self::ContractAddress { anvil, revm } => {
+                write!(f, "contract address: anvil {anvil:?}, revm {revm:?}")
+            }
+            Self::LogCount { anvil, revm } => write!(f, "logs: anvil {anvil}, revm {revm}"),
+            Self::Log { index, anvil, revm } => {
+                write!(f, "log {index}: anvil {anvil:?}, revm {revm:?}")
+            }
+            Self::Storage { address, slot, anvil, revm } => {
+                write!(f, "storage {address} [{slot:#x}]: anvil {anvil:?}, revm {revm:?}")
+            }
+        }
+    }
+}
+
+/// A transaction mined by Anvil, with its outcome.
+#[derive(Debug)]
+struct MinedTx {
+    hash: TxHash,
+    tx_env: TxEnv,
+    block: AnyRpcBlock,
+    outcome: Outcome,
+}
+
+/// The divergences of a replayed transaction.
+#[derive(Debug)]
+pub struct TxReport {
+    /// The hash of the transaction on Anvil.
+    pub hash: TxHash,
+    /// The divergences of `revm` from Anvil.
+    pub divergences: Vec<Divergence>,
+}
+
+/// Replays the transactions mined by Anvil on a local `revm` fork and compares the outcomes.
+#[derive(Debug)]
+pub struct DifferentialTester<P> {
+    provider: P,
+    start_block: AnyRpcBlock,
+    txs: Vec<MinedTx>,
+}
+
+impl<P> DifferentialTester<P>
+where
+    P: Provider<AnyNetwork> + Unpin + 'static + Clone,
+{
+    /// Mines `txs` on the Anvil node of `provider`, one block per transaction, recording the
+    /// outcomes to compare against.
+    pub async fn record(provider: P, txs: Vec<TransactionRequest>) -> Result<Self> {
+        let start_block =
+            provider.get_block(BlockId::latest()).await?.ok_or_eyre("missing start block")?;
+        let prestate_diff = GethDebugTracingOptions::prestate_tracer(PreStateConfig {
+            diff_mode: Some(true),
+            ..Default::default()
+        });
+
+        let mut mined = Vec::new();
+        for tx in txs {
+            let receipt = provider.send_transaction(tx.into()).await?.get_receipt().await?;
+            let hash = receipt.transaction_hash;
+
+            // Replay the transaction as Anvil filled and mined it.
+            let tx = provider.get_transaction_by_hash(hash).await?.ok_or_eyre("missing tx")?;
+            let envelope = tx.as_envelope().ok_or_eyre("unsupported tx type")?;
+            let tx_env = TxEnv::from_recovered_tx(envelope, tx.inner.inner.signer());
+
+            let number = receipt.block_number.ok_or_eyre("missing block number")?;
+            let block =
+                provider.get_block(BlockId::number(number)).await?.ok_or_eyre("missing block")?;
+
+            let trace = provider.debug_trace_transaction(hash, prestate_diff.clone()).await?;
+            let GethTrace::PreStateTracer(PreStateFrame::Diff(diff)) = trace else {
+                eyre::bail!("unexpected trace for {hash}");
+            };
+
+            let outcome = Outcome::from_receipt(&receipt, &diff);
+            mined.push(MinedTx { hash, tx_env, block, outcome });
+        }
+
+        Ok(Self { provider, start_block, txs: mined })
+    }
+
+    /// Replays the recorded transactions on a fork of the start block, executing each with the
+    /// EVM returned by `configure_evm` for its block, and returns the divergences.
+    pub async fn replay<F>(&self, configure_evm: F) -> Result<Vec<TxReport>>
+    where
+        F: Fn(&AnyRpcBlock, ForkDb) -> EthEvm<ForkDb, NoOpInspector>,
+    {
+        let meta = BlockchainDbMeta::default().with_block(&self.start_block.inner);
+        let number = BlockId::number(self.start_block.header.number());
+        let shared = SharedBackend::spawn_backend(
+            Arc::new(self.provider.clone()),
+            BlockchainDb::new(meta, None),
+            Some(number),
+        )
+        .await;
+        let mut db = CacheDB::new(WrapDatabaseRef(shared));
+
+        let mut reports = Vec::new();
+        for mined in &self.txs {
+            let mut evm = configure_evm(&mined.block, db);
+            let result = evm.transact(mined.tx_env.clone());
+            db = evm.into_db();
+
+            let divergences = match result {
+                Ok(result) => {
+                    let outcome = Outcome::from_result(&result.result, &result.state);
+                    db.commit(result.state);
+                    mined.outcome.diff(&outcome)
+                }
+                Err(err) => vec![Divergence::Rejected { error: err.to_string() }],
+            };
+            reports.push(TxReport { hash: mined.hash, divergences });
+        }
+        Ok(reports)
+    }
+}
+
+/// Configures an `EthEvm` for `block` of the chain `chain_id` under the hardfork `spec`, as
+/// `configure_evm` of `foundry_fork_db.rs` does.
+fn configure_evm(
+    block: &AnyRpcBlock,
+    db: ForkDb,
+    chain_id: u64,
+    spec: SpecId,
+) -> EthEvm<ForkDb, NoOpInspector> {
+    let block_env = BlockEnv {
+        number: U256::from(block.header.number()),
+        beneficiary: block.header.beneficiary(),
+        timestamp: U256::from(block.header.timestamp()),
+        gas_limit: block.header.gas_limit(),
+        basefee: block.header.base_fee_per_gas().unwrap_or(0),
+        prevrandao: block.header.mix_hash(),
+        difficulty: block.header.difficulty(),
+        blob_excess_gas_and_price: Some(BlobExcessGasAndPrice::new_with_spec(
+            block.header.excess_blob_gas().unwrap_or_default(),
+            spec,
+        )),
+    };
+
+    let context = EthEvmContext::new(db, spec)
+        .with_block(block_env)
+        .with_cfg(CfgEnv::new_with_spec(spec).with_chain_id(chain_id));
+
+    let evm = RevmEvm::new(context, EthInstructions::default(), EthPrecompiles::default())
+        .with_inspector(NoOpInspector);
+
+    EthEvm::new(evm, false)
+}
+
+/// Prints the divergences of `reports`, returning their number.
+fn print_reports(reports: &[TxReport]) -> usize {
+    for report in reports {
+        if report.divergences.is_empty() {
+            println!("{}: ok", report.hash);
+        }
+        for divergence in &report.divergences {
+            println!("{}: {divergence}", report.hash);
+        }
+    }
+    reports.iter().map(|report| report.divergences.len()).sum()
+}
+
+#[tokio::main]
+async fn main() -> Result<()> {
+    // Pin the hardfork, which the correct configuration has to match.
+    let anvil = Anvil::new().args(["--hardfork", "prague"]).spawn();
+    let provider =
+        ProviderBuilder::new().network::<AnyNetwork>().connect_http(anvil.endpoint_url());
+    let chain_id = provider.get_chain_id().await?;
+
+    let alice = anvil.addresses()[0];
+    let bob = anvil.addresses()[1];
+    let counter = alice.create(0);
+    let multiplexer = alice.create(1);
+
+    let call = |to: Address, input: Vec<u8>| {
+        TransactionRequest::default().with_from(alice).with_to(to).with_input(input)
+    };
+    let txs = vec![
+        TransactionRequest::default().with_from(alice).with_deploy_code(Counter::BYTECODE.clone()),
+        TransactionRequest::default()
+            .with_from(alice)
+            .with_deploy_code(EventMultiplexer::BYTECODE.clone()),
+        call(counter, Counter::setNumberCall { newNumber: U256::from(42) }.abi_encode()),
+        call(counter, Counter::incrementCall {}.abi_encode()),
+        call(
+            multiplexer,
+            EventMultiplexer::addCall { a: 1.try_into()?, b: 2.try_into()? }.abi_encode(),
+        ),
+        // Dividing by zero panics, so the gas limit is set to skip the estimation.
+        call(
+            multiplexer,
+            EventMultiplexer::divCall { a: 1.try_into()?, b: 0.try_into()? }.abi_encode(),
+        )
+        .with_gas_limit(100_000),
+        TransactionRequest::default().with_from(alice).with_to(bob).with_value(U256::from(100)),
+    ];
+
+    let tester = DifferentialTester::record(provider, txs).await?;
+
+    println!("-------prague--------");
+    let reports =
+        tester.replay(|block, db| configure_evm(block, db, chain_id, SpecId::PRAGUE)).await?;
+    assert_eq!(print_reports(&reports), 0);
+
+    // Before Shanghai, the init code of contract creations is not charged (EIP-3860), so the gas
+    // used by the deployments diverges.
+    println!("\n-------london--------");
+    let reports =
+        tester.replay(|block, db| configure_evm(block, db, chain_id, SpecId::LONDON)).await?;
+    assert!(print_reports(&reports) > 0);
+    assert!(matches!(reports[0].divergences[..], [Divergence::GasUsed { .. }, ..]));
+
+    Ok(())
+}
@@ -56,6 +56,10 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
     print_tuple(&decoded_message, &["from", "to", "contents"]);
 
     // Calculate EIP-712 hash
+    //
+    // Note: for brevity this hashes the plain ABI encodings, without the type hashes and the
+    // hashing of dynamic fields of `hashStruct`, so it does not match `eth_signTypedData_v4`.
+    // See `sign_typed_data_json.rs` for spec-compliant hashing of typed data.
     let domain_separator = keccak256(&encoded_domain);
     let message_hash = keccak256(&encoded_message);
     let eip712_hash = keccak256([&[0x19, 0x01], &domain_separator[..], &message_hash[..]].concat());
@@ -0,0 +1,447 @@
+//! Example of a local transaction simulator built on a `foundry_fork_db` fork and `alloy-evm`.
+//!
+//! The `Simulator` maps every field of a `TransactionRequest` to a `TxEnv`, picks the `SpecId`
+//! from the chain and the forked block, and executes sequences of transactions, such as bundles,
+//! on top of the forked state. The changes of a sequence are either committed to the local state of
+//! the simulator, so that later sequences build on them, or rolled back. Nothing is ever sent to
+//! the node, which is only used to fetch the state that is missing from the fork db.
+
+use std::{error::Error, fmt, sync::Arc};
+
+use alloy::{
+    consensus::{BlockHeader, Receipt, TxType},
+    eips::BlockId,
+    network::{AnyNetwork, TransactionBuilder},
+    node_bindings::Anvil,
+    primitives::{Address, Bytes, Log, TxKind, U256},
+    providers::{Provider, ProviderBuilder},
+    rpc::types::TransactionRequest,
+    sol,
+    sol_types::SolCall,
+};
+use alloy_evm::{eth::spec::EthSpec, EthEvmFactory, Evm, EvmEnv, EvmFactory};
+use eyre::{OptionExt, Result};
+use foundry_fork_db::{cache::BlockchainDbMeta, BlockchainDb, SharedBackend};
+use revm::{
+    context::{BlockEnv, CfgEnv, TxEnv},
+    context_interface::{
+        block::BlobExcessGasAndPrice,
+        result::{ExecutionResult, HaltReason, Output, ResultAndState},
+        Transaction,
+    },
+    database::CacheDB,
+    primitives::hardfork::SpecId,
+    DatabaseCommit, DatabaseRef,
+};
+
+sol! {
+    #[allow(missing_docs)]
+    // solc v0.8.26; solc Counter.sol --via-ir --optimize --bin
+    #[sol(bytecode="6080806040523460135760df908160198239f35b600080fdfe6080806040526004361015601257600080fd5b60003560e01c9081633fb5c1cb1460925781638381f58a146079575063d09de08a14603c57600080fd5b3460745760003660031901126074576000546000198114605e57600101600055005b634e487b7160e01b600052601160045260246000fd5b600080fd5b3460745760003660031901126074576020906000548152f35b34607457602036600319011260745760043560005500fea2646970667358221220e978270883b7baed10810c4079c941512e93a7ba1cd1108c781d4bc738d9090564736f6c634300081a0033")]
+    contract Counter {
+        uint256 public number;
+
+        function setNumber(uint256 newNumber) public {
+            number = newNumber;
+        }
+
+        function increment() public {
+            number++;
+        }
+    }
+}
+
+/// Returns the `SpecId` active at `header` on the chain with `chain_id`.
+///
+/// The hardfork schedules of Ethereum mainnet, Sepolia and Holesky are known. For other chains,
+/// such as Anvil or a devnet, the latest hardfork is derived from the fields of the header, which
+/// identify every hardfork from London to Prague.
+pub fn spec_id<H: BlockHeader>(chain_id: u64, header: &H) -> SpecId {
+    let schedule = match chain_id {
+        1 => Some(EthSpec::mainnet()),
+        11_155_111 => Some(EthSpec::sepolia()),
+        17_000 => Some(EthSpec::holesky()),
+        _ => None,
+    };
+    if let Some(schedule) = schedule {
+        return alloy_evm::spec(&schedule, header);
+    }
+
+    if header.requests_hash().is_some() {
+        SpecId::PRAGUE
+    } else if header.excess_blob_gas().is_some() || header.parent_beacon_block_root().is_some() {
+        SpecId::CANCUN
+    } else if header.withdrawals_root().is_some() {
+        SpecId::SHANGHAI
+    } else if header.base_fee_per_gas().is_some() && header.difficulty().is_zero() {
+        SpecId::MERGE
+    } else if header.base_fee_per_gas().is_some() {
+        SpecId::LONDON
+    } else {
+        SpecId::BERLIN
+    }
+}
+
+/// Returns the `BlockEnv` of `header` under `spec`.
+pub fn block_env<H: BlockHeader>(header: &H, spec: SpecId) -> BlockEnv {
+    BlockEnv {
+        number: U256::from(header.number()),
+        beneficiary: header.beneficiary(),
+        timestamp: U256::from(header.timestamp()),
+        gas_limit: header.gas_limit(),
+        basefee: header.base_fee_per_gas().unwrap_or_default(),
+        difficulty: header.difficulty(),
+        // After the merge, `mixHash` holds the `prevrandao` value of the beacon chain.
+        prevrandao: spec.is_enabled_in(SpecId::MERGE).then(|| header.mix_hash()).flatten(),
+        blob_excess_gas_and_price: header
+            .excess_blob_gas()
+            .map(|excess| BlobExcessGasAndPrice::new_with_spec(excess, spec)),
+    }
+}
+
+/// Whether the changes of a simulated sequence of transactions are kept.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum SimulationMode {
+    /// Keep the changes, so that later simulations run on top of them.
+    Commit,
+    /// Discard the changes once the receipts have been produced.
+    Rollback,
+}
+
+/// An error raised while simulating a sequence of transactions.
+///
+/// The changes of a sequence that fails are always rolled back.
+#[derive(Debug)]
+pub enum SimulationError {
+    /// The transaction at `index` has no sender.
+    MissingSender {
+        /// The index of the transaction in the sequence.
+        index: usize,
+    },
+    /// The transaction at `index` is invalid, e.g. its nonce is wrong or its sender cannot pay for
+    /// it, or its state could not be fetched.
+    Invalid {
+        /// The index of the transaction in the sequence.
+        index: usize,
+        /// The error raised by the EVM.
+        source: Box<dyn Error + Send + Sync>,
+    },
+}
+
+impl fmt::Display for SimulationError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::MissingSender { index } => write!(f, "transaction {index} has no sender"),
+            Self::Invalid { index, source } => {
+                write!(f, "transaction {index} is invalid: {source}")
+            }
+        }
+    }
+}
+
+impl Error for SimulationError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            Self::MissingSender { .. } => None,
+            Self::Invalid { source, .. } => Some(source.as_ref()),
+        }
+    }
+}
+
+/// The receipt of a simulated transaction.
+#[derive(Clone, Debug)]
+pub struct SimulatedReceipt {
+    /// The status, cumulative gas used and logs, as in a consensus receipt.
+    pub inner: Receipt,
+    /// The type of the transaction.
+    pub tx_type: u8,
+    /// The gas used by the transaction, after refunds.
+    pub gas_used: u64,
+    /// The gas refunded to the sender.
+    pub gas_refunded: u64,
+    /// The price paid per unit of gas, including the priority fee.
+    pub effective_gas_price: u128,
+    /// The address of the created contract, if any.
+    pub contract_address: Option<Address>,
+    /// The returned data, or the revert data if the transaction reverted.
+    pub output: Bytes,
+    /// Why the transaction halted, if it did.
+    pub halt_reason: Option<HaltReason>,
+}
+
+impl SimulatedReceipt {
+    /// Returns whether the transaction succeeded.
+    pub const fn status(&self) -> bool {
+        self.inner.status.coerce_status()
+    }
+
+    /// Returns the logs emitted by the transaction.
+    pub fn logs(&self) -> &[Log] {
+        &self.inner.logs
+    }
+}
+
+/// Simulates transactions locally on top of the state of `DB`, usually a forked `SharedBackend`.
+#[derive(Clone, Debug)]
+pub struct Simulator<DB> {
+    db: CacheDB<DB>,
+    env: EvmEnv,
+}
+
+impl Simulator<SharedBackend> {
+    /// Forks the chain of `provider` at `block`.
+    ///
+    /// The state is fetched lazily from `provider` and cached in the fork db.
+    pub async fn fork<P>(provider: P, block: BlockId) -> Result<Self>
+    where
+        P: Provider<AnyNetwork> + Unpin + Clone + 'static,
+    {
+        let chain_id = provider.get_chain_id().await?;
+        let block = provider.get_block(block).await?.ok_or_eyre("block not found")?;
+
+        let meta = BlockchainDbMeta::default().with_block(&block.inner);
+        let db = BlockchainDb::new(meta, None);
+        let pin = BlockId::number(block.header.number());
+        let backend = SharedBackend::spawn_backend(Arc::new(provider), db, Some(pin)).await;
+
+        Ok(Self::new(backend, chain_id, &block.header))
+    }
+}
+
+impl<DB> Simulator<DB>
+where
+    DB: DatabaseRef + Clone + fmt::Debug,
+    DB::Error: Error + Send + Sync + 'static,
+{
+    /// Creates a simulator over `db` for the block after `header` on the chain with `chain_id`.
+    ///
+    /// The transactions are executed in the block environment of `header`, as `eth_call` does for
+    /// the block they are called on.
+    pub fn new<H: BlockHeader>(db: DB, chain_id: u64, header: &H) -> Self {
+        let spec = spec_id(chain_id, header);
+        let cfg_env = CfgEnv::new_with_spec(spec).with_chain_id(chain_id);
+        let env = EvmEnv { cfg_env, block_env: block_env(header, spec) };
+        Self { db: CacheDB::new(db), env }
+    }
+
+    /// Returns the EVM environment of the simulations.
+    pub const fn env(&self) -> &EvmEnv {
+        &self.env
+    }
+
+    /// Returns the `SpecId` of the simulations.
+    pub const fn spec(&self) -> SpecId {
+        self.env.cfg_env.spec
+    }
+
+    /// Returns the local state, with the committed changes on top of the fork.
+    pub const fn db(&self) -> &CacheDB<DB> {
+        &self.db
+    }
+
+    /// Converts `tx` into a `TxEnv` on top of the local state.
+    pub fn tx_env(&self, tx: &TransactionRequest) -> Result<TxEnv, SimulationError> {
+        to_tx_env(&self.env, &self.db, 0, tx)
+    }
+
+    /// Executes `txs` in order, each on top of the changes of the previous ones, and returns their
+    /// receipts.
+    ///
+    /// Reverted and halted transactions are included, as they would be on chain, while invalid
+    /// transactions abort the sequence.
+    pub fn simulate(
+        &mut self,
+        txs: &[TransactionRequest],
+        mode: SimulationMode,
+    ) -> Result<Vec<SimulatedReceipt>, SimulationError> {
+        let mut evm = EthEvmFactory::default().create_evm(self.db.clone(), self.env.clone());
+        let basefee = u128::from(self.env.block_env.basefee);
+
+        let mut receipts = Vec::with_capacity(txs.len());
+        let mut cumulative_gas_used = 0;
+        for (index, tx) in txs.iter().enumerate() {
+            let tx_env = to_tx_env(&self.env, evm.db(), index, tx)?;
+            let tx_type = tx_env.tx_type;
+            let effective_gas_price = tx_env.effective_gas_price(basefee);
+
+            let ResultAndState { result, state } = evm
+                .transact(tx_env)
+                .map_err(|err| SimulationError::Invalid { index, source: Box::new(err) })?;
+            evm.db_mut().commit(state);
+
+            cumulative_gas_used += result.gas_used();
+            let (gas_refunded, contract_address, output, halt_reason) = match &result {
+                ExecutionResult::Success { gas_refunded, output, .. } => {
+                    let contract_address = match output {
+                        Output::Create(_, address) => *address,
+                        Output::Call(_) => None,
+                    };
+                    (*gas_refunded, contract_address, output.data().clone(), None)
+                }
+                ExecutionResult::Revert { output, .. } => (0, None, output.clone(), None),
+                ExecutionResult::Halt { reason, .. } => {
+                    (0, None, Bytes::new(), Some(reason.clone()))
+                }
+            };
+            receipts.push(SimulatedReceipt {
+                inner: Receipt {
+                    status: result.is_success().into(),
+                    cumulative_gas_used,
+                    logs: result.logs().to_vec(),
+                },
+                tx_type,
+                gas_used: result.gas_used(),
+                gas_refunded,
+                effective_gas_price,
+                contract_address,
+                output,
+                halt_reason,
+            });
+        }
+
+        if mode == SimulationMode::Commit {
+            self.db = evm.into_db();
+        }
+        Ok(receipts)
+    }
+}
+
+/// Converts `tx`, at `index` in its sequence, into a `TxEnv` on top of `db`.
+///
+/// The fields left empty are filled the way a node fills them for `eth_call`: the nonce is the
+/// current nonce of the sender, the gas limit is the block gas limit, the fees default to the base
+/// fee without a tip, and the chain id is the one of the simulator.
+fn to_tx_env<D: DatabaseRef>(
+    env: &EvmEnv,
+    db: &D,
+    index: usize,
+    tx: &TransactionRequest,
+) -> Result<TxEnv, SimulationError>
+where
+    D::Error: Error + Send + Sync + 'static,
+{
+    let caller = tx.from.ok_or(SimulationError::MissingSender { index })?;
+    let nonce = match tx.nonce {
+        Some(nonce) => nonce,
+        None => db
+            .basic_ref(caller)
+            .map_err(|err| SimulationError::Invalid { index, source: Box::new(err) })?
+            .map(|account| account.nonce)
+            .unwrap_or_default(),
+    };
+
+    let tx_type = tx.transaction_type.unwrap_or_else(|| tx.preferred_type() as u8);
+    let basefee = u128::from(env.block_env.basefee);
+    let (gas_price, gas_priority_fee) = if tx_type < TxType::Eip1559 as u8 {
+        (tx.gas_price.unwrap_or(basefee), None)
+    } else {
+        (
+            tx.max_fee_per_gas.or(tx.gas_price).unwrap_or(basefee),
+            Some(tx.max_priority_fee_per_gas.unwrap_or_default()),
+        )
+    };
+
+    let mut tx_env = TxEnv {
+        tx_type,
+        caller,
+        gas_limit: tx.gas.unwrap_or(env.block_env.gas_limit),
+        gas_price,
+        kind: tx.to.unwrap_or(TxKind::Create),
+        value: tx.value.unwrap_or_default(),
+        data: tx.input.input().cloned().unwrap_or_default(),
+        nonce,
+        chain_id: Some(tx.chain_id.unwrap_or(env.cfg_env.chain_id)),
+        access_list: tx.access_list.clone().unwrap_or_default(),
+        gas_priority_fee,
+        blob_hashes: tx.blob_versioned_hashes.clone().unwrap_or_default(),
+        max_fee_per_blob_gas: tx.max_fee_per_blob_gas.unwrap_or_default(),
+        authorization_list: Vec::new(),
+    };
+    tx_env.set_signed_authorization(tx.authorization_list.clone().unwrap_or_default());
+    Ok(tx_env)
+}
+
+#[tokio::main]
+async fn main() -> Result<()> {
+    // Spin up a local Anvil node.
+    // Ensure `anvil` is available in $PATH.
+    let anvil = Anvil::new().spawn();
+    let provider =
+        ProviderBuilder::new().network::<AnyNetwork>().connect_http(anvil.endpoint_url());
+
+    let alice = anvil.addresses()[0];
+    let bob = anvil.addresses()[1];
+
+    let mut simulator = Simulator::fork(provider.clone(), BlockId::latest()).await?;
+    println!("Forked chain {} with {:?}", simulator.env().cfg_env.chain_id, simulator.spec());
+    let basefee = u128::from(simulator.env().block_env.basefee);
+    let coinbase = simulator.env().block_env.beneficiary;
+
+    // Deploy `Counter` and call it, then roll the bundle back.
+    let counter = alice.create(0);
+    let bundle = [
+        TransactionRequest::default().with_from(alice).with_deploy_code(Counter::BYTECODE.clone()),
+        TransactionRequest::default()
+            .with_from(alice)
+            .with_to(counter)
+            .with_input(Counter::setNumberCall { newNumber: U256::from(42) }.abi_encode()),
+        TransactionRequest::default()
+            .with_from(alice)
+            .with_to(counter)
+            .with_input(Counter::incrementCall {}.abi_encode()),
+        // `Counter` has no payable fallback, so sending it Ether reverts.
+        TransactionRequest::default().with_from(bob).with_to(counter).with_value(U256::from(1)),
+    ];
+    let receipts = simulator.simulate(&bundle, SimulationMode::Rollback)?;
+    for (i, receipt) in receipts.iter().enumerate() {
+        println!(
+            "tx {i}: success: {}, gas used: {}, cumulative: {}",
+            receipt.status(),
+            receipt.gas_used,
+            receipt.inner.cumulative_gas_used
+        );
+    }
+    assert_eq!(receipts[0].contract_address, Some(counter));
+    assert!(receipts[..3].iter().all(SimulatedReceipt::status));
+    assert!(!receipts[3].status());
+    assert_eq!(
+        receipts[3].inner.cumulative_gas_used,
+        receipts.iter().map(|receipt| receipt.gas_used).sum::<u64>()
+    );
+
+    // Nothing was kept.
+    assert!(simulator.db().basic_ref(counter)?.is_none_or(|account| account.is_empty()));
+
+    // Send Ether from Alice to Bob with a tip and an access list, and commit it.
+    let coinbase_before = simulator.db().basic_ref(coinbase)?.unwrap_or_default().balance;
+    let bob_before = simulator.db().basic_ref(bob)?.unwrap_or_default().balance;
+    let tip = 2_000_000_000;
+    let transfer = TransactionRequest::default()
+        .with_from(alice)
+        .with_to(bob)
+        .with_value(U256::from(100))
+        .with_max_fee_per_gas(basefee * 2 + tip)
+        .with_max_priority_fee_per_gas(tip)
+        .with_access_list(vec![Default::default()].into());
+    let receipts = simulator.simulate(&[transfer], SimulationMode::Commit)?;
+    let receipt = &receipts[0];
+    assert_eq!(receipt.tx_type, TxType::Eip1559 as u8);
+    assert_eq!(receipt.effective_gas_price, basefee + tip);
+
+    let coinbase_after = simulator.db().basic_ref(coinbase)?.unwrap_or_default().balance;
+    let bob_after = simulator.db().basic_ref(bob)?.unwrap_or_default().balance;
+    assert_eq!(bob_after, bob_before + U256::from(100));
+    assert_eq!(coinbase_after - coinbase_before, U256::from(receipt.gas_used as u128 * tip));
+    println!("Committed a transfer paying {} wei per gas", receipt.effective_gas_price);
+
+    // The node itself is untouched.
+    assert_eq!(provider.get_balance(bob).await?, bob_before);
+
+    // A transaction reusing Alice's first nonce is invalid and aborts its sequence.
+    let replay = TransactionRequest::default().with_from(alice).with_to(bob).with_nonce(0);
+    let err = simulator.simulate(&[replay], SimulationMode::Commit).unwrap_err();
+    println!("{err}");
+    assert!(matches!(err, SimulationError::Invalid { index: 0, .. }));
+
+    Ok(())
+}
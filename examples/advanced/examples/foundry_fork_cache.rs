@@ -0,0 +1,490 @@
+//! Example of a persistent on-disk cache for `foundry_fork_db`.
+//!
+//! `foundry_fork_db.rs` creates its `BlockchainDb` without a cache path, so everything fetched from
+//! the RPC provider is lost on exit. `ForkCache` stores the block, accounts, storage and block
+//! hashes of every fork under a directory keyed by chain id and block number, with the same layout
+//! as the RPC cache of Foundry:
+//!
+//! ```text
+//! <root>/<chain id>/<block number>/block.json
+//! <root>/<chain id>/<block number>/storage.json
+//! ```
+//!
+//! A fork only requests what is missing from the cache, and flushing merges the entries other
+//! processes wrote in the meantime under a file lock instead of overwriting them. The cache reports
+//! its size per block and evicts the least recently used blocks, and every fork counts its cache
+//! hits and misses.
+
+use std::{
+    fmt,
+    fs::{self, File},
+    io::{BufWriter, ErrorKind, Write},
+    path::{Path, PathBuf},
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        Arc,
+    },
+    time::{Instant, SystemTime},
+};
+
+use alloy::{
+    eips::BlockId,
+    network::{AnyNetwork, AnyRpcBlock, TransactionBuilder},
+    node_bindings::Anvil,
+    primitives::{Address, B256, U256},
+    providers::{Provider, ProviderBuilder},
+    rpc::types::TransactionRequest,
+};
+use eyre::{OptionExt, Result};
+use foundry_fork_db::{
+    cache::{BlockchainDbMeta, JsonBlockCacheDB, JsonBlockCacheData},
+    BlockchainDb, DatabaseError, SharedBackend,
+};
+use fs2::FileExt;
+use revm::{bytecode::Bytecode, state::AccountInfo, DatabaseRef};
+
+/// The file holding the block of a fork.
+const BLOCK_FILE: &str = "block.json";
+
+/// The file holding the accounts, storage and block hashes of a fork.
+const STORAGE_FILE: &str = "storage.json";
+
+/// The file locked while flushing a fork.
+const LOCK_FILE: &str = ".lock";
+
+/// The cache hits and misses of one kind of data.
+#[derive(Debug, Default)]
+pub struct HitCounter {
+    hits: AtomicU64,
+    misses: AtomicU64,
+}
+
+impl HitCounter {
+    /// Records a lookup, served from the cache if `hit`.
+    pub fn record(&self, hit: bool) {
+        let counter = if hit { &self.hits } else { &self.misses };
+        counter.fetch_add(1, Ordering::Relaxed);
+    }
+
+    /// Returns the number of lookups served from the cache.
+    pub fn hits(&self) -> u64 {
+        self.hits.load(Ordering::Relaxed)
+    }
+
+    /// Returns the number of lookups sent to the RPC provider.
+    pub fn misses(&self) -> u64 {
+        self.misses.load(Ordering::Relaxed)
+    }
+}
+
+/// The cache hits and misses of a fork.
+#[derive(Debug, Default)]
+pub struct CacheStats {
+    /// The lookups of the forked block.
+    pub blocks: HitCounter,
+    /// The lookups of accounts.
+    pub accounts: HitCounter,
+    /// The lookups of storage slots.
+    pub storage: HitCounter,
+    /// The lookups of block hashes.
+    pub block_hashes: HitCounter,
+}
+
+impl CacheStats {
+    /// Returns the total number of hits and misses.
+    pub fn total(&self) -> (u64, u64) {
+        [&self.blocks, &self.accounts, &self.storage, &self.block_hashes]
+            .iter()
+            .fold((0, 0), |(hits, misses), counter| {
+                (hits + counter.hits(), misses + counter.misses())
+            })
+    }
+}
+
+impl fmt::Display for CacheStats {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "{:<14} {:>6} {:>6}", "", "hits", "misses")?;
+        for (name, counter) in [
+            ("blocks", &self.blocks),
+            ("accounts", &self.accounts),
+            ("storage", &self.storage),
+            ("block hashes", &self.block_hashes),
+        ] {
+            writeln!(f, "{name:<14} {:>6} {:>6}", counter.hits(), counter.misses())?;
+        }
+        Ok(())
+    }
+}
+
+/// A block stored in the cache.
+#[derive(Clone, Debug)]
+pub struct CacheEntry {
+    /// The chain id.
+    pub chain_id: u64,
+    /// The block number.
+    pub block: u64,
+    /// The directory of the block.
+    pub path: PathBuf,
+    /// The size of the files of the block, in bytes.
+    pub size: u64,
+    /// The last time the block was forked or flushed.
+    pub last_used: SystemTime,
+}
+
+/// An exclusive lock of the lock file of a fork, held while flushing it.
+///
+/// The lock is taken by the OS, so it is released when the file is closed, on drop or when the
+/// process dies, and the lock file itself is never removed.
+#[derive(Debug)]
+struct CacheLock(File);
+
+impl CacheLock {
+    /// Locks the lock file of the directory `dir`, blocking until other processes release it.
+    fn acquire(dir: &Path) -> Result<Self> {
+        let file =
+            File::options().create(true).truncate(false).write(true).open(dir.join(LOCK_FILE))?;
+        file.lock_exclusive()?;
+        Ok(Self(file))
+    }
+}
+
+impl Drop for CacheLock {
+    fn drop(&mut self) {
+        let _ = FileExt::unlock(&self.0);
+    }
+}
+
+/// Writes `contents` to `path` through a temporary file, so that readers never see a partial file.
+fn write_atomic(
+    path: &Path,
+    contents: impl FnOnce(&mut BufWriter<File>) -> Result<()>,
+) -> Result<()> {
+    let tmp = path.with_extension(format!("{}.tmp", std::process::id()));
+    let mut writer = BufWriter::new(File::create(&tmp)?);
+    contents(&mut writer)?;
+    writer.flush()?;
+    // Written files are stamped like touched ones, as the clock of the file system is coarser.
+    writer.get_ref().set_modified(SystemTime::now())?;
+    fs::rename(tmp, path)?;
+    Ok(())
+}
+
+/// Marks `path` as used now, for the eviction of the least recently used blocks.
+fn touch(path: &Path) {
+    let _ = File::options()
+        .append(true)
+        .open(path)
+        .and_then(|file| file.set_modified(SystemTime::now()));
+}
+
+/// A persistent cache of forks, keyed by chain id and block number.
+#[derive(Clone, Debug)]
+pub struct ForkCache {
+    root: PathBuf,
+}
+
+impl ForkCache {
+    /// Creates a cache stored under `root`.
+    pub fn new(root: impl Into<PathBuf>) -> Self {
+        Self { root: root.into() }
+    }
+
+    /// Returns the directory of the block `number` of the chain `chain_id`.
+    pub fn path(&self, chain_id: u64, number: u64) -> PathBuf {
+        self.root.join(chain_id.to_string()).join(number.to_string())
+    }
+
+    /// Forks the chain of `provider` at the block `number`, starting from the cached state of the
+    /// block.
+    ///
+    /// The chain id is read from the provider, so that the state of a chain is never cached under
+    /// the id of another.
+    pub async fn fork<P>(&self, provider: P, number: u64) -> Result<CachedBackend>
+    where
+        P: Provider<AnyNetwork> + Unpin + 'static + Clone,
+    {
+        let chain_id = provider.get_chain_id().await?;
+        let stats = Arc::new(CacheStats::default());
+        let dir = self.path(chain_id, number);
+        fs::create_dir_all(&dir)?;
+
+        let block_path = dir.join(BLOCK_FILE);
+        let cached_block = fs::read(&block_path)
+            .ok()
+            .and_then(|block| serde_json::from_slice::<AnyRpcBlock>(&block).ok());
+        stats.blocks.record(cached_block.is_some());
+        let block = match cached_block {
+            Some(block) => {
+                touch(&block_path);
+                block
+            }
+            None => {
+                let block = provider
+                    .get_block(BlockId::number(number))
+                    .await?
+                    .ok_or_eyre("block not found")?;
+                write_atomic(&block_path, |writer| Ok(serde_json::to_writer(writer, &block)?))?;
+                block
+            }
+        };
+
+        // The backend is given a transient db, as it would otherwise overwrite the cache file on
+        // drop, losing what other processes flushed in the meantime.
+        let meta = BlockchainDbMeta::default().with_block(&block.inner);
+        let db = BlockchainDb::new(meta, None);
+        let storage_path = dir.join(STORAGE_FILE);
+        if let Ok(cached) = JsonBlockCacheDB::load(&storage_path) {
+            if *cached.meta().read() == *db.meta().read() {
+                merge(&db, &cached);
+            }
+        }
+
+        let backend =
+            SharedBackend::spawn_backend(provider, db.clone(), Some(BlockId::number(number))).await;
+
+        Ok(CachedBackend { backend, db, path: storage_path, stats })
+    }
+
+    /// Returns the cached blocks.
+    pub fn entries(&self) -> Result<Vec<CacheEntry>> {
+        let mut entries = Vec::new();
+        let Ok(chains) = fs::read_dir(&self.root) else { return Ok(entries) };
+        for chain in chains {
+            let chain = chain?;
+            let Some(chain_id) = chain.file_name().to_str().and_then(|id| id.parse().ok()) else {
+                continue;
+            };
+            for block in fs::read_dir(chain.path())? {
+                let block = block?;
+                let Some(number) = block.file_name().to_str().and_then(|n| n.parse().ok()) else {
+                    continue;
+                };
+                let mut entry = CacheEntry {
+                    chain_id,
+                    block: number,
+                    path: block.path(),
+                    size: 0,
+                    last_used: SystemTime::UNIX_EPOCH,
+                };
+                for file in fs::read_dir(block.path())? {
+                    let metadata = file?.metadata()?;
+                    entry.size += metadata.len();
+                    entry.last_used = entry.last_used.max(metadata.modified()?);
+                }
+                entries.push(entry);
+            }
+        }
+        entries.sort_by_key(|entry| (entry.chain_id, entry.block));
+        Ok(entries)
+    }
+
+    /// Returns the size of the cache, in bytes.
+    pub fn size(&self) -> Result<u64> {
+        Ok(self.entries()?.iter().map(|entry| entry.size).sum())
+    }
+
+    /// Removes the block `number` of the chain `chain_id` from the cache.
+    pub fn remove(&self, chain_id: u64, number: u64) -> Result<()> {
+        match fs::remove_dir_all(self.path(chain_id, number)) {
+            Err(err) if err.kind() != ErrorKind::NotFound => Err(err.into()),
+            _ => Ok(()),
+        }
+    }
+
+    /// Evicts the least recently used blocks until the cache fits in `max_size` bytes, and returns
+    /// the evicted blocks.
+    pub fn prune(&self, max_size: u64) -> Result<Vec<CacheEntry>> {
+        let mut entries = self.entries()?;
+        entries.sort_by_key(|entry| entry.last_used);
+        let mut size: u64 = entries.iter().map(|entry| entry.size).sum();
+        let mut evicted = Vec::new();
+        for entry in entries {
+            if size <= max_size {
+                break;
+            }
+            self.remove(entry.chain_id, entry.block)?;
+            size -= entry.size;
+            evicted.push(entry);
+        }
+        Ok(evicted)
+    }
+}
+
+/// Adds the entries of `cached` missing from `db`.
+fn merge(db: &BlockchainDb, cached: &JsonBlockCacheDB) {
+    let cached = cached.db();
+    let mut accounts = db.accounts().write();
+    for (address, info) in cached.accounts.read().iter() {
+        accounts.entry(*address).or_insert_with(|| info.clone());
+    }
+    let mut storage = db.storage().write();
+    for (address, slots) in cached.storage.read().iter() {
+        let account = storage.entry(*address).or_default();
+        for (index, value) in slots {
+            account.entry(*index).or_insert(*value);
+        }
+    }
+    let mut block_hashes = db.block_hashes().write();
+    for (number, hash) in cached.block_hashes.read().iter() {
+        block_hashes.entry(*number).or_insert(*hash);
+    }
+}
+
+/// A `SharedBackend` over a fork of the cache, counting the cache hits and misses.
+///
+/// Only the state of the forked block should be written to the fork, so transactions are meant to
+/// be committed to a `CacheDB` wrapping it rather than to the fork itself.
+#[derive(Clone, Debug)]
+pub struct CachedBackend {
+    backend: SharedBackend,
+    db: BlockchainDb,
+    path: PathBuf,
+    stats: Arc<CacheStats>,
+}
+
+impl CachedBackend {
+    /// Returns the cache hits and misses of the fork.
+    pub fn stats(&self) -> &CacheStats {
+        &self.stats
+    }
+
+    /// Writes the fork to the cache, keeping the entries flushed by other processes.
+    ///
+    /// The file lock and the writes block, so they run on the blocking thread pool.
+    pub async fn flush(&self) -> Result<()> {
+        let fork = self.clone();
+        tokio::task::spawn_blocking(move || fork.flush_blocking()).await?
+    }
+
+    /// Writes the fork to the cache under the file lock of its directory.
+    fn flush_blocking(&self) -> Result<()> {
+        let dir = self.path.parent().ok_or_eyre("invalid cache path")?;
+        let _lock = CacheLock::acquire(dir)?;
+        if let Ok(cached) = JsonBlockCacheDB::load(&self.path) {
+            if *cached.meta().read() == *self.db.meta().read() {
+                merge(&self.db, &cached);
+            }
+        }
+        let data = JsonBlockCacheData { meta: self.db.meta().clone(), data: self.db.db().clone() };
+        write_atomic(&self.path, |writer| Ok(serde_json::to_writer(writer, &data)?))
+    }
+}
+
+impl DatabaseRef for CachedBackend {
+    type Error = DatabaseError;
+
+    fn basic_ref(&self, address: Address) -> Result<Option<AccountInfo>, Self::Error> {
+        self.stats.accounts.record(self.db.accounts().read().contains_key(&address));
+        self.backend.basic_ref(address)
+    }
+
+    fn code_by_hash_ref(&self, code_hash: B256) -> Result<Bytecode, Self::Error> {
+        self.backend.code_by_hash_ref(code_hash)
+    }
+
+    fn storage_ref(&self, address: Address, index: U256) -> Result<U256, Self::Error> {
+        let hit =
+            self.db.storage().read().get(&address).is_some_and(|slots| slots.contains_key(&index));
+        self.stats.storage.record(hit);
+        self.backend.storage_ref(address, index)
+    }
+
+    fn block_hash_ref(&self, number: u64) -> Result<B256, Self::Error> {
+        self.stats
+            .block_hashes
+            .record(self.db.block_hashes().read().contains_key(&U256::from(number)));
+        self.backend.block_hash_ref(number)
+    }
+}
+
+/// Reads the balance and first storage slot of `accounts` and the hash of `block` from `fork`.
+fn read_state(fork: &CachedBackend, accounts: &[Address], block: u64) -> Result<Vec<U256>> {
+    let mut values = Vec::new();
+    for account in accounts {
+        values.push(fork.basic_ref(*account)?.unwrap_or_default().balance);
+        values.push(fork.storage_ref(*account, U256::ZERO)?);
+    }
+    values.push(fork.block_hash_ref(block)?.into());
+    Ok(values)
+}
+
+#[tokio::main]
+async fn main() -> Result<()> {
+    let anvil = Anvil::new().spawn();
+    let provider =
+        ProviderBuilder::new().network::<AnyNetwork>().connect_http(anvil.endpoint_url());
+
+    let alice = anvil.addresses()[0];
+    let bob = anvil.addresses()[1];
+    let carol = anvil.addresses()[2];
+
+    // The cache would usually live in a fixed directory, such as `~/.foundry/cache/rpc`.
+    let dir = tempfile::tempdir()?;
+    let cache = ForkCache::new(dir.path());
+
+    // The first run fetches everything from the RPC provider and flushes it to the cache.
+    let start_t = Instant::now();
+    let fork = cache.fork(Arc::new(provider.clone()), 0).await?;
+    let state_rpc = read_state(&fork, &[alice, bob], 0)?;
+    let time_rpc = start_t.elapsed();
+    fork.flush().await?;
+    let stats_rpc = fork.stats().to_string();
+    assert_eq!(fork.stats().total().0, 0);
+    drop(fork);
+
+    // A later run, in this process or another one, reads the same state from the cache.
+    let start_t = Instant::now();
+    let fork = cache.fork(Arc::new(provider.clone()), 0).await?;
+    let state_cache = read_state(&fork, &[alice, bob], 0)?;
+    let time_cache = start_t.elapsed();
+    assert_eq!(state_rpc, state_cache);
+    assert_eq!(fork.stats().total().1, 0);
+
+    println!("-------fork block 0--------");
+    println!("1st run     (via rpc): {time_rpc:?}");
+    println!("{stats_rpc}");
+    println!("2nd run   (via cache): {time_cache:?}");
+    println!("{}", fork.stats());
+
+    // Forks of the same block flushing different state share the cache instead of overwriting
+    // each other.
+    let other = cache.fork(Arc::new(provider.clone()), 0).await?;
+    other.basic_ref(carol)?;
+    other.flush().await?;
+    fork.basic_ref(anvil.addresses()[3])?;
+    fork.flush().await?;
+    drop((fork, other));
+
+    let merged = cache.fork(Arc::new(provider.clone()), 0).await?;
+    for account in [alice, bob, carol, anvil.addresses()[3]] {
+        merged.basic_ref(account)?;
+    }
+    assert_eq!(merged.stats().accounts.misses(), 0);
+    drop(merged);
+
+    // Mine a block and fork it, adding a second block to the cache.
+    let tx =
+        TransactionRequest::default().with_from(alice).with_to(bob).with_value(U256::from(100));
+    provider.send_transaction(tx.into()).await?.get_receipt().await?;
+
+    let fork = cache.fork(Arc::new(provider), 1).await?;
+    let bob_bal = fork.basic_ref(bob)?.unwrap_or_default().balance;
+    assert_eq!(bob_bal, state_rpc[2] + U256::from(100));
+    fork.flush().await?;
+    drop(fork);
+
+    println!("-------cache entries--------");
+    for entry in cache.entries()? {
+        println!("chain {} block {}: {} bytes", entry.chain_id, entry.block, entry.size);
+    }
+    println!("total: {} bytes\n", cache.size()?);
+
+    // Evicting down to the size of the newest block removes the least recently used one.
+    let newest = cache.entries()?.into_iter().max_by_key(|entry| entry.last_used).unwrap();
+    let evicted = cache.prune(newest.size)?;
+    assert_eq!(evicted.iter().map(|entry| entry.block).collect::<Vec<_>>(), [0]);
+    assert_eq!(cache.size()?, newest.size);
+    println!("evicted block 0, {} bytes left", cache.size()?);
+
+    Ok(())
+}
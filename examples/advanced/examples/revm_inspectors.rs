@@ -0,0 +1,497 @@
+//! Example of custom `revm` inspectors plugged into the `EthEvm` setup of `foundry_fork_db.rs` in
+//! place of its `NoOpInspector`:
+//!
+//! - `CallTracer` records the call tree in the shape of the `callTracer` of geth.
+//! - `GasProfiler` attributes the gas of every call frame to the called function, named from the
+//!   registered ABIs.
+//! - `OpcodeHistogram` counts the executed opcodes.
+//!
+//! The inspectors are combined as a tuple and run over an in-memory database on the `Counter`
+//! contract and an EIP-1167 clone of it, which delegates its calls to `Counter`. Wrapping a
+//! `SharedBackend` in `WrapDatabaseRef`, as `foundry_fork_db.rs` does, runs them on forked state.
+
+use std::collections::{BTreeMap, HashMap};
+
+use alloy::{
+    hex,
+    json_abi::JsonAbi,
+    primitives::{address, bytes, Address, Bytes, Selector, TxKind, U256},
+    rpc::types::trace::geth::{CallFrame, CallLogFrame},
+    sol,
+    sol_types::{Revert, SolCall, SolError},
+};
+use alloy_evm::{eth::EthEvmContext, EthEvm, Evm};
+use eyre::{OptionExt, Result};
+use revm::{
+    bytecode::opcode::{self, OpCode},
+    context::{BlockEnv, ContextTr, Evm as RevmEvm, TxEnv},
+    context_interface::{result::ExecutionResult, CreateScheme},
+    database::{CacheDB, EmptyDB},
+    handler::{instructions::EthInstructions, EthPrecompiles},
+    interpreter::{
+        interpreter_types::Jumps, CallInputs, CallOutcome, CallScheme, CallValue, CreateInputs,
+        CreateOutcome, InstructionResult, Interpreter,
+    },
+    primitives::{hardfork::SpecId, Log},
+    state::AccountInfo,
+    Inspector,
+};
+
+sol! {
+    #[allow(missing_docs)]
+    // solc v0.8.26; solc Counter.sol --via-ir --optimize --bin
+    #[sol(abi, bytecode="6080806040523460135760df908160198239f35b600080fdfe6080806040526004361015601257600080fd5b60003560e01c9081633fb5c1cb1460925781638381f58a146079575063d09de08a14603c57600080fd5b3460745760003660031901126074576000546000198114605e57600101600055005b634e487b7160e01b600052601160045260246000fd5b600080fd5b3460745760003660031901126074576020906000548152f35b34607457602036600319011260745760043560005500fea2646970667358221220e978270883b7baed10810c4079c941512e93a7ba1cd1108c781d4bc738d9090564736f6c634300081a0033")]
+    contract Counter {
+        uint256 public number;
+
+        function setNumber(uint256 newNumber) public {
+            number = newNumber;
+        }
+
+        function increment() public {
+            number++;
+        }
+    }
+}
+
+/// Returns the error geth reports for a frame that ended with `result`.
+fn geth_error(result: InstructionResult) -> String {
+    match result {
+        InstructionResult::Revert => "execution reverted",
+        InstructionResult::OutOfGas
+        | InstructionResult::MemoryOOG
+        | InstructionResult::MemoryLimitOOG
+        | InstructionResult::PrecompileOOG
+        | InstructionResult::InvalidOperandOOG
+        | InstructionResult::ReentrancySentryOOG => "out of gas",
+        InstructionResult::CallTooDeep => "max call depth exceeded",
+        InstructionResult::OutOfFunds => "insufficient balance for transfer",
+        InstructionResult::CreateCollision => "contract address collision",
+        InstructionResult::CreateContractSizeLimit => "max code size exceeded",
+        InstructionResult::InvalidJump => "invalid jump destination",
+        InstructionResult::StateChangeDuringStaticCall
+        | InstructionResult::CallNotAllowedInsideStatic => "write protection",
+        InstructionResult::OutOfOffset => "return data out of bounds",
+        InstructionResult::NonceOverflow => "nonce uint64 overflow",
+        InstructionResult::StackUnderflow => "stack underflow",
+        InstructionResult::StackOverflow => "stack limit reached 1024",
+        InstructionResult::OpcodeNotFound | InstructionResult::InvalidFEOpcode => "invalid opcode",
+        result => return format!("{result:?}"),
+    }
+    .to_string()
+}
+
+/// Records the call tree of a transaction in the shape of the `callTracer` of geth, with logs.
+#[derive(Debug, Default)]
+pub struct CallTracer {
+    stack: Vec<CallFrame>,
+    root: Option<CallFrame>,
+}
+
+impl CallTracer {
+    /// Returns the call tree of the last transaction, which used `gas_limit` and ended with
+    /// `result`, and resets the tracer.
+    ///
+    /// As with geth, the gas of the top frame is the gas of the transaction, including the
+    /// intrinsic gas.
+    pub fn take_frame(&mut self, gas_limit: u64, result: &ExecutionResult) -> Option<CallFrame> {
+        self.stack.clear();
+        let mut root = self.root.take()?;
+        root.gas = U256::from(gas_limit);
+        root.gas_used = U256::from(result.gas_used());
+        Some(root)
+    }
+
+    /// Completes the current frame and attaches it to its parent.
+    fn end_frame(&mut self, result: InstructionResult, output: &Bytes, gas_spent: u64) {
+        let Some(mut frame) = self.stack.pop() else { return };
+        frame.gas_used = U256::from(gas_spent);
+        if !output.is_empty() {
+            frame.output = Some(output.clone());
+        }
+        if !result.is_ok() {
+            frame.error = Some(geth_error(result));
+            frame.revert_reason = result
+                .is_revert()
+                .then(|| Revert::abi_decode(output).ok().map(|revert| revert.reason))
+                .flatten();
+            // Logs of failed frames are discarded, as their state changes are.
+            frame.logs.clear();
+        }
+        match self.stack.last_mut() {
+            Some(parent) => parent.calls.push(frame),
+            None => self.root = Some(frame),
+        }
+    }
+}
+
+impl<CTX: ContextTr> Inspector<CTX> for CallTracer {
+    fn log(&mut self, _interp: &mut Interpreter, _context: &mut CTX, log: Log) {
+        let Some(frame) = self.stack.last_mut() else { return };
+        frame.logs.push(CallLogFrame {
+            address: Some(log.address),
+            topics: Some(log.topics().to_vec()),
+            data: Some(log.data.data),
+            position: Some(frame.calls.len() as u64),
+            index: None,
+        });
+    }
+
+    fn call(&mut self, context: &mut CTX, inputs: &mut CallInputs) -> Option<CallOutcome> {
+        let typ = match inputs.scheme {
+            CallScheme::Call => "CALL",
+            CallScheme::CallCode => "CALLCODE",
+            CallScheme::DelegateCall => "DELEGATECALL",
+            CallScheme::StaticCall => "STATICCALL",
+        };
+        // Delegate calls carry the value of their parent, which is not transferred again.
+        let value = match inputs.value {
+            CallValue::Transfer(value) => Some(value),
+            CallValue::Apparent(_) => None,
+        };
+        // Delegate calls keep the caller of their parent, while geth reports the delegating
+        // contract, which is also the target of the call.
+        let from = match inputs.scheme {
+            CallScheme::DelegateCall => inputs.target_address,
+            _ => inputs.caller,
+        };
+        self.stack.push(CallFrame {
+            from,
+            gas: U256::from(inputs.gas_limit),
+            to: Some(inputs.bytecode_address),
+            input: inputs.input.bytes(context),
+            value,
+            typ: typ.to_string(),
+            ..Default::default()
+        });
+        None
+    }
+
+    fn call_end(&mut self, _context: &mut CTX, _inputs: &CallInputs, outcome: &mut CallOutcome) {
+        self.end_frame(outcome.result.result, &outcome.result.output, outcome.gas().spent());
+    }
+
+    fn create(&mut self, _context: &mut CTX, inputs: &mut CreateInputs) -> Option<CreateOutcome> {
+        let typ = match inputs.scheme {
+            CreateScheme::Create2 { .. } => "CREATE2",
+            _ => "CREATE",
+        };
+        self.stack.push(CallFrame {
+            from: inputs.caller,
+            gas: U256::from(inputs.gas_limit),
+            input: inputs.init_code.clone(),
+            value: Some(inputs.value),
+            typ: typ.to_string(),
+            ..Default::default()
+        });
+        None
+    }
+
+    fn create_end(
+        &mut self,
+        _context: &mut CTX,
+        _inputs: &CreateInputs,
+        outcome: &mut CreateOutcome,
+    ) {
+        if let Some(frame) = self.stack.last_mut() {
+            frame.to = outcome.address;
+        }
+        self.end_frame(outcome.result.result, &outcome.result.output, outcome.gas().spent());
+    }
+}
+
+/// The entry point of a call frame.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum Entry {
+    /// The constructor, run by a contract creation.
+    Constructor,
+    /// The function with the selector.
+    Function(Selector),
+    /// The fallback or receive function, for calls without a selector.
+    Fallback,
+}
+
+/// The gas spent by a function, over all its calls.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct FunctionGas {
+    /// The number of calls.
+    pub calls: u64,
+    /// The gas spent by the calls, including their sub-calls.
+    pub inclusive: u64,
+    /// The gas spent by the calls themselves.
+    pub exclusive: u64,
+}
+
+/// A call frame being profiled.
+#[derive(Debug)]
+struct ProfiledFrame {
+    code: Address,
+    entry: Entry,
+    children_gas: u64,
+}
+
+/// Attributes the gas of every call frame to the function it runs, across transactions.
+///
+/// Frames are attributed to the code they run, so the delegate calls of a proxy are attributed to
+/// its implementation, while the proxy itself keeps the gas it spends forwarding them.
+#[derive(Debug, Default)]
+pub struct GasProfiler {
+    contracts: HashMap<Address, (String, JsonAbi)>,
+    stack: Vec<ProfiledFrame>,
+    functions: BTreeMap<(Address, Entry), FunctionGas>,
+}
+
+impl GasProfiler {
+    /// Registers the contract at `address`, named `label`, with its ABI.
+    pub fn register(&mut self, address: Address, label: &str, abi: JsonAbi) {
+        self.contracts.insert(address, (label.to_string(), abi));
+    }
+
+    /// Returns the gas of `entry` of the code at `code`.
+    pub fn function(&self, code: Address, entry: Entry) -> FunctionGas {
+        self.functions.get(&(code, entry)).copied().unwrap_or_default()
+    }
+
+    /// Returns the name of `entry` of the code at `code`.
+    ///
+    /// Functions are looked up in the ABI of the contract first and in the other ABIs next, as
+    /// proxies forward calls to functions they do not declare.
+    pub fn name(&self, code: Address, entry: Entry) -> String {
+        let contract = self.contracts.get(&code);
+        let label = contract.map_or_else(|| code.to_string(), |(label, _)| label.clone());
+        let function = match entry {
+            Entry::Constructor => "constructor".to_string(),
+            Entry::Fallback => "fallback()".to_string(),
+            Entry::Function(selector) => contract
+                .into_iter()
+                .chain(self.contracts.values())
+                .flat_map(|(_, abi)| abi.functions())
+                .find(|function| function.selector() == selector)
+                .map_or_else(|| hex::encode_prefixed(selector), |function| function.signature()),
+        };
+        format!("{label}::{function}")
+    }
+
+    /// Returns the named functions, by decreasing inclusive gas.
+    pub fn report(&self) -> Vec<(String, FunctionGas)> {
+        let mut report: Vec<_> = self
+            .functions
+            .iter()
+            .map(|((code, entry), gas)| (self.name(*code, *entry), *gas))
+            .collect();
+        report.sort_by_key(|(_, gas)| std::cmp::Reverse(gas.inclusive));
+        report
+    }
+
+    /// Completes the current frame, which spent `gas_spent` including its sub-calls.
+    fn end_frame(&mut self, gas_spent: u64) {
+        let Some(frame) = self.stack.pop() else { return };
+        let function = self.functions.entry((frame.code, frame.entry)).or_default();
+        function.calls += 1;
+        function.inclusive += gas_spent;
+        function.exclusive += gas_spent.saturating_sub(frame.children_gas);
+        if let Some(parent) = self.stack.last_mut() {
+            parent.children_gas += gas_spent;
+        }
+    }
+}
+
+impl<CTX: ContextTr> Inspector<CTX> for GasProfiler {
+    fn call(&mut self, context: &mut CTX, inputs: &mut CallInputs) -> Option<CallOutcome> {
+        let input = inputs.input.bytes(context);
+        let entry = input
+            .get(..4)
+            .map_or(Entry::Fallback, |selector| Entry::Function(Selector::from_slice(selector)));
+        self.stack.push(ProfiledFrame { code: inputs.bytecode_address, entry, children_gas: 0 });
+        None
+    }
+
+    fn call_end(&mut self, _context: &mut CTX, _inputs: &CallInputs, outcome: &mut CallOutcome) {
+        self.end_frame(outcome.gas().spent());
+    }
+
+    fn create(&mut self, _context: &mut CTX, _inputs: &mut CreateInputs) -> Option<CreateOutcome> {
+        // The address is only known once the creation ends.
+        let frame =
+            ProfiledFrame { code: Address::ZERO, entry: Entry::Constructor, children_gas: 0 };
+        self.stack.push(frame);
+        None
+    }
+
+    fn create_end(
+        &mut self,
+        _context: &mut CTX,
+        _inputs: &CreateInputs,
+        outcome: &mut CreateOutcome,
+    ) {
+        if let Some(frame) = self.stack.last_mut() {
+            frame.code = outcome.address.unwrap_or_default();
+        }
+        self.end_frame(outcome.gas().spent());
+    }
+}
+
+/// Counts the executed opcodes, across transactions.
+#[derive(Debug)]
+pub struct OpcodeHistogram {
+    counts: [u64; 256],
+}
+
+impl Default for OpcodeHistogram {
+    fn default() -> Self {
+        Self { counts: [0; 256] }
+    }
+}
+
+impl OpcodeHistogram {
+    /// Returns the number of times `opcode` was executed.
+    pub const fn count(&self, opcode: u8) -> u64 {
+        self.counts[opcode as usize]
+    }
+
+    /// Returns the number of executed opcodes.
+    pub fn total(&self) -> u64 {
+        self.counts.iter().sum()
+    }
+
+    /// Returns the `n` most executed opcodes with their names, by decreasing count.
+    pub fn top(&self, n: usize) -> Vec<(&'static str, u64)> {
+        let mut counts: Vec<_> = (0..=u8::MAX)
+            .filter(|opcode| self.count(*opcode) > 0)
+            .map(|opcode| {
+                (OpCode::new(opcode).map_or("UNKNOWN", OpCode::as_str), self.count(opcode))
+            })
+            .collect();
+        counts.sort_by_key(|(_, count)| std::cmp::Reverse(*count));
+        counts.truncate(n);
+        counts
+    }
+}
+
+impl<CTX> Inspector<CTX> for OpcodeHistogram {
+    fn step(&mut self, interp: &mut Interpreter, _context: &mut CTX) {
+        self.counts[interp.bytecode.opcode() as usize] += 1;
+    }
+}
+
+/// Configures an `EthEvm` over `db` with `inspector`, as `configure_evm` of `foundry_fork_db.rs`
+/// does with `NoOpInspector`.
+fn configure_evm<I>(db: CacheDB<EmptyDB>, inspector: I) -> EthEvm<CacheDB<EmptyDB>, I>
+where
+    I: Inspector<EthEvmContext<CacheDB<EmptyDB>>>,
+{
+    let block_env = BlockEnv {
+        number: U256::from(1),
+        timestamp: U256::from(1_700_000_000),
+        gas_limit: 30_000_000,
+        ..Default::default()
+    };
+
+    let context = EthEvmContext::new(db, SpecId::PRAGUE).with_block(block_env);
+
+    let evm = RevmEvm::new(context, EthInstructions::default(), EthPrecompiles::default())
+        .with_inspector(inspector);
+
+    // Inspectors are only called when the EVM is created with inspection enabled.
+    EthEvm::new(evm, true)
+}
+
+#[tokio::main]
+async fn main() -> Result<()> {
+    let alice = address!("f39Fd6e51aad88F6F4ce6aB8827279cffFb92266");
+    let mut db = CacheDB::new(EmptyDB::default());
+    db.insert_account_info(alice, AccountInfo { balance: U256::from(1), ..Default::default() });
+
+    let inspectors = (CallTracer::default(), (GasProfiler::default(), OpcodeHistogram::default()));
+    let mut evm = configure_evm(db, inspectors);
+
+    // The EIP-1167 minimal proxy delegating to `Counter`, and its creation code.
+    let counter = alice.create(0);
+    let clone = alice.create(1);
+    let proxy = [
+        &bytes!("363d3d373d3d3d363d73")[..],
+        counter.as_slice(),
+        &bytes!("5af43d82803e903d91602b57fd5bf3")[..],
+    ]
+    .concat();
+    let clone_code = [&bytes!("3d602d80600a3d3981f3")[..], &proxy].concat();
+
+    let set_number = Counter::setNumberCall { newNumber: U256::from(41) }.abi_encode();
+    let increment = Counter::incrementCall {}.abi_encode();
+    let txs = [
+        (TxKind::Create, Counter::BYTECODE.clone(), U256::ZERO),
+        (TxKind::Create, clone_code.into(), U256::ZERO),
+        (TxKind::Call(clone), set_number.into(), U256::ZERO),
+        (TxKind::Call(clone), increment.clone().into(), U256::ZERO),
+        (TxKind::Call(counter), increment.into(), U256::ZERO),
+        // `Counter` has no payable fallback, so sending it Ether reverts.
+        (TxKind::Call(counter), Bytes::new(), U256::from(1)),
+    ];
+
+    let mut traces = Vec::new();
+    for (nonce, (kind, data, value)) in txs.into_iter().enumerate() {
+        let gas_limit = 1_000_000;
+        let tx_env = TxEnv {
+            caller: alice,
+            kind,
+            data,
+            value,
+            gas_limit,
+            nonce: nonce as u64,
+            ..Default::default()
+        };
+        let result = evm.transact_commit(tx_env)?;
+        let (tracer, _) = evm.inspector_mut();
+        traces.push(tracer.take_frame(gas_limit, &result).ok_or_eyre("missing trace")?);
+    }
+
+    // The call through the clone delegates to `Counter`.
+    let trace = &traces[3];
+    println!("{}", serde_json::to_string_pretty(trace)?);
+    assert_eq!((trace.typ.as_str(), trace.to), ("CALL", Some(clone)));
+    assert_eq!(trace.calls.len(), 1);
+    let delegate = &trace.calls[0];
+    assert_eq!(
+        (delegate.typ.as_str(), delegate.from, delegate.to),
+        ("DELEGATECALL", clone, Some(counter))
+    );
+    assert_eq!(delegate.input[..], Counter::incrementCall::SELECTOR);
+    assert!(delegate.gas_used < trace.gas_used);
+
+    // The creations report the created addresses, and the failed call its error.
+    assert_eq!((traces[0].typ.as_str(), traces[0].to), ("CREATE", Some(counter)));
+    assert_eq!(traces[1].output.as_deref().map(|output| &output[..]), Some(&proxy[..]));
+    assert_eq!(traces[5].error.as_deref(), Some("execution reverted"));
+    assert!(traces[5].revert_reason.is_none());
+
+    let json = serde_json::to_value(trace)?;
+    assert_eq!(json["calls"][0]["type"], "DELEGATECALL");
+    let parsed: CallFrame = serde_json::from_value(json)?;
+    assert_eq!(&parsed, trace);
+
+    let (_, (profiler, histogram)) = evm.inspector_mut();
+    profiler.register(counter, "Counter", Counter::abi::contract());
+    profiler.register(clone, "CounterClone", JsonAbi::new());
+
+    println!("\n{:<40} {:>6} {:>10} {:>10}", "function", "calls", "inclusive", "exclusive");
+    for (name, gas) in profiler.report() {
+        println!("{name:<40} {:>6} {:>10} {:>10}", gas.calls, gas.inclusive, gas.exclusive);
+    }
+    let increment = Entry::Function(Counter::incrementCall::SELECTOR.into());
+    assert_eq!(profiler.function(counter, increment).calls, 2);
+    assert_eq!(profiler.function(clone, increment).calls, 1);
+    assert_eq!(profiler.function(counter, Entry::Fallback).calls, 1);
+    assert_eq!(profiler.name(clone, increment), "CounterClone::increment()");
+    let through_clone = profiler.function(clone, increment);
+    assert_eq!(through_clone.inclusive - through_clone.exclusive, delegate.gas_used.to::<u64>());
+
+    println!("\n{} opcodes executed, the most frequent:", histogram.total());
+    for (name, count) in histogram.top(10) {
+        println!("{name:<16} {count:>6}");
+    }
+    // `setNumber` and both `increment` calls store the number, and the clone delegates twice.
+    assert_eq!(histogram.count(opcode::SSTORE), 3);
+    assert_eq!(histogram.count(opcode::DELEGATECALL), 2);
+
+    Ok(())
+}
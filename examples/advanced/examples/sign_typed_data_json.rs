@@ -0,0 +1,253 @@
+//! Example of signing an [EIP-712](https://eips.ethereum.org/EIPS/eip-712) `eth_signTypedData_v4`
+//! JSON payload with any signer, without declaring the types with `sol!`.
+
+use alloy::{
+    dyn_abi::TypedData,
+    hex,
+    primitives::{address, b256, keccak256, Address, Signature, B256},
+    signers::{local::PrivateKeySigner, Signer},
+};
+use eyre::{bail, ensure, OptionExt, Result};
+use serde_json::Value;
+
+/// An `eth_signTypedData_v4` payload that has been checked to be complete and consistent.
+#[derive(Clone, Debug)]
+pub struct TypedDataPayload {
+    typed_data: TypedData,
+}
+
+impl TypedDataPayload {
+    /// Parses and validates a payload with `types`, `primaryType`, `domain` and `message`.
+    ///
+    /// Beyond parsing, this checks that the domain has exactly the fields declared by
+    /// `EIP712Domain`, that every referenced type is defined and not recursive, and that the
+    /// message matches the primary type, including nested structs and arrays.
+    pub fn from_json(json: &str) -> Result<Self> {
+        let value: Value = serde_json::from_str(json)?;
+        let object = value.as_object().ok_or_eyre("typed data must be a JSON object")?;
+        for key in ["types", "primaryType", "domain", "message"] {
+            ensure!(object.contains_key(key), "typed data is missing `{key}`");
+        }
+
+        // The domain separator only hashes the fields declared in the types, so a domain field
+        // that is not declared, or the other way around, would be silently ignored.
+        let declared: Vec<&str> = object["types"]["EIP712Domain"]
+            .as_array()
+            .ok_or_eyre("`types` must declare `EIP712Domain`")?
+            .iter()
+            .filter_map(|field| field["name"].as_str())
+            .collect();
+        let domain = object["domain"].as_object().ok_or_eyre("`domain` must be an object")?;
+        for name in domain.keys() {
+            ensure!(declared.contains(&name.as_str()), "domain field `{name}` is not declared");
+        }
+        for name in &declared {
+            ensure!(domain.contains_key(*name), "declared domain field `{name}` is missing");
+        }
+
+        let typed_data: TypedData = serde_json::from_value(value)?;
+        if typed_data.primary_type == "EIP712Domain" {
+            bail!("the primary type cannot be `EIP712Domain`");
+        }
+
+        // Resolves the type graph and coerces the message, which fails on undefined or recursive
+        // types and on values that do not match their declared type.
+        typed_data.encode_type()?;
+        typed_data.coerce()?;
+
+        Ok(Self { typed_data })
+    }
+
+    /// Returns the encoding of the primary type and the types it references, e.g.
+    /// `Mail(Person from,Person to,string contents)Person(string name,address wallet)`.
+    pub fn encode_type(&self) -> Result<String> {
+        Ok(self.typed_data.encode_type()?)
+    }
+
+    /// Returns the domain separator.
+    pub fn domain_separator(&self) -> B256 {
+        self.typed_data.domain().separator()
+    }
+
+    /// Returns `hashStruct(message)`.
+    pub fn hash_struct(&self) -> Result<B256> {
+        Ok(self.typed_data.hash_struct()?)
+    }
+
+    /// Returns `keccak256(0x1901 || domainSeparator || hashStruct(message))`.
+    pub fn signing_hash(&self) -> Result<B256> {
+        Ok(self.typed_data.eip712_signing_hash()?)
+    }
+
+    /// Signs the payload with any signer.
+    pub async fn sign<S: Signer + ?Sized + Sync>(&self, signer: &S) -> Result<Signature> {
+        Ok(signer.sign_dynamic_typed_data(&self.typed_data).await?)
+    }
+
+    /// Returns the address that produced `signature` over the payload.
+    pub fn recover(&self, signature: &Signature) -> Result<Address> {
+        Ok(signature.recover_address_from_prehash(&self.signing_hash()?)?)
+    }
+}
+
+/// The `Mail` example of the EIP-712 specification.
+const MAIL: &str = r#"{
+    "types": {
+        "EIP712Domain": [
+            { "name": "name", "type": "string" },
+            { "name": "version", "type": "string" },
+            { "name": "chainId", "type": "uint256" },
+            { "name": "verifyingContract", "type": "address" }
+        ],
+        "Person": [
+            { "name": "name", "type": "string" },
+            { "name": "wallet", "type": "address" }
+        ],
+        "Mail": [
+            { "name": "from", "type": "Person" },
+            { "name": "to", "type": "Person" },
+            { "name": "contents", "type": "string" }
+        ]
+    },
+    "primaryType": "Mail",
+    "domain": {
+        "name": "Ether Mail",
+        "version": "1",
+        "chainId": 1,
+        "verifyingContract": "0xCcCCccccCCCCcCCCCCCcCcCccCcCCCcCcccccccC"
+    },
+    "message": {
+        "from": { "name": "Cow", "wallet": "0xCD2a3d9F938E13CD947Ec05AbC7FE734Df8DD826" },
+        "to": { "name": "Bob", "wallet": "0xbBbBBBBbbBBBbbbBbbBbbbbBBbBbbbbBbBbbBBbB" },
+        "contents": "Hello, Bob!"
+    }
+}"#;
+
+/// The `Mail` example of `eth_signTypedData_v4` in `MetaMask`, with arrays of nested structs.
+const MAIL_WITH_ARRAYS: &str = r#"{
+    "types": {
+        "EIP712Domain": [
+            { "name": "name", "type": "string" },
+            { "name": "version", "type": "string" },
+            { "name": "chainId", "type": "uint256" },
+            { "name": "verifyingContract", "type": "address" }
+        ],
+        "Group": [
+            { "name": "name", "type": "string" },
+            { "name": "members", "type": "Person[]" }
+        ],
+        "Mail": [
+            { "name": "from", "type": "Person" },
+            { "name": "to", "type": "Person[]" },
+            { "name": "contents", "type": "string" }
+        ],
+        "Person": [
+            { "name": "name", "type": "string" },
+            { "name": "wallets", "type": "address[]" }
+        ]
+    },
+    "primaryType": "Mail",
+    "domain": {
+        "name": "Ether Mail",
+        "version": "1",
+        "chainId": 1,
+        "verifyingContract": "0xCcCCccccCCCCcCCCCCCcCcCccCcCCCcCcccccccC"
+    },
+    "message": {
+        "from": {
+            "name": "Cow",
+            "wallets": [
+                "0xCD2a3d9F938E13CD947Ec05AbC7FE734Df8DD826",
+                "0xDeaDbeefdEAdbeefdEadbEEFdeadbeEFdEaDbeeF"
+            ]
+        },
+        "to": [
+            {
+                "name": "Bob",
+                "wallets": [
+                    "0xbBbBBBBbbBBBbbbBbbBbbbbBBbBbbbbBbBbbBBbB",
+                    "0xB0BdaBea57B0BDABeA57b0bdABEA57b0BDabEa57",
+                    "0xB0B0b0b0b0b0B000000000000000000000000000"
+                ]
+            }
+        ],
+        "contents": "Hello, Bob!"
+    }
+}"#;
+
+#[tokio::main]
+async fn main() -> Result<()> {
+    // The key of "Cow" in the EIP-712 specification.
+    let cow = PrivateKeySigner::from_bytes(&keccak256("cow"))?;
+    assert_eq!(cow.address(), address!("CD2a3d9F938E13CD947Ec05AbC7FE734Df8DD826"));
+
+    // Check the intermediate hashes of the specification.
+    let mail = TypedDataPayload::from_json(MAIL)?;
+    println!("Encoded type: {}", mail.encode_type()?);
+    assert_eq!(
+        mail.encode_type()?,
+        "Mail(Person from,Person to,string contents)Person(string name,address wallet)"
+    );
+    assert_eq!(
+        mail.domain_separator(),
+        b256!("f2cee375fa42b42143804025fc449deafd50cc031ca257e0b194a650a912090f")
+    );
+    assert_eq!(
+        mail.hash_struct()?,
+        b256!("c52c0ee5d84264471806290a3f2c4cecfc5490626bf912d01f240d7a274b371e")
+    );
+    assert_eq!(
+        mail.signing_hash()?,
+        b256!("be609aee343fb3c4b28e1df9e632fca64fcfaede20f02e86244efddf30957bd2")
+    );
+
+    // Signing is deterministic, so the signature matches the specification too.
+    let signature = mail.sign(&cow).await?;
+    println!("Signature: 0x{}", hex::encode(signature.as_bytes()));
+    assert_eq!(
+        B256::from(signature.r()),
+        b256!("4355c47d63924e8a72e509b65029052eb6c299d53a04e167c5775fd466751c9d")
+    );
+    assert_eq!(
+        B256::from(signature.s()),
+        b256!("07299936d304c153f6443dfa05f40ff007d72911b6f72307f996231605b91562")
+    );
+    assert!(signature.v());
+    assert_eq!(mail.recover(&signature)?, cow.address());
+
+    // Arrays of nested structs, with a type that is declared but not referenced.
+    let mail = TypedDataPayload::from_json(MAIL_WITH_ARRAYS)?;
+    println!("Encoded type: {}", mail.encode_type()?);
+    println!("Signing hash: {}", mail.signing_hash()?);
+    assert_eq!(
+        mail.signing_hash()?,
+        b256!("a85c2e2b118698e88db68a8105b794a8cc7cec074e89ef991cb4f5f533819cc2")
+    );
+    let signature = mail.sign(&cow).await?;
+    println!("Signature: 0x{}", hex::encode(signature.as_bytes()));
+    assert_eq!(
+        hex::encode(signature.as_bytes()),
+        "65cbd956f2fae28a601bebc9b906cea0191744bd4c4247bcd27cd08f8eb6b71c\
+         78efdf7a31dc9abee78f492292721f362d296cf86b4538e07b51303b67f749061b"
+    );
+    assert_eq!(mail.recover(&signature)?, cow.address());
+
+    // Invalid payloads are rejected.
+    let mut invalid: Value = serde_json::from_str(MAIL)?;
+    invalid["domain"]["salt"] = "0x00".into();
+    assert!(TypedDataPayload::from_json(&invalid.to_string()).is_err());
+
+    let mut invalid: Value = serde_json::from_str(MAIL)?;
+    invalid["types"]["Mail"][0]["type"] = "Human".into();
+    assert!(TypedDataPayload::from_json(&invalid.to_string()).is_err());
+
+    let mut invalid: Value = serde_json::from_str(MAIL)?;
+    invalid["message"]["to"]["wallet"] = "Bob".into();
+    assert!(TypedDataPayload::from_json(&invalid.to_string()).is_err());
+
+    let mut invalid: Value = serde_json::from_str(MAIL)?;
+    invalid.as_object_mut().unwrap().remove("message");
+    assert!(TypedDataPayload::from_json(&invalid.to_string()).is_err());
+
+    Ok(())
+}
@@ -0,0 +1,581 @@
+//! Example of a registry of contract ABIs and bytecode loaded from the artifacts of a Foundry
+//! `out/` or Hardhat `artifacts/` directory.
+//!
+//! The registry indexes the contracts by name, function and error selector, and event topic, and
+//! exposes their `JsonAbi`, their creation and deployed bytecode with link references, and their
+//! source maps. Labeling deployed addresses lets calldata and logs be decoded, and
+//! `ContractInstance`s be built, from the artifact of the contract at an address.
+
+use std::{
+    collections::{BTreeMap, HashMap},
+    fs,
+    path::Path,
+};
+
+use alloy::{
+    consensus::Transaction,
+    contract::{ContractInstance, Interface},
+    dyn_abi::{DynSolValue, EventExt, JsonAbiExt},
+    hex,
+    json_abi::{Event, Function, JsonAbi},
+    network::{Network, TransactionBuilder},
+    primitives::{Address, Bytes, Log, Selector, B256, U256},
+    providers::{Provider, ProviderBuilder},
+    rpc::types::TransactionRequest,
+};
+use eyre::{bail, OptionExt, Result};
+use serde::Deserialize;
+
+/// The range of a library address in bytecode, as stored in artifacts.
+#[derive(Clone, Debug, PartialEq, Eq, Deserialize)]
+struct Offset {
+    start: usize,
+    length: usize,
+}
+
+/// The link references of bytecode, by source file and library name.
+type RawLinkReferences = BTreeMap<String, BTreeMap<String, Vec<Offset>>>;
+
+/// Bytecode as stored by Foundry, with its source map and link references, or by Hardhat, as hex.
+#[derive(Debug, Deserialize)]
+#[serde(untagged)]
+enum RawBytecode {
+    Hex(String),
+    #[serde(rename_all = "camelCase")]
+    Object {
+        object: String,
+        source_map: Option<String>,
+        #[serde(default)]
+        link_references: RawLinkReferences,
+    },
+}
+
+/// A Foundry artifact, as stored in `out/<File>.sol/<Contract>.json`.
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct FoundryArtifact {
+    abi: JsonAbi,
+    bytecode: Option<RawBytecode>,
+    deployed_bytecode: Option<RawBytecode>,
+    metadata: Option<serde_json::Value>,
+}
+
+/// A Hardhat artifact, as stored in `artifacts/<path>/<File>.sol/<Contract>.json`.
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct HardhatArtifact {
+    contract_name: String,
+    source_name: String,
+    abi: JsonAbi,
+    bytecode: String,
+    deployed_bytecode: String,
+    #[serde(default)]
+    link_references: RawLinkReferences,
+    #[serde(default)]
+    deployed_link_references: RawLinkReferences,
+}
+
+/// A range of bytecode to be replaced by the address of a library.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct LinkReference {
+    /// The source file of the library.
+    pub source: String,
+    /// The name of the library.
+    pub library: String,
+    /// The offset of the address in the bytecode, in bytes.
+    pub start: usize,
+    /// The length of the address, in bytes.
+    pub length: usize,
+}
+
+/// An element of a source map, mapping an instruction to a range of a source file.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct SourceElement {
+    /// The byte offset of the range in the source file, or -1 for generated code.
+    pub offset: i64,
+    /// The length of the range, or -1 for generated code.
+    pub length: i64,
+    /// The index of the source file, or -1 for generated code.
+    pub index: i64,
+    /// The jump type: `i` into a function, `o` out of a function or `-` for a regular jump.
+    pub jump: char,
+    /// The depth of modifiers the instruction is in.
+    pub modifier_depth: u64,
+}
+
+/// Parses a compressed source map, where every element has the fields `s:l:f:j:m` and empty
+/// fields repeat those of the previous element.
+pub fn parse_source_map(source_map: &str) -> Result<Vec<SourceElement>> {
+    let mut elements = Vec::new();
+    let mut element = SourceElement { jump: '-', ..Default::default() };
+    for entry in source_map.split(';') {
+        for (field, value) in entry.split(':').enumerate().filter(|(_, value)| !value.is_empty()) {
+            match field {
+                0 => element.offset = value.parse()?,
+                1 => element.length = value.parse()?,
+                2 => element.index = value.parse()?,
+                3 => element.jump = value.chars().next().unwrap_or('-'),
+                4 => element.modifier_depth = value.parse()?,
+                _ => bail!("invalid source map entry: {entry}"),
+            }
+        }
+        elements.push(element);
+    }
+    Ok(elements)
+}
+
+/// The creation or deployed bytecode of an artifact.
+#[derive(Clone, Debug, Default)]
+pub struct ArtifactBytecode {
+    /// The hex encoded bytecode, with placeholders for the addresses of unlinked libraries.
+    pub object: String,
+    /// The ranges to be replaced by the addresses of libraries.
+    pub link_references: Vec<LinkReference>,
+    /// The compressed source map, which Hardhat only stores in its build info files.
+    pub source_map: Option<String>,
+}
+
+impl ArtifactBytecode {
+    fn new(object: String, link_references: RawLinkReferences, source_map: Option<String>) -> Self {
+        let mut references: Vec<_> = link_references
+            .into_iter()
+            .flat_map(|(source, libraries)| {
+                libraries.into_iter().flat_map(move |(library, offsets)| {
+                    let source = source.clone();
+                    offsets.into_iter().map(move |Offset { start, length }| LinkReference {
+                        source: source.clone(),
+                        library: library.clone(),
+                        start,
+                        length,
+                    })
+                })
+            })
+            .collect();
+        references.sort_by_key(|reference| reference.start);
+        let object = object.strip_prefix("0x").unwrap_or(&object).to_string();
+        Self { object, link_references: references, source_map }
+    }
+
+    /// Returns whether the bytecode has libraries left to link.
+    pub fn needs_linking(&self) -> bool {
+        self.object.contains("__")
+    }
+
+    /// Returns the bytecode, or `None` if it has libraries left to link.
+    pub fn code(&self) -> Option<Bytes> {
+        hex::decode(&self.object).ok().map(Into::into)
+    }
+
+    /// Returns the bytecode, with zeros in place of the addresses of unlinked libraries.
+    pub fn code_unlinked(&self) -> Result<Bytes> {
+        let mut object = self.object.clone();
+        // Placeholders are 40 characters long and start with `__`, whichever the compiler version.
+        while let Some(start) = object.find("__") {
+            object.replace_range(start..(start + 40).min(object.len()), &"0".repeat(40));
+        }
+        Ok(hex::decode(object)?.into())
+    }
+
+    /// Returns the source element of the instruction at the program counter `pc`.
+    pub fn source_element(&self, pc: usize) -> Result<Option<SourceElement>> {
+        let Some(source_map) = &self.source_map else { return Ok(None) };
+        let code = self.code_unlinked()?;
+
+        // Source maps have one element per instruction, so count the instructions up to `pc`,
+        // skipping the immediates of the `PUSH` instructions.
+        let (mut offset, mut index) = (0, 0);
+        while offset < pc {
+            let Some(opcode) = code.get(offset) else { return Ok(None) };
+            let immediates = if (0x60..=0x7f).contains(opcode) { opcode - 0x5f } else { 0 };
+            offset += 1 + usize::from(immediates);
+            index += 1;
+        }
+        if offset != pc {
+            return Ok(None);
+        }
+        Ok(parse_source_map(source_map)?.get(index).copied())
+    }
+}
+
+/// A contract compiled by Foundry or Hardhat.
+#[derive(Clone, Debug)]
+pub struct Artifact {
+    /// The name of the contract.
+    pub name: String,
+    /// The source file of the contract.
+    pub source: String,
+    /// The ABI of the contract.
+    pub abi: JsonAbi,
+    /// The creation bytecode, absent for interfaces and abstract contracts.
+    pub bytecode: Option<ArtifactBytecode>,
+    /// The deployed bytecode, absent for interfaces and abstract contracts.
+    pub deployed_bytecode: Option<ArtifactBytecode>,
+}
+
+impl Artifact {
+    /// Returns the fully qualified name of the contract, `<source>:<name>`.
+    pub fn qualified_name(&self) -> String {
+        format!("{}:{}", self.source, self.name)
+    }
+}
+
+/// A call decoded with the ABI of a registered contract.
+#[derive(Clone, Debug)]
+pub struct DecodedCall<'a> {
+    /// The called contract.
+    pub artifact: &'a Artifact,
+    /// The called function.
+    pub function: &'a Function,
+    /// The arguments of the call.
+    pub args: Vec<DynSolValue>,
+}
+
+/// A log decoded with the ABI of a registered contract.
+#[derive(Clone, Debug)]
+pub struct DecodedLog<'a> {
+    /// The emitting contract.
+    pub artifact: &'a Artifact,
+    /// The emitted event.
+    pub event: &'a Event,
+    /// The indexed parameters of the event.
+    pub indexed: Vec<DynSolValue>,
+    /// The other parameters of the event.
+    pub body: Vec<DynSolValue>,
+}
+
+/// A registry of the contracts of Foundry and Hardhat projects, indexed by name, selector and
+/// event topic.
+#[derive(Debug, Default)]
+pub struct ArtifactRegistry {
+    artifacts: Vec<Artifact>,
+    names: HashMap<String, Vec<usize>>,
+    functions: HashMap<Selector, Vec<usize>>,
+    errors: HashMap<Selector, Vec<usize>>,
+    events: HashMap<B256, Vec<usize>>,
+    labels: HashMap<Address, usize>,
+}
+
+impl ArtifactRegistry {
+    /// Loads the artifacts of a Foundry `out/` directory, returning the number of contracts.
+    pub fn load_foundry(&mut self, out: &Path) -> Result<usize> {
+        let mut count = 0;
+        for file_dir in fs::read_dir(out)? {
+            let file_dir = file_dir?.path();
+            if !file_dir.is_dir() || file_dir.ends_with("build-info") {
+                continue;
+            }
+            for file in fs::read_dir(&file_dir)? {
+                let path = file?.path();
+                if path.extension().is_none_or(|extension| extension != "json") {
+                    continue;
+                }
+                let artifact: FoundryArtifact = serde_json::from_slice(&fs::read(&path)?)?;
+                let name = path.file_stem().and_then(|s| s.to_str()).unwrap_or_default();
+
+                // The output keeps the file name of the source only, while the metadata has its
+                // path in the project.
+                let source = artifact
+                    .metadata
+                    .as_ref()
+                    .and_then(|metadata| metadata["settings"]["compilationTarget"].as_object())
+                    .and_then(|target| target.keys().next().cloned())
+                    .or_else(|| file_dir.file_name()?.to_str().map(String::from))
+                    .unwrap_or_default();
+
+                let bytecode = |raw| match raw {
+                    RawBytecode::Hex(object) => {
+                        ArtifactBytecode::new(object, BTreeMap::new(), None)
+                    }
+                    RawBytecode::Object { object, source_map, link_references } => {
+                        ArtifactBytecode::new(object, link_references, source_map)
+                    }
+                };
+                self.insert(Artifact {
+                    name: name.to_string(),
+                    source,
+                    abi: artifact.abi,
+                    bytecode: artifact.bytecode.map(bytecode),
+                    deployed_bytecode: artifact.deployed_bytecode.map(bytecode),
+                });
+                count += 1;
+            }
+        }
+        Ok(count)
+    }
+
+    /// Loads the artifacts of a Hardhat `artifacts/` directory, returning the number of contracts.
+    pub fn load_hardhat(&mut self, artifacts: &Path) -> Result<usize> {
+        let mut count = 0;
+        for entry in fs::read_dir(artifacts)? {
+            let path = entry?.path();
+            let file_name = path.file_name().and_then(|s| s.to_str()).unwrap_or_default();
+            if path.is_dir() {
+                if file_name != "build-info" {
+                    count += self.load_hardhat(&path)?;
+                }
+                continue;
+            }
+            // Debug files point to the build info, which holds the compiler input and output.
+            if !file_name.ends_with(".json") || file_name.ends_with(".dbg.json") {
+                continue;
+            }
+            let artifact: HardhatArtifact = serde_json::from_slice(&fs::read(&path)?)?;
+            let empty = |object: &str| object.is_empty() || object == "0x";
+            self.insert(Artifact {
+                name: artifact.contract_name,
+                source: artifact.source_name,
+                abi: artifact.abi,
+                bytecode: (!empty(&artifact.bytecode)).then(|| {
+                    ArtifactBytecode::new(artifact.bytecode, artifact.link_references, None)
+                }),
+                deployed_bytecode: (!empty(&artifact.deployed_bytecode)).then(|| {
+                    ArtifactBytecode::new(
+                        artifact.deployed_bytecode,
+                        artifact.deployed_link_references,
+                        None,
+                    )
+                }),
+            });
+            count += 1;
+        }
+        Ok(count)
+    }
+
+    /// Registers `artifact`, indexing its name, functions, errors and events.
+    pub fn insert(&mut self, artifact: Artifact) {
+        let index = self.artifacts.len();
+        self.names.entry(artifact.name.clone()).or_default().push(index);
+        self.names.entry(artifact.qualified_name()).or_default().push(index);
+        for function in artifact.abi.functions() {
+            self.functions.entry(function.selector()).or_default().push(index);
+        }
+        for error in artifact.abi.errors() {
+            self.errors.entry(error.selector()).or_default().push(index);
+        }
+        for event in artifact.abi.events().filter(|event| !event.anonymous) {
+            self.events.entry(event.selector()).or_default().push(index);
+        }
+        self.artifacts.push(artifact);
+    }
+
+    /// Returns the registered contracts.
+    pub fn iter(&self) -> impl Iterator<Item = &Artifact> {
+        self.artifacts.iter()
+    }
+
+    /// Returns the contract named `name`, or with the fully qualified name `<source>:<name>`.
+    ///
+    /// Returns `None` if several contracts have the name, which then has to be qualified.
+    pub fn get(&self, name: &str) -> Option<&Artifact> {
+        self.position(name).map(|index| &self.artifacts[index])
+    }
+
+    fn position(&self, name: &str) -> Option<usize> {
+        match self.names.get(name)?.as_slice() {
+            [index] => Some(*index),
+            _ => None,
+        }
+    }
+
+    /// Returns the functions with the selector, with their contracts.
+    pub fn functions(&self, selector: Selector) -> impl Iterator<Item = (&Artifact, &Function)> {
+        self.lookup(&self.functions, &selector).filter_map(move |artifact| {
+            let function =
+                artifact.abi.functions().find(|function| function.selector() == selector)?;
+            Some((artifact, function))
+        })
+    }
+
+    /// Returns the contracts declaring an error with the selector.
+    pub fn errors(&self, selector: Selector) -> impl Iterator<Item = &Artifact> {
+        self.lookup(&self.errors, &selector)
+    }
+
+    /// Returns the events with the topic, with their contracts.
+    pub fn events(&self, topic: B256) -> impl Iterator<Item = (&Artifact, &Event)> {
+        self.lookup(&self.events, &topic).filter_map(move |artifact| {
+            let event = artifact.abi.events().find(|event| event.selector() == topic)?;
+            Some((artifact, event))
+        })
+    }
+
+    fn lookup<'a, K: std::hash::Hash + Eq>(
+        &'a self,
+        index: &'a HashMap<K, Vec<usize>>,
+        key: &K,
+    ) -> impl Iterator<Item = &'a Artifact> {
+        index.get(key).into_iter().flatten().map(|index| &self.artifacts[*index])
+    }
+
+    /// Labels `address` as a deployment of the contract `name`.
+    pub fn label(&mut self, address: Address, name: &str) -> Result<()> {
+        let index = self.position(name).ok_or_eyre(format!("unknown contract {name}"))?;
+        self.labels.insert(address, index);
+        Ok(())
+    }
+
+    /// Returns the contract labeled at `address`.
+    pub fn at(&self, address: Address) -> Option<&Artifact> {
+        self.labels.get(&address).map(|index| &self.artifacts[*index])
+    }
+
+    /// Returns a `ContractInstance` of the contract `name` at `address`.
+    pub fn instance<P: Provider<N>, N: Network>(
+        &self,
+        name: &str,
+        address: Address,
+        provider: P,
+    ) -> Result<ContractInstance<P, N>> {
+        let artifact = self.get(name).ok_or_eyre(format!("unknown contract {name}"))?;
+        Ok(ContractInstance::new(address, provider, Interface::new(artifact.abi.clone())))
+    }
+
+    /// Decodes a call of `input` to `to`, with the ABI of the contract labeled at `to` or else
+    /// the first contract with a function of the selector.
+    pub fn decode_call(&self, to: Option<Address>, input: &[u8]) -> Option<DecodedCall<'_>> {
+        let selector = Selector::try_from(input.get(..4)?).ok()?;
+        let labeled = to.and_then(|to| self.at(to)).and_then(|artifact| {
+            let function =
+                artifact.abi.functions().find(|function| function.selector() == selector)?;
+            Some((artifact, function))
+        });
+        labeled.into_iter().chain(self.functions(selector)).find_map(|(artifact, function)| {
+            let args = function.abi_decode_input(&input[4..]).ok()?;
+            Some(DecodedCall { artifact, function, args })
+        })
+    }
+
+    /// Decodes `log` with the ABI of the contract labeled at its address or else the first
+    /// contract with an event of the topic.
+    pub fn decode_log(&self, log: &Log) -> Option<DecodedLog<'_>> {
+        let topic = *log.topics().first()?;
+        let labeled = self.at(log.address).and_then(|artifact| {
+            let event = artifact.abi.events().find(|event| event.selector() == topic)?;
+            Some((artifact, event))
+        });
+        labeled.into_iter().chain(self.events(topic)).find_map(|(artifact, event)| {
+            let decoded = event.decode_log(&log.data).ok()?;
+            Some(DecodedLog { artifact, event, indexed: decoded.indexed, body: decoded.body })
+        })
+    }
+}
+
+#[tokio::main]
+async fn main() -> Result<()> {
+    let projects = std::env::current_dir()?.join("examples/contracts/examples/projects");
+    let mut registry = ArtifactRegistry::default();
+    let foundry = registry.load_foundry(&projects.join("foundry/out"))?;
+    let hardhat = registry.load_hardhat(&projects.join("hardhat/artifacts"))?;
+    println!("Loaded {foundry} Foundry and {hardhat} Hardhat artifacts:");
+    for artifact in registry.iter() {
+        let unlinked = artifact.bytecode.as_ref().is_some_and(ArtifactBytecode::needs_linking);
+        println!(
+            "  {} ({} functions, {} events{})",
+            artifact.qualified_name(),
+            artifact.abi.functions().count(),
+            artifact.abi.events().count(),
+            if unlinked { ", needs linking" } else { "" }
+        );
+    }
+
+    // Look up contracts by selector and event topic.
+    let set_number = registry.functions(Selector::from(hex!("3fb5c1cb"))).next();
+    let (artifact, function) = set_number.ok_or_eyre("setNumber not found")?;
+    assert_eq!(
+        (artifact.name.as_str(), function.signature()),
+        ("Counter", "setNumber(uint256)".into())
+    );
+
+    let add_topic = registry
+        .get("EventMultiplexer")
+        .ok_or_eyre("missing artifact")?
+        .abi
+        .event("Add")
+        .and_then(|events| events.first())
+        .ok_or_eyre("missing event")?
+        .selector();
+    let (artifact, event) = registry.events(add_topic).next().ok_or_eyre("Add not found")?;
+    assert_eq!(
+        (artifact.source.as_str(), event.name.as_str()),
+        ("contracts/EventMultiplexer.sol", "Add")
+    );
+
+    // Libraries are linked at the ranges of the link references.
+    let bounded = registry.get("BoundedCounter").ok_or_eyre("missing artifact")?;
+    let bytecode = bounded.bytecode.as_ref().ok_or_eyre("missing bytecode")?;
+    let [reference] = bytecode.link_references.as_slice() else { bail!("expected one library") };
+    println!(
+        "\n{} links {}:{} at bytes {}..{}",
+        bounded.name,
+        reference.source,
+        reference.library,
+        reference.start,
+        reference.start + reference.length
+    );
+    assert!(bytecode.code().is_none());
+    let placeholder =
+        &bytecode.object[reference.start * 2..(reference.start + reference.length) * 2];
+    assert!(placeholder.starts_with("__$") && placeholder.ends_with("$__"));
+
+    // Source maps map instructions to the source, here the declaration of `Counter`.
+    let counter = registry.get("src/Counter.sol:Counter").ok_or_eyre("missing artifact")?;
+    let deployed = counter.deployed_bytecode.as_ref().ok_or_eyre("missing bytecode")?;
+    let element = deployed.source_element(0)?.ok_or_eyre("missing source element")?;
+    println!(
+        "pc 0 of Counter maps to {}:{} of source {}",
+        element.offset, element.length, element.index
+    );
+    assert_eq!((element.offset, element.length, element.index), (65, 192, 25));
+
+    // Spin up a local Anvil node.
+    // Ensure `anvil` is available in $PATH.
+    let provider = ProviderBuilder::new().connect_anvil_with_wallet();
+    let alice = provider.get_accounts().await?[0];
+
+    // Deploy the contracts from their artifacts and label their addresses.
+    let deploy = async |name: &str| -> Result<Address> {
+        let artifact = registry.get(name).ok_or_eyre("missing artifact")?;
+        let code =
+            artifact.bytecode.as_ref().and_then(ArtifactBytecode::code).ok_or_eyre("no code")?;
+        let tx = TransactionRequest::default().from(alice).with_deploy_code(code);
+        let receipt = provider.send_transaction(tx).await?.get_receipt().await?;
+        receipt.contract_address.ok_or_eyre("no contract deployed")
+    };
+    let counter_address = deploy("Counter").await?;
+    let multiplexer_address = deploy("EventMultiplexer").await?;
+    registry.label(counter_address, "Counter")?;
+    registry.label(multiplexer_address, "contracts/EventMultiplexer.sol:EventMultiplexer")?;
+
+    // Interact with the contracts through instances built from the registry.
+    let counter = registry.instance("Counter", counter_address, provider.clone())?;
+    let receipt = counter
+        .function("setNumber", &[DynSolValue::Uint(U256::from(42), 256)])?
+        .send()
+        .await?
+        .get_receipt()
+        .await?;
+    assert!(receipt.status());
+
+    let multiplexer =
+        registry.instance("EventMultiplexer", multiplexer_address, provider.clone())?;
+    let args = [DynSolValue::Int(1.try_into()?, 256), DynSolValue::Int(2.try_into()?, 256)];
+    let receipt = multiplexer.function("add", &args)?.send().await?.get_receipt().await?;
+
+    // Decode the transaction and its logs with the labeled ABIs.
+    let tx = provider
+        .get_transaction_by_hash(receipt.transaction_hash)
+        .await?
+        .ok_or_eyre("missing transaction")?;
+    let call = registry.decode_call(tx.inner.to(), tx.inner.input()).ok_or_eyre("unknown call")?;
+    println!("\n{}::{}{:?}", call.artifact.name, call.function.name, call.args);
+    assert_eq!(call.function.name, "add");
+
+    for log in receipt.inner.logs() {
+        let decoded = registry.decode_log(&log.inner).ok_or_eyre("unknown log")?;
+        println!("{}::{}{:?}", decoded.artifact.name, decoded.event.name, decoded.indexed);
+        assert_eq!(decoded.indexed[0], DynSolValue::Address(alice));
+        assert_eq!(decoded.indexed[1], DynSolValue::Int(3.try_into()?, 256));
+    }
+
+    Ok(())
+}
@@ -0,0 +1,397 @@
+//! Example of a [library linker](https://docs.soliditylang.org/en/latest/using-the-compiler.html#library-linking)
+//! driven by the link references of Foundry artifacts and solc `--combined-json` output.
+//!
+//! The linker deploys the libraries a contract depends on, and the libraries they depend on, in
+//! topological order, then patches the exact byte ranges of their placeholders with the deployed
+//! addresses. Both the `__$<hash>$__` placeholders of solc >=0.5 and the legacy `__<Name>__`
+//! placeholders are supported.
+
+use std::{
+    collections::{BTreeMap, BTreeSet},
+    fs,
+    path::Path,
+};
+
+use alloy::{
+    hex,
+    network::TransactionBuilder,
+    primitives::{address, keccak256, Address, Bytes, U256},
+    providers::{Provider, ProviderBuilder},
+    rpc::types::TransactionRequest,
+    sol,
+};
+use eyre::{bail, ensure, OptionExt, Result};
+use serde::Deserialize;
+
+sol! {
+    #[allow(missing_docs)]
+    #[sol(rpc)]
+    interface IComparators {
+        function lt(uint256 a, uint256 b) external pure returns (bool);
+        function gt(uint256 a, uint256 b) external pure returns (bool);
+    }
+
+    #[allow(missing_docs)]
+    #[sol(rpc)]
+    interface ICounter {
+        function number() external view returns (uint256);
+        function incrementUntil(uint256 upperBound) external;
+    }
+}
+
+/// The length of an address, and of its placeholder, in bytes.
+const ADDRESS_LENGTH: usize = 20;
+
+/// The range of a library address in bytecode, as stored in artifacts.
+#[derive(Debug, Deserialize)]
+struct Offset {
+    start: usize,
+    length: usize,
+}
+
+/// The creation bytecode of a Foundry artifact.
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct FoundryBytecode {
+    object: String,
+    #[serde(default)]
+    link_references: BTreeMap<String, BTreeMap<String, Vec<Offset>>>,
+}
+
+/// A Foundry artifact, as stored in `out/<File>.sol/<Contract>.json`.
+#[derive(Debug, Deserialize)]
+struct FoundryArtifact {
+    bytecode: Option<FoundryBytecode>,
+    metadata: Option<serde_json::Value>,
+}
+
+/// A contract of the output of `solc --combined-json bin`.
+#[derive(Debug, Deserialize)]
+struct CombinedContract {
+    #[serde(default)]
+    bin: String,
+}
+
+/// The output of `solc --combined-json bin`.
+#[derive(Debug, Deserialize)]
+struct CombinedJson {
+    contracts: BTreeMap<String, CombinedContract>,
+}
+
+/// A range of bytecode to be replaced by the address of a library.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct LinkReference {
+    /// The fully qualified name of the library, `<source>:<name>`.
+    pub library: String,
+    /// The offset of the address in the bytecode, in bytes.
+    pub start: usize,
+}
+
+/// The unlinked creation bytecode of a contract.
+#[derive(Clone, Debug)]
+struct Contract {
+    /// The hex encoded bytecode, with placeholders for the addresses of libraries.
+    object: String,
+    /// The link references stored in the artifact, empty if the compiler did not output them.
+    link_references: Vec<LinkReference>,
+}
+
+/// Returns the placeholder of the library `library` used by solc >=0.5: the first 17 bytes of the
+/// hash of its fully qualified name, between `__$` and `$__`.
+pub fn hashed_placeholder(library: &str) -> String {
+    format!("__${}$__", hex::encode(&keccak256(library)[..17]))
+}
+
+/// Returns the placeholder of the library `library` used by solc <0.5: its fully qualified name
+/// truncated to 36 characters, between `__` and padding underscores.
+pub fn legacy_placeholder(library: &str) -> String {
+    let name = library.get(..36).unwrap_or(library);
+    format!("{:_<40}", format!("__{name}"))
+}
+
+/// A linker of the contracts of Foundry projects and solc outputs, indexed by fully qualified
+/// name.
+#[derive(Debug, Default)]
+pub struct Linker {
+    contracts: BTreeMap<String, Contract>,
+}
+
+impl Linker {
+    /// Loads the artifacts of a Foundry `out/` directory, returning the number of contracts.
+    pub fn load_foundry(&mut self, out: &Path) -> Result<usize> {
+        let mut count = 0;
+        for file_dir in fs::read_dir(out)? {
+            let file_dir = file_dir?.path();
+            if !file_dir.is_dir() || file_dir.ends_with("build-info") {
+                continue;
+            }
+            for file in fs::read_dir(&file_dir)? {
+                let path = file?.path();
+                if path.extension().is_none_or(|extension| extension != "json") {
+                    continue;
+                }
+                let artifact: FoundryArtifact = serde_json::from_slice(&fs::read(&path)?)?;
+                let Some(bytecode) = artifact.bytecode else { continue };
+                let name = path.file_stem().and_then(|s| s.to_str()).unwrap_or_default();
+
+                // Link references are keyed by the path of the source in the project, which only
+                // the metadata has for the contract itself.
+                let source = artifact
+                    .metadata
+                    .as_ref()
+                    .and_then(|metadata| metadata["settings"]["compilationTarget"].as_object())
+                    .and_then(|target| target.keys().next().cloned())
+                    .or_else(|| file_dir.file_name()?.to_str().map(String::from))
+                    .unwrap_or_default();
+
+                let mut link_references = Vec::new();
+                for (library_source, libraries) in bytecode.link_references {
+                    for (library, offsets) in libraries {
+                        for Offset { start, length } in offsets {
+                            ensure!(
+                                length == ADDRESS_LENGTH,
+                                "invalid link reference length {length} in {}",
+                                path.display()
+                            );
+                            let library = format!("{library_source}:{library}");
+                            link_references.push(LinkReference { library, start });
+                        }
+                    }
+                }
+                if self.insert(format!("{source}:{name}"), bytecode.object, link_references) {
+                    count += 1;
+                }
+            }
+        }
+        Ok(count)
+    }
+
+    /// Loads the output of `solc --combined-json bin`, returning the number of contracts.
+    ///
+    /// The output has no link references, so the placeholders are found in the bytecode.
+    pub fn load_solc_combined(&mut self, path: &Path) -> Result<usize> {
+        let output: CombinedJson = serde_json::from_slice(&fs::read(path)?)?;
+        let mut count = 0;
+        for (name, contract) in output.contracts {
+            if self.insert(name, contract.bin, Vec::new()) {
+                count += 1;
+            }
+        }
+        Ok(count)
+    }
+
+    /// Inserts the creation bytecode of a contract, unless it is empty, as for interfaces.
+    fn insert(
+        &mut self,
+        name: String,
+        object: String,
+        link_references: Vec<LinkReference>,
+    ) -> bool {
+        let object = object.strip_prefix("0x").unwrap_or(&object).to_string();
+        if object.is_empty() {
+            return false;
+        }
+        self.contracts.insert(name, Contract { object, link_references });
+        true
+    }
+
+    /// Returns the fully qualified name of the contract `name`, which may be the bare name of a
+    /// contract if no other contract has the same.
+    pub fn resolve(&self, name: &str) -> Result<String> {
+        if self.contracts.contains_key(name) {
+            return Ok(name.to_string());
+        }
+        let mut matches = self
+            .contracts
+            .keys()
+            .filter(|qualified| qualified.rsplit_once(':').is_some_and(|(_, bare)| bare == name));
+        match (matches.next(), matches.next()) {
+            (Some(qualified), None) => Ok(qualified.clone()),
+            (Some(_), Some(_)) => bail!("ambiguous contract name {name}"),
+            (None, _) => bail!("unknown contract {name}"),
+        }
+    }
+
+    /// Returns the link references of the contract `name`, found from the placeholders of its
+    /// bytecode if the artifact has none.
+    pub fn references(&self, name: &str) -> Result<Vec<LinkReference>> {
+        let contract = self.contracts.get(name).ok_or_eyre(format!("unknown contract {name}"))?;
+        if !contract.link_references.is_empty() {
+            return Ok(contract.link_references.clone());
+        }
+
+        let mut references = Vec::new();
+        let mut offset = 0;
+        while let Some(found) = contract.object[offset..].find("__") {
+            let start = offset + found;
+            let placeholder = contract
+                .object
+                .get(start..start + 2 * ADDRESS_LENGTH)
+                .ok_or_eyre(format!("truncated placeholder in {name}"))?;
+            // Placeholders are aligned to bytes, and the hash of a name cannot be reversed, so
+            // they are matched against those of the known contracts.
+            ensure!(start % 2 == 0, "misaligned placeholder {placeholder} in {name}");
+            let library = self
+                .contracts
+                .keys()
+                .find(|library| {
+                    placeholder == hashed_placeholder(library)
+                        || placeholder == legacy_placeholder(library)
+                })
+                .ok_or_eyre(format!("unknown library placeholder {placeholder} in {name}"))?;
+            references.push(LinkReference { library: library.clone(), start: start / 2 });
+            offset = start + 2 * ADDRESS_LENGTH;
+        }
+        Ok(references)
+    }
+
+    /// Returns the libraries the contract `name` links to directly.
+    pub fn libraries(&self, name: &str) -> Result<BTreeSet<String>> {
+        Ok(self.references(name)?.into_iter().map(|reference| reference.library).collect())
+    }
+
+    /// Returns the contract `name` and the libraries it depends on, transitively, in the order
+    /// they have to be deployed: every library comes after the libraries it links to.
+    pub fn deploy_order(&self, name: &str) -> Result<Vec<String>> {
+        let mut order = Vec::new();
+        self.visit(name, &mut Vec::new(), &mut order)?;
+        Ok(order)
+    }
+
+    /// Appends the dependencies of the contract `name`, then the contract, to `order`.
+    fn visit(&self, name: &str, path: &mut Vec<String>, order: &mut Vec<String>) -> Result<()> {
+        if order.iter().any(|ordered| ordered == name) {
+            return Ok(());
+        }
+        if path.iter().any(|visiting| visiting == name) {
+            bail!("circular library dependency: {} -> {name}", path.join(" -> "));
+        }
+        path.push(name.to_string());
+        for library in self.libraries(name)? {
+            self.visit(&library, path, order)?;
+        }
+        path.pop();
+        order.push(name.to_string());
+        Ok(())
+    }
+
+    /// Returns the bytecode of the contract `name`, linked to the libraries at `addresses`, by
+    /// fully qualified name.
+    pub fn link(&self, name: &str, addresses: &BTreeMap<String, Address>) -> Result<Bytes> {
+        let contract = self.contracts.get(name).ok_or_eyre(format!("unknown contract {name}"))?;
+        let mut object = contract.object.clone();
+        for LinkReference { library, start } in self.references(name)? {
+            let address =
+                addresses.get(&library).ok_or_eyre(format!("library {library} is not deployed"))?;
+            let range = 2 * start..2 * (start + ADDRESS_LENGTH);
+            let placeholder = object
+                .get(range.clone())
+                .ok_or_eyre(format!("link reference {start} out of bounds in {name}"))?;
+            ensure!(
+                placeholder == hashed_placeholder(&library)
+                    || placeholder == legacy_placeholder(&library),
+                "expected a placeholder for {library} at {start} in {name}, found {placeholder}"
+            );
+            object.replace_range(range, &hex::encode(address));
+        }
+        ensure!(!object.contains("__"), "{name} has unresolved library placeholders");
+        Ok(hex::decode(object)?.into())
+    }
+
+    /// Deploys the contract `name`, after the libraries it depends on which are not in
+    /// `deployed`, and returns its address.
+    ///
+    /// The addresses of the newly deployed libraries are added to `deployed`, so they are reused
+    /// by the next deployments.
+    pub async fn deploy<P: Provider>(
+        &self,
+        name: &str,
+        provider: &P,
+        deployed: &mut BTreeMap<String, Address>,
+    ) -> Result<Address> {
+        let name = self.resolve(name)?;
+        for contract in self.deploy_order(&name)? {
+            if contract != name && deployed.contains_key(&contract) {
+                continue;
+            }
+            let tx =
+                TransactionRequest::default().with_deploy_code(self.link(&contract, deployed)?);
+            let receipt = provider.send_transaction(tx).await?.get_receipt().await?;
+            ensure!(receipt.status(), "deployment of {contract} reverted");
+            let address = receipt.contract_address.ok_or_eyre("missing contract address")?;
+            if contract == name {
+                return Ok(address);
+            }
+            deployed.insert(contract, address);
+        }
+        unreachable!("the deploy order ends with the contract")
+    }
+}
+
+#[tokio::main]
+async fn main() -> Result<()> {
+    let projects = std::env::current_dir()?.join("examples/contracts/examples/projects");
+
+    // Load a Foundry project, whose artifacts have link references with `__$<hash>$__`
+    // placeholders, and an output in the `--combined-json` format of solc <0.5, with legacy
+    // `__<Name>__` placeholders, which links to a library of the Foundry project.
+    //
+    // The solc output is synthetic, assembled by hand from the Foundry artifacts rather than
+    // compiled: `CappedCounter` is `BoundedCounter` with its placeholder replaced by the legacy one
+    // of `Bounds`, and `Bounds` forwards its calls to `Comparators`.
+    let mut linker = Linker::default();
+    let count = linker.load_foundry(&projects.join("foundry/out"))?;
+    println!("Loaded {count} contracts from the Foundry project");
+    let count = linker.load_solc_combined(&projects.join("solc/synthetic-combined.json"))?;
+    println!("Loaded {count} contracts from the synthetic solc output");
+
+    // `CappedCounter` links to `Bounds`, which links to `Comparators`.
+    let capped_counter = linker.resolve("CappedCounter")?;
+    let order = linker.deploy_order(&capped_counter)?;
+    println!("Deploy order of {capped_counter}: {}", order.join(" -> "));
+    assert_eq!(
+        order,
+        ["src/Comparators.sol:Comparators", "src/Bounds.sol:Bounds", &capped_counter]
+    );
+
+    // Linking patches the exact range of the link reference.
+    let bounded_counter = linker.resolve("BoundedCounter")?;
+    let placeholder = address!("1234567890123456789012345678901234567890");
+    let references = linker.references(&bounded_counter)?;
+    assert_eq!(
+        references,
+        [LinkReference { library: "src/Comparators.sol:Comparators".into(), start: 158 }]
+    );
+    let addresses = BTreeMap::from([(references[0].library.clone(), placeholder)]);
+    let code = linker.link(&bounded_counter, &addresses)?;
+    assert_eq!(&code[158..158 + ADDRESS_LENGTH], placeholder.as_slice());
+    println!("Linked {bounded_counter} to Comparators at {placeholder}");
+
+    // Spin up a local Anvil node.
+    // Ensure `anvil` is available in $PATH.
+    let provider = ProviderBuilder::new().connect_anvil_with_wallet();
+
+    // Deploy both counters, which share the `Comparators` library.
+    let mut deployed = BTreeMap::new();
+    let capped_counter = linker.deploy("CappedCounter", &provider, &mut deployed).await?;
+    let bounded_counter = linker.deploy("BoundedCounter", &provider, &mut deployed).await?;
+    assert_eq!(deployed.len(), 2);
+    for (library, address) in &deployed {
+        println!("Deployed {library} at {address}");
+    }
+    println!("Deployed the counters at {capped_counter} and {bounded_counter}");
+
+    // `Bounds` forwards its calls to the `Comparators` library it is linked to.
+    let bounds = IComparators::new(deployed["src/Bounds.sol:Bounds"], &provider);
+    assert!(bounds.lt(U256::from(1), U256::from(2)).call().await?);
+    println!("Bounds.lt(1, 2) verified!");
+
+    for address in [capped_counter, bounded_counter] {
+        let counter = ICounter::new(address, &provider);
+        counter.incrementUntil(U256::from(10)).send().await?.watch().await?;
+        assert_eq!(counter.number().call().await?, U256::from(1));
+        println!("Counter.number == 1 verified at {address}!");
+    }
+
+    Ok(())
+}
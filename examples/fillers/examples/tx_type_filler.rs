@@ -0,0 +1,304 @@
+//! Rolls a custom filler that detects the capabilities of the connected chain once and picks,
+//! or downgrades, the transaction envelope type accordingly.
+
+use std::{
+    fmt,
+    sync::{Arc, OnceLock},
+};
+
+use alloy::{
+    consensus::{SidecarBuilder, SimpleCoder, TxType},
+    eips::{eip7702::Authorization, BlockNumberOrTag},
+    network::{Ethereum, TransactionBuilder, TransactionBuilder4844, TransactionBuilder7702},
+    node_bindings::Anvil,
+    primitives::U256,
+    providers::{
+        fillers::{BlobGasFiller, FillerControlFlow, GasFiller, TxFiller},
+        Provider, ProviderBuilder, SendableTx,
+    },
+    rpc::types::TransactionRequest,
+    signers::{local::PrivateKeySigner, SignerSync},
+    transports::{RpcError, TransportResult},
+};
+use eyre::Result;
+
+/// The features of the chain that determine which transaction types it accepts.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct ChainCapabilities {
+    /// EIP-1559 fee market, detected by a base fee in the latest header.
+    pub london: bool,
+    /// EIP-4844 blob transactions, detected by blob gas fields in the latest header.
+    pub cancun: bool,
+    /// EIP-7702 authorization lists, detected by the EIP-7685 requests hash in the latest header.
+    pub prague: bool,
+}
+
+/// A transaction feature that the connected chain does not support.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum UnsupportedFeature {
+    /// The transaction carries a blob sidecar but the chain has not activated Cancun.
+    Blobs,
+    /// The transaction carries an authorization list but the chain has not activated Prague.
+    AuthorizationList,
+}
+
+impl fmt::Display for UnsupportedFeature {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Blobs => f.write_str("blob transactions (EIP-4844) require the Cancun hardfork"),
+            Self::AuthorizationList => {
+                f.write_str("authorization lists (EIP-7702) require the Prague hardfork")
+            }
+        }
+    }
+}
+
+impl std::error::Error for UnsupportedFeature {}
+
+/// The custom filler that selects the transaction type based on the chain capabilities.
+///
+/// The capabilities are fetched from the latest block the first time a transaction is prepared
+/// and cached for future transactions. The filler also takes care of the gas fields, so it
+/// replaces the [`GasFiller`] and [`BlobGasFiller`] in the provider stack.
+///
+/// Transactions that already have a `transaction_type` set keep it, and only get their gas fields
+/// filled.
+#[derive(Clone, Debug, Default)]
+pub struct TxTypeFiller {
+    capabilities: Arc<OnceLock<ChainCapabilities>>,
+}
+
+impl TxTypeFiller {
+    /// Returns the cached chain capabilities, if they have been detected yet.
+    pub fn capabilities(&self) -> Option<ChainCapabilities> {
+        self.capabilities.get().copied()
+    }
+
+    /// Detects the chain capabilities from the latest block header, or returns the cached ones.
+    async fn detect<P: Provider>(&self, provider: &P) -> TransportResult<ChainCapabilities> {
+        if let Some(capabilities) = self.capabilities() {
+            return Ok(capabilities);
+        }
+
+        let block = provider
+            .get_block_by_number(BlockNumberOrTag::Latest)
+            .await?
+            .ok_or(RpcError::NullResp)?;
+        let capabilities = ChainCapabilities {
+            london: block.header.base_fee_per_gas.is_some(),
+            cancun: block.header.excess_blob_gas.is_some(),
+            prague: block.header.requests_hash.is_some(),
+        };
+
+        Ok(*self.capabilities.get_or_init(|| capabilities))
+    }
+
+    /// Picks the transaction type for the request, or returns the feature the chain is missing.
+    const fn select(
+        capabilities: ChainCapabilities,
+        tx: &TransactionRequest,
+    ) -> Result<TxType, UnsupportedFeature> {
+        if tx.authorization_list.is_some() {
+            return if capabilities.prague {
+                Ok(TxType::Eip7702)
+            } else {
+                Err(UnsupportedFeature::AuthorizationList)
+            };
+        }
+
+        if tx.has_eip4844_blob_data() {
+            return if capabilities.cancun {
+                Ok(TxType::Eip4844)
+            } else {
+                Err(UnsupportedFeature::Blobs)
+            };
+        }
+
+        // Without a fee market the request is downgraded to a legacy or access list transaction.
+        if !capabilities.london || tx.gas_price.is_some() {
+            return Ok(if tx.access_list.is_some() { TxType::Eip2930 } else { TxType::Legacy });
+        }
+
+        Ok(TxType::Eip1559)
+    }
+
+    /// Rewrites the fee fields of the request so that it can only be built as `tx_type`.
+    const fn apply(tx_type: TxType, tx: &mut TransactionRequest) {
+        if matches!(tx_type, TxType::Legacy | TxType::Eip2930) {
+            // The fee cap is the most the sender is willing to pay per unit of gas.
+            if tx.gas_price.is_none() {
+                tx.gas_price = tx.max_fee_per_gas;
+            }
+            tx.max_fee_per_gas = None;
+            tx.max_priority_fee_per_gas = None;
+        } else {
+            tx.gas_price = None;
+        }
+    }
+}
+
+/// The fillable type for the [`TxTypeFiller`] filler.
+#[derive(Debug)]
+pub struct TxTypeFillable {
+    tx_type: TxType,
+    gas: <GasFiller as TxFiller>::Fillable,
+    max_fee_per_blob_gas: Option<u128>,
+}
+
+impl TxFiller<Ethereum> for TxTypeFiller {
+    type Fillable = TxTypeFillable;
+
+    fn status(&self, tx: &TransactionRequest) -> FillerControlFlow {
+        if tx.transaction_type.is_none() {
+            return FillerControlFlow::Ready;
+        }
+
+        // The type is already selected, but the gas fields may still be missing.
+        TxFiller::<Ethereum>::status(&GasFiller, tx)
+            .absorb(TxFiller::<Ethereum>::status(&BlobGasFiller::default(), tx))
+    }
+
+    fn fill_sync(&self, _tx: &mut SendableTx<Ethereum>) {}
+
+    // Detects the chain capabilities, selects the transaction type unless the request has one, and
+    // estimates the gas fields for the request as it will look once it has been rewritten to that
+    // type.
+    async fn prepare<P>(
+        &self,
+        provider: &P,
+        tx: &TransactionRequest,
+    ) -> TransportResult<Self::Fillable>
+    where
+        P: Provider,
+    {
+        let tx_type = match tx.transaction_type {
+            Some(tx_type) => TxType::try_from(tx_type).map_err(RpcError::local_usage)?,
+            None => {
+                let capabilities = self.detect(provider).await?;
+                Self::select(capabilities, tx).map_err(RpcError::local_usage)?
+            }
+        };
+
+        let mut tx = tx.clone();
+        Self::apply(tx_type, &mut tx);
+
+        // The gas filler only estimates legacy fees for requests that have a gas price already.
+        if matches!(tx_type, TxType::Legacy | TxType::Eip2930) && tx.gas_price.is_none() {
+            tx.gas_price = Some(provider.get_gas_price().await?);
+        }
+
+        let gas = GasFiller.prepare(provider, &tx).await?;
+        let max_fee_per_blob_gas = if tx_type == TxType::Eip4844 {
+            Some(BlobGasFiller::default().prepare(provider, &tx).await?)
+        } else {
+            None
+        };
+
+        Ok(TxTypeFillable { tx_type, gas, max_fee_per_blob_gas })
+    }
+
+    // Rewrites the request to the selected type and fills in the gas fields.
+    async fn fill(
+        &self,
+        fillable: Self::Fillable,
+        mut tx: SendableTx<Ethereum>,
+    ) -> TransportResult<SendableTx<Ethereum>> {
+        if let Some(builder) = tx.as_mut_builder() {
+            Self::apply(fillable.tx_type, builder);
+            builder.transaction_type = Some(fillable.tx_type as u8);
+            if let Some(max_fee_per_blob_gas) = fillable.max_fee_per_blob_gas {
+                builder.set_max_fee_per_blob_gas(max_fee_per_blob_gas);
+            }
+        }
+
+        GasFiller.fill(fillable.gas, tx).await
+    }
+}
+
+#[tokio::main]
+async fn main() -> Result<()> {
+    for hardfork in ["berlin", "london", "cancun", "prague"] {
+        // Spin up a local Anvil node with the given hardfork enabled.
+        // Ensure `anvil` is available in $PATH.
+        let anvil = Anvil::new().args(["--hardfork", hardfork]).try_spawn()?;
+
+        // Create two users, Alice and Bob.
+        // Alice sends the transactions and Bob signs the authorization.
+        let alice: PrivateKeySigner = anvil.keys()[0].clone().into();
+        let bob: PrivateKeySigner = anvil.keys()[1].clone().into();
+
+        // Instantiate the provider with the `TxTypeFiller` in place of the gas fillers.
+        let filler = TxTypeFiller::default();
+        let provider = ProviderBuilder::new()
+            .disable_recommended_fillers()
+            .filler(filler.clone())
+            .with_cached_nonce_management()
+            .fetch_chain_id()
+            .wallet(alice)
+            .connect_http(anvil.endpoint_url());
+
+        // Request an EIP-1559 transaction, which is downgraded to legacy before London.
+        let tx = TransactionRequest::default()
+            .with_to(bob.address())
+            .with_value(U256::from(100))
+            .with_max_fee_per_gas(20_000_000_000)
+            .with_max_priority_fee_per_gas(1_000_000_000);
+        let receipt = provider.send_transaction(tx).await?.get_receipt().await?;
+
+        let capabilities = filler.capabilities().expect("detected on first transaction");
+        println!("{hardfork}: {capabilities:?}");
+        println!("  fee cap transaction sent as {:?}", receipt.transaction_type());
+        let expected = if capabilities.london { TxType::Eip1559 } else { TxType::Legacy };
+        assert_eq!(receipt.transaction_type(), expected);
+
+        // Request a legacy transaction explicitly, which keeps its type and gets its gas fields.
+        let mut tx =
+            TransactionRequest::default().with_to(bob.address()).with_value(U256::from(100));
+        tx.transaction_type = Some(TxType::Legacy as u8);
+        let receipt = provider.send_transaction(tx).await?.get_receipt().await?;
+        println!("  typed transaction sent as {:?}", receipt.transaction_type());
+        assert_eq!(receipt.transaction_type(), TxType::Legacy);
+
+        // Request a blob transaction, which is rejected before Cancun.
+        let sidecar = SidecarBuilder::<SimpleCoder>::from_slice(b"Blobs are fun!").build()?;
+        let tx = TransactionRequest::default().with_to(bob.address()).with_blob_sidecar(sidecar);
+        match provider.send_transaction(tx).await {
+            Ok(pending_tx) => {
+                let receipt = pending_tx.get_receipt().await?;
+                println!("  blob transaction sent as {:?}", receipt.transaction_type());
+                assert!(capabilities.cancun);
+            }
+            Err(err) => {
+                println!("  blob transaction rejected: {err}");
+                assert!(!capabilities.cancun);
+            }
+        }
+
+        // Request a transaction with an authorization list, which is rejected before Prague.
+        let authorization = Authorization {
+            chain_id: U256::from(anvil.chain_id()),
+            address: bob.address(),
+            nonce: provider.get_transaction_count(bob.address()).await?,
+        };
+        let signature = bob.sign_hash_sync(&authorization.signature_hash())?;
+        let tx = TransactionRequest::default()
+            .with_to(bob.address())
+            .with_authorization_list(vec![authorization.into_signed(signature)]);
+        match provider.send_transaction(tx).await {
+            Ok(pending_tx) => {
+                let receipt = pending_tx.get_receipt().await?;
+                println!(
+                    "  authorization list transaction sent as {:?}",
+                    receipt.transaction_type()
+                );
+                assert!(capabilities.prague);
+            }
+            Err(err) => {
+                println!("  authorization list transaction rejected: {err}");
+                assert!(!capabilities.prague);
+            }
+        }
+    }
+
+    Ok(())
+}
@@ -0,0 +1,710 @@
+//! Example of turning the state diff of a transaction into semantic changes: Ether balance deltas,
+//! ERC-20 balance and allowance changes, nonce bumps, contract creations and a net asset flow per
+//! address.
+//!
+//! Both the `stateDiff` of `trace_call` and `trace_replayTransaction` and the `diffMode` output of
+//! the `prestateTracer` of `debug_traceTransaction` are supported. ERC-20 changes are found by
+//! matching the changed storage slots against the known storage layouts of the registered tokens,
+//! for the holders and spenders that appear in the diff, in the `Transfer` and `Approval` logs of
+//! the transaction or among the labeled addresses, and fall back to the logs for tokens without a
+//! known layout.
+//!
+//! The diffs are read from stored fixtures. The swap is the one of `call_trace_swap.json`, whose
+//! logs are taken from its call trace.
+
+use std::{
+    collections::{BTreeMap, BTreeSet, HashMap, HashSet},
+    fmt::Write,
+};
+
+use alloy::{
+    primitives::{
+        address, keccak256,
+        utils::{format_ether, format_units},
+        Address, Bytes, Log, B256, I256, U256,
+    },
+    rpc::types::trace::{
+        geth::{AccountChangeKind, CallFrame, DiffMode},
+        parity::{Delta, StateDiff, TraceResults},
+    },
+    sol,
+    sol_types::{SolEvent, SolValue},
+};
+use eyre::Result;
+
+sol! {
+    #[allow(missing_docs)]
+    interface IERC20 {
+        event Transfer(address indexed from, address indexed to, uint256 value);
+        event Approval(address indexed owner, address indexed spender, uint256 value);
+    }
+}
+
+/// The changes of an account, normalized from either trace format.
+#[derive(Clone, Debug, Default)]
+pub struct AccountChange {
+    /// Whether the account was modified, created or destroyed.
+    pub kind: AccountChangeKind,
+    /// The balance before and after the transaction, if it changed.
+    pub balance: Option<(U256, U256)>,
+    /// The nonce before and after the transaction, if it changed.
+    pub nonce: Option<(u64, u64)>,
+    /// The code after the transaction, if it changed.
+    pub code: Option<Bytes>,
+    /// The changed storage slots, with their values before and after the transaction.
+    pub storage: BTreeMap<B256, (B256, B256)>,
+}
+
+/// The changes of all accounts touched by a transaction.
+#[derive(Clone, Debug, Default)]
+pub struct StateChanges(pub BTreeMap<Address, AccountChange>);
+
+impl StateChanges {
+    /// Normalizes the `stateDiff` of a parity-style trace.
+    pub fn from_state_diff(diff: &StateDiff) -> Self {
+        fn change<T: Clone + Default>(delta: &Delta<T>) -> Option<(T, T)> {
+            match delta {
+                Delta::Unchanged => None,
+                Delta::Added(after) => Some((T::default(), after.clone())),
+                Delta::Removed(before) => Some((before.clone(), T::default())),
+                Delta::Changed(changed) => Some((changed.from.clone(), changed.to.clone())),
+            }
+        }
+
+        let accounts = diff
+            .0
+            .iter()
+            .map(|(address, diff)| {
+                let kind = if diff.balance.is_added() || diff.nonce.is_added() {
+                    AccountChangeKind::Create
+                } else if diff.balance.is_removed() || diff.nonce.is_removed() {
+                    AccountChangeKind::SelfDestruct
+                } else {
+                    AccountChangeKind::Modify
+                };
+                let change = AccountChange {
+                    kind,
+                    balance: change(&diff.balance),
+                    nonce: change(&diff.nonce).map(|(before, after)| (before.to(), after.to())),
+                    code: change(&diff.code).map(|(_, after)| after),
+                    storage: diff
+                        .storage
+                        .iter()
+                        .filter_map(|(slot, delta)| Some((*slot, change(delta)?)))
+                        .collect(),
+                };
+                (*address, change)
+            })
+            .collect();
+        Self(accounts).retain_changed()
+    }
+
+    /// Normalizes the `diffMode` output of the `prestateTracer`.
+    ///
+    /// The pre state holds the previous values of the changed accounts and the post state only
+    /// the fields that changed. Accounts missing from the pre state were created and accounts
+    /// missing from the post state were destroyed. Storage slots are omitted where they are zero.
+    pub fn from_prestate_diff(diff: &DiffMode) -> Self {
+        let addresses: BTreeSet<_> = diff.pre.keys().chain(diff.post.keys()).copied().collect();
+        let accounts = addresses
+            .into_iter()
+            .map(|address| {
+                let (pre, post) = (diff.pre.get(&address), diff.post.get(&address));
+                let kind = match (pre, post) {
+                    (None, _) => AccountChangeKind::Create,
+                    (_, None) => AccountChangeKind::SelfDestruct,
+                    _ => AccountChangeKind::Modify,
+                };
+                let pre = pre.cloned().unwrap_or_default();
+                let post = post.cloned().unwrap_or_default();
+                let destroyed = kind.is_selfdestruct();
+
+                let balance_before = pre.balance.unwrap_or_default();
+                let nonce_before = pre.nonce.unwrap_or_default();
+                let slots: BTreeSet<_> = pre.storage.keys().chain(post.storage.keys()).collect();
+                let change = AccountChange {
+                    kind,
+                    balance: post
+                        .balance
+                        .or_else(|| destroyed.then_some(U256::ZERO))
+                        .map(|after| (balance_before, after)),
+                    nonce: post
+                        .nonce
+                        .or_else(|| destroyed.then_some(0))
+                        .map(|after| (nonce_before, after)),
+                    code: post.code.clone(),
+                    storage: slots
+                        .into_iter()
+                        .map(|slot| {
+                            let before = pre.storage.get(slot).copied().unwrap_or_default();
+                            let after = post.storage.get(slot).copied().unwrap_or_default();
+                            (*slot, (before, after))
+                        })
+                        .collect(),
+                };
+                (address, change)
+            })
+            .collect();
+        Self(accounts).retain_changed()
+    }
+
+    /// Drops the values that did not actually change.
+    fn retain_changed(mut self) -> Self {
+        for change in self.0.values_mut() {
+            change.balance = change.balance.filter(|(before, after)| before != after);
+            change.nonce = change.nonce.filter(|(before, after)| before != after);
+            change.storage.retain(|_, (before, after)| before != after);
+        }
+        self
+    }
+}
+
+/// The storage slots of the balance and allowance mappings of an ERC-20 token.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct TokenLayout {
+    /// The slot of `mapping(address => uint256)` holding the balances.
+    pub balances_slot: u64,
+    /// The slot of `mapping(address => mapping(address => uint256))` holding the allowances.
+    pub allowances_slot: u64,
+}
+
+impl TokenLayout {
+    /// The layout of `ERC20` from `@openzeppelin/contracts`.
+    pub const OPENZEPPELIN: Self = Self { balances_slot: 0, allowances_slot: 1 };
+    /// The layout of `WETH9`.
+    pub const WETH9: Self = Self { balances_slot: 3, allowances_slot: 4 };
+    /// The layout of Circle's `FiatTokenV2_2`, behind the USDC proxy.
+    pub const FIAT_TOKEN: Self = Self { balances_slot: 9, allowances_slot: 10 };
+
+    /// Returns the slot of the balance of `holder`.
+    pub fn balance_slot(&self, holder: Address) -> B256 {
+        keccak256((holder, U256::from(self.balances_slot)).abi_encode())
+    }
+
+    /// Returns the slot of the allowance of `spender` over the tokens of `owner`.
+    pub fn allowance_slot(&self, owner: Address, spender: Address) -> B256 {
+        let inner = keccak256((owner, U256::from(self.allowances_slot)).abi_encode());
+        keccak256((spender, inner).abi_encode())
+    }
+}
+
+/// A registered ERC-20 token.
+#[derive(Clone, Debug)]
+pub struct TokenInfo {
+    /// The symbol of the token.
+    pub symbol: String,
+    /// The decimals of the token.
+    pub decimals: u8,
+    /// The storage layout of the token, if it is known.
+    pub layout: Option<TokenLayout>,
+}
+
+/// Where a token change was found.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ChangeSource {
+    /// The change was read from the storage diff, with exact values before and after.
+    Storage,
+    /// The change was derived from the logs, as the layout of the token is unknown.
+    Logs,
+}
+
+/// A change of the token balance of a holder.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct TokenBalanceChange {
+    /// The token.
+    pub token: Address,
+    /// The holder.
+    pub holder: Address,
+    /// The balance before and after the transaction, if read from storage.
+    pub values: Option<(U256, U256)>,
+    /// The change of the balance.
+    pub delta: I256,
+    /// The change according to the `Transfer` logs, if any were emitted for the holder.
+    pub logged: Option<I256>,
+    /// Where the change was found.
+    pub source: ChangeSource,
+}
+
+/// A change of the allowance of a spender.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct AllowanceChange {
+    /// The token.
+    pub token: Address,
+    /// The owner of the tokens.
+    pub owner: Address,
+    /// The spender.
+    pub spender: Address,
+    /// The allowance before the transaction, if read from storage.
+    pub before: Option<U256>,
+    /// The allowance after the transaction.
+    pub after: U256,
+    /// Where the change was found.
+    pub source: ChangeSource,
+}
+
+/// An asset held by an address.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Asset {
+    /// Ether.
+    Ether,
+    /// An ERC-20 token.
+    Token(Address),
+}
+
+/// A changed storage slot that could not be attributed to a token.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct StorageChange {
+    /// The account owning the slot.
+    pub address: Address,
+    /// The slot.
+    pub slot: B256,
+    /// The value before the transaction.
+    pub before: B256,
+    /// The value after the transaction.
+    pub after: B256,
+}
+
+/// The semantic changes of a transaction.
+#[derive(Clone, Debug, Default)]
+pub struct StateDiffReport {
+    /// The change of the Ether balance of every account, including gas payments.
+    pub balance_deltas: BTreeMap<Address, I256>,
+    /// The nonces before and after the transaction.
+    pub nonce_bumps: BTreeMap<Address, (u64, u64)>,
+    /// The created contracts, with their code.
+    pub created: BTreeMap<Address, Bytes>,
+    /// The destroyed accounts.
+    pub destroyed: BTreeSet<Address>,
+    /// The changes of token balances.
+    pub token_balances: Vec<TokenBalanceChange>,
+    /// The changes of token allowances.
+    pub allowances: Vec<AllowanceChange>,
+    /// The storage changes that could not be attributed to a token.
+    pub unexplained_storage: Vec<StorageChange>,
+}
+
+impl StateDiffReport {
+    /// Returns the net change of every asset of every address.
+    pub fn net_flows(&self) -> BTreeMap<Address, BTreeMap<Asset, I256>> {
+        let mut flows: BTreeMap<Address, BTreeMap<Asset, I256>> = BTreeMap::new();
+        for (address, delta) in &self.balance_deltas {
+            *flows.entry(*address).or_default().entry(Asset::Ether).or_default() += *delta;
+        }
+        for change in &self.token_balances {
+            *flows
+                .entry(change.holder)
+                .or_default()
+                .entry(Asset::Token(change.token))
+                .or_default() += change.delta;
+        }
+        for assets in flows.values_mut() {
+            assets.retain(|_, delta| !delta.is_zero());
+        }
+        flows.retain(|_, assets| !assets.is_empty());
+        flows
+    }
+}
+
+/// Returns the signed difference between `before` and `after`.
+const fn signed_delta(before: U256, after: U256) -> I256 {
+    I256::from_raw(after.wrapping_sub(before))
+}
+
+/// Returns the logs emitted in `frame` and its sub-calls, in execution order.
+pub fn logs_from_call_frame(frame: &CallFrame) -> Vec<Log> {
+    fn collect(frame: &CallFrame, logs: &mut Vec<Log>) {
+        let mut calls = frame.calls.iter();
+        let mut position = 0;
+        for log in &frame.logs {
+            let until = log.position.unwrap_or_default();
+            for call in calls.by_ref().take(until.saturating_sub(position) as usize) {
+                collect(call, logs);
+            }
+            position = until;
+            logs.push(Log::new_unchecked(
+                log.address.unwrap_or_default(),
+                log.topics.clone().unwrap_or_default(),
+                log.data.clone().unwrap_or_default(),
+            ));
+        }
+        for call in calls {
+            collect(call, logs);
+        }
+    }
+
+    let mut logs = Vec::new();
+    collect(frame, &mut logs);
+    logs
+}
+
+/// Turns state diffs into semantic changes with registered tokens and labels.
+#[derive(Debug, Default)]
+pub struct StateDiffAnalyzer {
+    tokens: HashMap<Address, TokenInfo>,
+    labels: HashMap<Address, String>,
+}
+
+impl StateDiffAnalyzer {
+    /// Registers the ERC-20 token at `address`, with its storage layout if it is known.
+    pub fn register_token(
+        &mut self,
+        address: Address,
+        symbol: &str,
+        decimals: u8,
+        layout: Option<TokenLayout>,
+    ) {
+        self.tokens.insert(address, TokenInfo { symbol: symbol.to_string(), decimals, layout });
+        self.label(address, symbol);
+    }
+
+    /// Labels `address` with `label`.
+    pub fn label(&mut self, address: Address, label: &str) {
+        self.labels.insert(address, label.to_string());
+    }
+
+    /// Analyzes `changes`, with the `logs` emitted by the transaction.
+    ///
+    /// Spenders such as routers rarely appear in the diff or in the logs, so they must be labeled
+    /// for their allowances to be found.
+    pub fn analyze(&self, changes: &StateChanges, logs: &[Log]) -> StateDiffReport {
+        let mut report = StateDiffReport::default();
+        for (address, change) in &changes.0 {
+            if let Some((before, after)) = change.balance {
+                report.balance_deltas.insert(*address, signed_delta(before, after));
+            }
+            if let Some(nonce) = change.nonce {
+                report.nonce_bumps.insert(*address, nonce);
+            }
+            match change.kind {
+                AccountChangeKind::Create => {
+                    report.created.insert(*address, change.code.clone().unwrap_or_default());
+                }
+                AccountChangeKind::SelfDestruct => {
+                    report.destroyed.insert(*address);
+                }
+                AccountChangeKind::Modify => {}
+            }
+        }
+
+        // The token movements and approvals according to the logs.
+        let mut logged: BTreeMap<(Address, Address), I256> = BTreeMap::new();
+        let mut approved: BTreeMap<(Address, Address, Address), U256> = BTreeMap::new();
+        let mut candidates: BTreeSet<Address> =
+            changes.0.keys().chain(self.labels.keys()).copied().collect();
+        for log in logs {
+            if let Ok(transfer) = IERC20::Transfer::decode_log_data(&log.data) {
+                let value = I256::from_raw(transfer.value);
+                for (holder, delta) in [(transfer.from, -value), (transfer.to, value)] {
+                    if !holder.is_zero() {
+                        *logged.entry((log.address, holder)).or_default() += delta;
+                        candidates.insert(holder);
+                    }
+                }
+            } else if let Ok(approval) = IERC20::Approval::decode_log_data(&log.data) {
+                approved.insert((log.address, approval.owner, approval.spender), approval.value);
+                candidates.extend([approval.owner, approval.spender]);
+            }
+        }
+
+        // The exact changes of the tokens with a known layout, from their storage.
+        let mut explained = HashSet::new();
+        for (token, info) in &self.tokens {
+            let (Some(layout), Some(change)) = (info.layout, changes.0.get(token)) else {
+                continue;
+            };
+            for holder in &candidates {
+                let slot = layout.balance_slot(*holder);
+                if let Some(&(before, after)) = change.storage.get(&slot) {
+                    let (before, after) = (before.into(), after.into());
+                    report.token_balances.push(TokenBalanceChange {
+                        token: *token,
+                        holder: *holder,
+                        values: Some((before, after)),
+                        delta: signed_delta(before, after),
+                        logged: logged.remove(&(*token, *holder)),
+                        source: ChangeSource::Storage,
+                    });
+                    explained.insert((*token, slot));
+                }
+                for spender in &candidates {
+                    let slot = layout.allowance_slot(*holder, *spender);
+                    if let Some(&(before, after)) = change.storage.get(&slot) {
+                        approved.remove(&(*token, *holder, *spender));
+                        report.allowances.push(AllowanceChange {
+                            token: *token,
+                            owner: *holder,
+                            spender: *spender,
+                            before: Some(before.into()),
+                            after: after.into(),
+                            source: ChangeSource::Storage,
+                        });
+                        explained.insert((*token, slot));
+                    }
+                }
+            }
+        }
+
+        // The remaining changes can only be taken from the logs.
+        report.token_balances.extend(logged.into_iter().map(|((token, holder), delta)| {
+            TokenBalanceChange {
+                token,
+                holder,
+                values: None,
+                delta,
+                logged: Some(delta),
+                source: ChangeSource::Logs,
+            }
+        }));
+        report.allowances.extend(approved.into_iter().map(|((token, owner, spender), after)| {
+            AllowanceChange {
+                token,
+                owner,
+                spender,
+                before: None,
+                after,
+                source: ChangeSource::Logs,
+            }
+        }));
+        report.token_balances.retain(|change| !change.delta.is_zero());
+        report.token_balances.sort_by_key(|change| (change.token, change.holder));
+        report.allowances.sort_by_key(|change| (change.token, change.owner, change.spender));
+
+        for (address, change) in &changes.0 {
+            for (slot, (before, after)) in &change.storage {
+                if !explained.contains(&(*address, *slot)) {
+                    report.unexplained_storage.push(StorageChange {
+                        address: *address,
+                        slot: *slot,
+                        before: *before,
+                        after: *after,
+                    });
+                }
+            }
+        }
+        report
+    }
+
+    /// Returns the label of `address`, or the address itself.
+    fn name(&self, address: &Address) -> String {
+        self.labels.get(address).cloned().unwrap_or_else(|| address.to_string())
+    }
+
+    /// Formats `amount` of `asset` in whole units.
+    fn format_amount(&self, asset: Asset, amount: I256) -> String {
+        let sign = if amount.is_negative() { "-" } else { "+" };
+        let amount = amount.unsigned_abs();
+        match asset {
+            Asset::Ether => format!("{sign}{} ETH", format_ether(amount)),
+            Asset::Token(token) => match self.tokens.get(&token) {
+                Some(info) => format!(
+                    "{sign}{} {}",
+                    format_units(amount, info.decimals).unwrap_or_default(),
+                    info.symbol
+                ),
+                None => format!("{sign}{amount} of {token}"),
+            },
+        }
+    }
+
+    /// Formats the unsigned `amount` of `token` in whole units.
+    fn format_token(&self, token: Address, amount: U256) -> String {
+        let decimals = self.tokens.get(&token).map_or(0, |info| info.decimals);
+        format_units(amount, decimals).unwrap_or_default()
+    }
+
+    /// Renders `report` for humans.
+    pub fn render(&self, report: &StateDiffReport) -> String {
+        let mut out = String::new();
+        if !report.balance_deltas.is_empty() {
+            let _ = writeln!(out, "Ether balances:");
+            for (address, delta) in &report.balance_deltas {
+                let _ = writeln!(
+                    out,
+                    "  {}: {}",
+                    self.name(address),
+                    self.format_amount(Asset::Ether, *delta)
+                );
+            }
+        }
+        if !report.nonce_bumps.is_empty() {
+            let _ = writeln!(out, "Nonces:");
+            for (address, (before, after)) in &report.nonce_bumps {
+                let _ = writeln!(out, "  {}: {before} → {after}", self.name(address));
+            }
+        }
+        if !report.created.is_empty() || !report.destroyed.is_empty() {
+            let _ = writeln!(out, "Accounts:");
+            for (address, code) in &report.created {
+                let _ = writeln!(
+                    out,
+                    "  created {} with {} bytes of code",
+                    self.name(address),
+                    code.len()
+                );
+            }
+            for address in &report.destroyed {
+                let _ = writeln!(out, "  destroyed {}", self.name(address));
+            }
+        }
+        if !report.token_balances.is_empty() {
+            let _ = writeln!(out, "Token balances:");
+            for change in &report.token_balances {
+                let delta = self.format_amount(Asset::Token(change.token), change.delta);
+                let mut line = format!("  {}: {delta}", self.name(&change.holder));
+                if let Some((before, after)) = change.values {
+                    let before = self.format_token(change.token, before);
+                    let after = self.format_token(change.token, after);
+                    let _ = write!(line, " ({before} → {after})");
+                }
+                match (change.source, change.logged) {
+                    (ChangeSource::Logs, _) => line.push_str(" [logs]"),
+                    (ChangeSource::Storage, None) => line.push_str(" [storage, no Transfer log]"),
+                    (ChangeSource::Storage, Some(logged)) if logged != change.delta => {
+                        let logged = self.format_amount(Asset::Token(change.token), logged);
+                        let _ = write!(line, " [storage, Transfer logs say {logged}]");
+                    }
+                    (ChangeSource::Storage, Some(_)) => line.push_str(" [storage]"),
+                }
+                let _ = writeln!(out, "{line}");
+            }
+        }
+        if !report.allowances.is_empty() {
+            let _ = writeln!(out, "Allowances:");
+            for change in &report.allowances {
+                let symbol = self.name(&change.token);
+                let after = self.format_token(change.token, change.after);
+                let values = match change.before {
+                    Some(before) => {
+                        format!("{} → {after}", self.format_token(change.token, before))
+                    }
+                    None => format!("→ {after}"),
+                };
+                let _ = writeln!(
+                    out,
+                    "  {symbol} {} → {}: {values}",
+                    self.name(&change.owner),
+                    self.name(&change.spender)
+                );
+            }
+        }
+        if !report.unexplained_storage.is_empty() {
+            let _ = writeln!(out, "Other storage:");
+            for change in &report.unexplained_storage {
+                let _ = writeln!(
+                    out,
+                    "  {}[{}]: {} → {}",
+                    self.name(&change.address),
+                    change.slot,
+                    change.before,
+                    change.after
+                );
+            }
+        }
+        let flows = report.net_flows();
+        if !flows.is_empty() {
+            let _ = writeln!(out, "Net asset flows:");
+            for (address, assets) in flows {
+                let assets: Vec<_> = assets
+                    .into_iter()
+                    .map(|(asset, delta)| self.format_amount(asset, delta))
+                    .collect();
+                let _ = writeln!(out, "  {}: {}", self.name(&address), assets.join(", "));
+            }
+        }
+        out
+    }
+}
+
+#[tokio::main]
+async fn main() -> Result<()> {
+    let sender = address!("8ba1f109551bD432803012645Ac136ddd64DBA72");
+    let router = address!("7a250d5630B4cF539739dF2C5dAcb4c659F2488D");
+    let pair = address!("B4e16d0168e52d35CaCD2c6185b44281Ec28C9Dc");
+    let usdc = address!("A0b86991c6218b36c1d19D4a2e9Eb0cE3606eB48");
+    let weth = address!("C02aaA39b223FE8D0A0e5C4F27eAD9083C756Cc2");
+    let coinbase = address!("95222290DD7278Aa3Ddd389Cc1E1d165CC4BAfe5");
+
+    let mut analyzer = StateDiffAnalyzer::default();
+    analyzer.register_token(usdc, "USDC", 6, Some(TokenLayout::FIAT_TOKEN));
+    analyzer.register_token(weth, "WETH", 18, Some(TokenLayout::WETH9));
+    analyzer.label(sender, "sender");
+    analyzer.label(router, "UniswapV2Router02");
+    analyzer.label(pair, "UniswapV2Pair USDC/WETH");
+    analyzer.label(coinbase, "coinbase");
+
+    let fixtures = std::env::current_dir()?.join("examples/transactions/examples/fixtures");
+    let read = |name: &str| -> Result<String> { Ok(std::fs::read_to_string(fixtures.join(name))?) };
+
+    // A swap of 1000 USDC for WETH through the Uniswap V2 router, traced with the
+    // `prestateTracer` in diff mode. The logs come from the `callTracer` trace of the same swap.
+    let diff: DiffMode = serde_json::from_str(&read("prestate_diff_swap.json")?)?;
+    let trace: CallFrame = serde_json::from_str(&read("call_trace_swap.json")?)?;
+    let logs = logs_from_call_frame(&trace);
+    assert_eq!(logs.len(), 4);
+
+    let report = analyzer.analyze(&StateChanges::from_prestate_diff(&diff), &logs);
+    println!("{}", analyzer.render(&report));
+
+    let fee = I256::try_from(131_046u64 * 12_000_000_000)?;
+    assert_eq!(report.balance_deltas[&sender], -fee);
+    assert_eq!(report.nonce_bumps[&sender], (7, 8));
+    let usdc_in = I256::try_from(1_000_000_000u64)?;
+    let weth_out = I256::try_from(332_322_289_155_923_718u64)?;
+    assert!(report.token_balances.iter().all(|change| change.source == ChangeSource::Storage));
+    assert!(report.token_balances.iter().all(|change| change.logged == Some(change.delta)));
+    assert_eq!(
+        report.allowances,
+        [AllowanceChange {
+            token: usdc,
+            owner: sender,
+            spender: router,
+            before: Some(U256::from(10_000_000_000u64)),
+            after: U256::from(9_000_000_000u64),
+            source: ChangeSource::Storage,
+        }]
+    );
+    // Only the packed reserves of the pair are not token balances.
+    assert_eq!(report.unexplained_storage.len(), 1);
+    assert_eq!(report.unexplained_storage[0].address, pair);
+
+    let flows = report.net_flows();
+    assert_eq!(
+        flows[&sender],
+        BTreeMap::from([
+            (Asset::Ether, -fee),
+            (Asset::Token(weth), weth_out),
+            (Asset::Token(usdc), -usdc_in),
+        ])
+    );
+    assert_eq!(
+        flows[&pair],
+        BTreeMap::from([(Asset::Token(weth), -weth_out), (Asset::Token(usdc), usdc_in)])
+    );
+    assert!(!flows.contains_key(&router));
+
+    // The deployment of the `ERC20Example` token, traced with `trace_call` and the `stateDiff`
+    // trace type. The constructor mints 1000 XYZ to the deployer.
+    let results: TraceResults = serde_json::from_str(&read("state_diff_deploy.json")?)?;
+    let deployer = address!("f39Fd6e51aad88F6F4ce6aB8827279cffFb92266");
+    let token = deployer.create(0);
+    analyzer.register_token(token, "XYZ", 18, Some(TokenLayout::OPENZEPPELIN));
+    analyzer.label(deployer, "deployer");
+
+    let supply = U256::from(1000u64) * U256::from(10u64).pow(U256::from(18));
+    let mint = IERC20::Transfer { from: Address::ZERO, to: deployer, value: supply };
+    let logs = [Log { address: token, data: mint.encode_log_data() }];
+
+    let changes = StateChanges::from_state_diff(results.state_diff.as_ref().unwrap());
+    let report = analyzer.analyze(&changes, &logs);
+    println!("{}", analyzer.render(&report));
+
+    assert_eq!(report.created.keys().collect::<Vec<_>>(), [&token]);
+    assert_eq!(report.nonce_bumps[&deployer], (0, 1));
+    assert_eq!(report.token_balances.len(), 1);
+    assert_eq!(report.token_balances[0].values, Some((U256::ZERO, supply)));
+    // The total supply, name and symbol.
+    assert_eq!(report.unexplained_storage.len(), 3);
+    assert_eq!(report.net_flows()[&deployer][&Asset::Token(token)], I256::from_raw(supply));
+
+    Ok(())
+}
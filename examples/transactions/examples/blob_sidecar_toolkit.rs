@@ -0,0 +1,201 @@
+//! Example of packing a large payload into [EIP-4844](https://github.com/ethereum/EIPs/blob/master/EIPS/eip-4844.md)
+//! and [EIP-7594](https://github.com/ethereum/EIPs/blob/master/EIPS/eip-7594.md) blob sidecars,
+//! verifying their versioned hashes and decoding the payload back from the node.
+
+use alloy::{
+    consensus::{
+        BlobTransactionSidecar, BlobTransactionSidecarVariant, EnvKzgSettings, SidecarBuilder,
+        SimpleCoder, Transaction,
+    },
+    eips::{
+        eip4844::{
+            builder::{BuildableSidecar, SidecarCoder},
+            Blob, DATA_GAS_PER_BLOB, FIELD_ELEMENTS_PER_BLOB,
+        },
+        eip7594::BlobTransactionSidecarEip7594,
+        eip7840::BlobParams,
+        BlockNumberOrTag,
+    },
+    network::TransactionBuilder,
+    primitives::{TxHash, B256},
+    providers::{ext::AnvilApi, Provider, ProviderBuilder},
+    rpc::types::TransactionRequest,
+};
+use eyre::{bail, ensure, OptionExt, Result};
+
+/// Usable bytes per field element with the [`SimpleCoder`], the first byte is always zero.
+const BYTES_PER_FIELD_ELEMENT: usize = 31;
+
+/// Packs payloads into blob sidecars, verifies them and decodes them back.
+#[derive(Clone, Copy, Debug)]
+pub struct BlobToolkit {
+    params: BlobParams,
+}
+
+impl BlobToolkit {
+    /// Creates a new [`BlobToolkit`] for a chain with the given blob parameters.
+    pub const fn new(params: BlobParams) -> Self {
+        Self { params }
+    }
+
+    /// Returns the largest payload that fits in the blobs of a single transaction.
+    ///
+    /// The [`SimpleCoder`] stores the payload length in the first field element.
+    pub const fn max_bytes_per_tx(&self) -> usize {
+        (self.params.max_blobs_per_tx as usize * FIELD_ELEMENTS_PER_BLOB as usize - 1)
+            * BYTES_PER_FIELD_ELEMENT
+    }
+
+    /// Splits `data` across as many EIP-4844 sidecars as needed, one per transaction.
+    pub fn pack_4844(&self, data: &[u8]) -> Result<Vec<BlobTransactionSidecarVariant>> {
+        self.pack::<BlobTransactionSidecar>(data, BlobTransactionSidecarVariant::Eip4844)
+    }
+
+    /// Splits `data` across as many EIP-7594 sidecars, carrying cell proofs, as needed.
+    pub fn pack_7594(&self, data: &[u8]) -> Result<Vec<BlobTransactionSidecarVariant>> {
+        self.pack::<BlobTransactionSidecarEip7594>(data, BlobTransactionSidecarVariant::Eip7594)
+    }
+
+    fn pack<S: BuildableSidecar>(
+        &self,
+        data: &[u8],
+        variant: fn(S) -> BlobTransactionSidecarVariant,
+    ) -> Result<Vec<BlobTransactionSidecarVariant>> {
+        ensure!(!data.is_empty(), "cannot pack an empty payload");
+
+        let mut sidecars = Vec::new();
+        for chunk in data.chunks(self.max_bytes_per_tx()) {
+            let sidecar = SidecarBuilder::<SimpleCoder>::from_slice(chunk).build::<S>()?;
+            sidecars.push(variant(sidecar));
+        }
+
+        Ok(sidecars)
+    }
+
+    /// Verifies the KZG proofs of the sidecar against the versioned hashes of its transaction.
+    pub fn verify(
+        sidecar: &BlobTransactionSidecarVariant,
+        versioned_hashes: &[B256],
+    ) -> Result<()> {
+        sidecar.validate(versioned_hashes, EnvKzgSettings::Default.get())?;
+        Ok(())
+    }
+
+    /// Decodes the payload stored in the blobs of a single transaction.
+    pub fn unpack(blobs: &[Blob]) -> Result<Vec<u8>> {
+        let items = SimpleCoder::default()
+            .decode_all(blobs)
+            .ok_or_eyre("blobs are not SimpleCoder encoded")?;
+        Ok(items.concat())
+    }
+
+    /// Fetches the blobs of a transaction from Anvil, checks them against the versioned hashes
+    /// committed in the transaction and decodes the payload.
+    ///
+    /// The blobs are fetched with `anvil_get_blobs_by_tx_hash`, which only Anvil implements.
+    /// Execution clients do not serve blobs over JSON-RPC, so on other networks they have to be
+    /// fetched from the `blob_sidecars` endpoint of a beacon node instead.
+    pub async fn retrieve<P: Provider>(provider: &P, tx_hash: TxHash) -> Result<Vec<u8>> {
+        let tx =
+            provider.get_transaction_by_hash(tx_hash).await?.ok_or_eyre("transaction not found")?;
+        let Some(versioned_hashes) = tx.blob_versioned_hashes() else {
+            bail!("transaction {tx_hash} is not a blob transaction");
+        };
+
+        let blobs =
+            provider.anvil_get_blobs_by_tx_hash(tx_hash).await?.ok_or_eyre("blobs not found")?;
+
+        // Recompute the commitments, as the node does not return them with the blobs.
+        let sidecar = BlobTransactionSidecar::try_from_blobs_with_settings(
+            blobs,
+            EnvKzgSettings::Default.get(),
+        )?;
+        ensure!(
+            sidecar.versioned_hashes().eq(versioned_hashes.iter().copied()),
+            "blobs of {tx_hash} do not match its versioned hashes"
+        );
+
+        Self::unpack(&sidecar.blobs)
+    }
+
+    /// Estimates the blob fee of a transaction with `blob_count` blobs at `excess_blob_gas`.
+    pub const fn blob_fee(&self, excess_blob_gas: u64, blob_count: usize) -> u128 {
+        self.params.calc_blob_fee(excess_blob_gas)
+            * (DATA_GAS_PER_BLOB as u128 * blob_count as u128)
+    }
+}
+
+#[tokio::main]
+async fn main() -> Result<()> {
+    // Spin up a local Anvil node with the Prague hardfork enabled.
+    // Ensure `anvil` is available in $PATH.
+    let provider = ProviderBuilder::new()
+        .connect_anvil_with_wallet_and_config(|anvil| anvil.args(["--hardfork", "prague"]))?;
+
+    // The wallet sends from the first Anvil account, Alice, to the second one, Bob.
+    let accounts = provider.get_accounts().await?;
+    let bob = accounts[1];
+
+    // Create a payload that does not fit in the blobs of a single transaction.
+    let toolkit = BlobToolkit::new(BlobParams::prague());
+    let payload: Vec<u8> =
+        (0..toolkit.max_bytes_per_tx() + 100_000).map(|i| (i % 251) as u8).collect();
+
+    let sidecars = toolkit.pack_4844(&payload)?;
+    println!("Packed {} bytes into {} sidecars", payload.len(), sidecars.len());
+    assert_eq!(sidecars.len(), 2);
+
+    // Estimate the blob fee from the excess blob gas of the latest block.
+    let block = provider
+        .get_block_by_number(BlockNumberOrTag::Latest)
+        .await?
+        .ok_or_eyre("latest block not found")?;
+    let excess_blob_gas = block.header.excess_blob_gas.unwrap_or_default();
+    let blob_base_fee = provider.get_blob_base_fee().await?;
+    println!("Blob base fee: {blob_base_fee} wei");
+    assert_eq!(BlobParams::prague().calc_blob_fee(excess_blob_gas), blob_base_fee);
+
+    let mut tx_hashes = Vec::with_capacity(sidecars.len());
+    for sidecar in sidecars {
+        let versioned_hashes: Vec<B256> = sidecar.versioned_hashes().collect();
+        BlobToolkit::verify(&sidecar, &versioned_hashes)?;
+
+        println!(
+            "Estimated blob fee for {} blobs: {} wei",
+            versioned_hashes.len(),
+            toolkit.blob_fee(excess_blob_gas, versioned_hashes.len())
+        );
+
+        // Build a transaction to send the sidecar from Alice to Bob.
+        let mut tx = TransactionRequest::default().with_to(bob);
+        tx.sidecar = Some(sidecar);
+        tx.populate_blob_hashes();
+
+        let receipt = provider.send_transaction(tx).await?.get_receipt().await?;
+        assert_eq!(receipt.blob_gas_used, Some(DATA_GAS_PER_BLOB * versioned_hashes.len() as u64));
+        tx_hashes.push(receipt.transaction_hash);
+    }
+
+    // Reconstruct the payload from the blobs stored by the node.
+    let mut retrieved = Vec::with_capacity(payload.len());
+    for tx_hash in tx_hashes {
+        retrieved.extend(BlobToolkit::retrieve(&provider, tx_hash).await?);
+    }
+    assert_eq!(retrieved, payload);
+    println!("Retrieved {} bytes from the node", retrieved.len());
+
+    // After Osaka a transaction carries at most 6 blobs, with cell proofs instead of blob proofs.
+    let toolkit = BlobToolkit::new(BlobParams::osaka());
+    let sidecars = toolkit.pack_7594(&payload)?;
+    println!("Packed {} bytes into {} EIP-7594 sidecars", payload.len(), sidecars.len());
+
+    let mut unpacked = Vec::with_capacity(payload.len());
+    for sidecar in &sidecars {
+        let versioned_hashes: Vec<B256> = sidecar.versioned_hashes().collect();
+        BlobToolkit::verify(sidecar, &versioned_hashes)?;
+        unpacked.extend(BlobToolkit::unpack(sidecar.blobs())?);
+    }
+    assert_eq!(unpacked, payload);
+
+    Ok(())
+}
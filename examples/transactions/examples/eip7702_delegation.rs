@@ -0,0 +1,209 @@
+//! Example of a toolkit to sign, inspect and revoke [EIP-7702](https://github.com/ethereum/EIPs/blob/master/EIPS/eip-7702.md) code delegations.
+
+use std::collections::HashSet;
+
+use alloy::{
+    eips::eip7702::{constants::EIP7702_DELEGATION_DESIGNATOR, Authorization, SignedAuthorization},
+    network::{EthereumWallet, TransactionBuilder, TransactionBuilder7702},
+    node_bindings::Anvil,
+    primitives::{Address, Bytes, U256},
+    providers::{Provider, ProviderBuilder},
+    rpc::types::{TransactionReceipt, TransactionRequest},
+    signers::{local::PrivateKeySigner, Signer},
+    sol,
+    sol_types::SolCall,
+};
+use eyre::{ensure, Result};
+
+// Codegen from embedded Solidity code and precompiled bytecode.
+// solc v0.8.25 Log.sol --via-ir --optimize --bin
+sol!(
+    #[allow(missing_docs)]
+    #[sol(rpc, bytecode = "6080806040523460135760c9908160188239f35b5f80fdfe6004361015600b575f80fd5b5f3560e01c80637b3ab2d014605f57639ee1a440146027575f80fd5b34605b575f366003190112605b577f2d67bb91f17bca05af6764ab411e86f4ddf757adb89fcec59a7d21c525d417125f80a1005b5f80fd5b34605b575f366003190112605b577fbcdfe0d5b27dd186282e187525415c57ea3077c34efb39148111e4d342e7ab0e5f80a100fea2646970667358221220f6b42b522bc9fb2b4c7d7e611c7c3e995d057ecab7fd7be4179712804c886b4f64736f6c63430008190033")]
+    contract Log {
+        #[derive(Debug)]
+        event Hello();
+        event World();
+
+        function emitHello() public {
+            emit Hello();
+        }
+
+        function emitWorld() public {
+            emit World();
+        }
+    }
+);
+
+/// Parses the delegated address from the code of an account, which is `0xef0100 || address`
+/// for a delegated EOA.
+pub fn parse_delegation(code: &[u8]) -> Option<Address> {
+    let address = code.strip_prefix(EIP7702_DELEGATION_DESIGNATOR.as_slice())?;
+    (address.len() == Address::len_bytes()).then(|| Address::from_slice(address))
+}
+
+/// Signs, inspects and revokes EIP-7702 delegations of externally owned accounts.
+#[derive(Debug)]
+pub struct DelegationToolkit<P> {
+    provider: P,
+    chain_id: u64,
+}
+
+impl<P: Provider> DelegationToolkit<P> {
+    /// Creates a new [`DelegationToolkit`] for the chain the provider is connected to.
+    pub async fn new(provider: P) -> Result<Self> {
+        let chain_id = provider.get_chain_id().await?;
+        Ok(Self { provider, chain_id })
+    }
+
+    /// Signs an authorization for `authority` to delegate its code to `delegate`.
+    ///
+    /// The authorization nonce is checked after the sender's nonce has been incremented, so if
+    /// the authority also sends the transaction the authorization must use the next nonce.
+    pub async fn authorize<S>(
+        &self,
+        authority: &S,
+        delegate: Address,
+        sender: Address,
+    ) -> Result<SignedAuthorization>
+    where
+        S: Signer + ?Sized,
+    {
+        let mut nonce = self.provider.get_transaction_count(authority.address()).await?;
+        if authority.address() == sender {
+            nonce += 1;
+        }
+
+        let authorization =
+            Authorization { chain_id: U256::from(self.chain_id), address: delegate, nonce };
+        let signature = authority.sign_hash(&authorization.signature_hash()).await?;
+
+        Ok(authorization.into_signed(signature))
+    }
+
+    /// Returns the address `account` currently delegates its code to, if any.
+    pub async fn delegation(&self, account: Address) -> Result<Option<Address>> {
+        let code = self.provider.get_code_at(account).await?;
+        Ok(parse_delegation(&code))
+    }
+
+    /// Delegates the code of every authority to `delegate` in a single transaction sent by
+    /// `sender`, which calls `to` with `input`.
+    ///
+    /// An EIP-7702 transaction cannot create a contract, so it needs a target. The authorizations
+    /// are processed before execution, so the call already runs the delegated code of the
+    /// authorities, and has to be valid for it.
+    pub async fn delegate<S>(
+        &self,
+        sender: Address,
+        authorities: &[&S],
+        delegate: Address,
+        to: Address,
+        input: Bytes,
+    ) -> Result<TransactionReceipt>
+    where
+        S: Signer + ?Sized,
+    {
+        ensure!(!authorities.is_empty(), "at least one authority is required");
+
+        // The nonce of every authorization is read before the transaction is sent, so an
+        // authority listed twice would sign the same nonce twice and only its first authorization
+        // would apply.
+        let mut addresses = HashSet::new();
+        for authority in authorities {
+            ensure!(
+                addresses.insert(authority.address()),
+                "duplicate authority {}",
+                authority.address()
+            );
+        }
+
+        let mut authorization_list = Vec::with_capacity(authorities.len());
+        for authority in authorities {
+            authorization_list.push(self.authorize(*authority, delegate, sender).await?);
+        }
+
+        let tx = TransactionRequest::default()
+            .with_from(sender)
+            .with_to(to)
+            .with_input(input)
+            .with_authorization_list(authorization_list);
+
+        let receipt = self.provider.send_transaction(tx).await?.get_receipt().await?;
+        ensure!(receipt.status(), "delegation transaction {} reverted", receipt.transaction_hash);
+        Ok(receipt)
+    }
+
+    /// Revokes the delegation of every authority by delegating to the zero address, which clears
+    /// the code of the account.
+    ///
+    /// The transaction calls the first authority, which has no code anymore once it executes.
+    pub async fn revoke<S>(&self, sender: Address, authorities: &[&S]) -> Result<TransactionReceipt>
+    where
+        S: Signer + ?Sized,
+    {
+        let to = authorities.first().map(|authority| authority.address()).unwrap_or_default();
+        self.delegate(sender, authorities, Address::ZERO, to, Bytes::new()).await
+    }
+}
+
+#[tokio::main]
+async fn main() -> Result<()> {
+    // Spin up a local Anvil node with the Prague hardfork enabled.
+    // Ensure `anvil` is available in $PATH.
+    let anvil = Anvil::new().arg("--hardfork").arg("prague").try_spawn()?;
+
+    // Create four users, Alice, Bob, Charlie and Dave.
+    // Alice and Bob send transactions, Charlie and Dave only sign authorizations.
+    let alice: PrivateKeySigner = anvil.keys()[0].clone().into();
+    let bob: PrivateKeySigner = anvil.keys()[1].clone().into();
+    let charlie: PrivateKeySigner = anvil.keys()[2].clone().into();
+    let dave: PrivateKeySigner = anvil.keys()[3].clone().into();
+
+    // Create a provider with a wallet for Alice and Bob.
+    let mut wallet = EthereumWallet::new(alice.clone());
+    wallet.register_signer(bob.clone());
+    let provider = ProviderBuilder::new().wallet(wallet).connect_http(anvil.endpoint_url());
+
+    // Deploy the contract the accounts will delegate to.
+    let contract = Log::deploy(&provider).await?;
+    let log = *contract.address();
+
+    let toolkit = DelegationToolkit::new(&provider).await?;
+
+    // The delegation transactions call `emitHello` on the first authority, which already runs the
+    // contract code.
+    let hello = Bytes::from(Log::emitHelloCall {}.abi_encode());
+
+    // Alice delegates her own account and sends the transaction herself.
+    let receipt =
+        toolkit.delegate(alice.address(), &[&alice], log, alice.address(), hello.clone()).await?;
+    assert_eq!(receipt.inner.logs().len(), 1);
+    assert_eq!(toolkit.delegation(alice.address()).await?, Some(log));
+    println!("Alice delegated to {log}");
+
+    // Bob sponsors the delegation of both Charlie and Dave in a single transaction.
+    toolkit.delegate(bob.address(), &[&charlie, &dave], log, charlie.address(), hello).await?;
+    assert_eq!(toolkit.delegation(charlie.address()).await?, Some(log));
+    assert_eq!(toolkit.delegation(dave.address()).await?, Some(log));
+    println!("Charlie and Dave delegated to {log}");
+
+    // The delegated accounts now run the contract code.
+    let receipt =
+        Log::new(charlie.address(), &provider).emitHello().send().await?.get_receipt().await?;
+    assert_eq!(receipt.inner.logs().len(), 1);
+    assert_eq!(receipt.inner.logs()[0].address(), charlie.address());
+
+    // Bob revokes all three delegations, which clears the code of the accounts.
+    toolkit.revoke(bob.address(), &[&alice, &charlie, &dave]).await?;
+    for account in [alice.address(), charlie.address(), dave.address()] {
+        assert_eq!(toolkit.delegation(account).await?, None);
+        assert!(provider.get_code_at(account).await?.is_empty());
+    }
+    println!("Delegations of Alice, Charlie and Dave revoked");
+
+    // An authority listed twice is rejected before anything is signed.
+    assert!(toolkit.revoke(bob.address(), &[&alice, &alice]).await.is_err());
+
+    Ok(())
+}
@@ -0,0 +1,176 @@
+//! Example of an air-gapped signing workflow: an online machine prepares an unsigned transaction,
+//! an offline machine signs it and the online machine validates and broadcasts the result.
+//!
+//! Every stage only exchanges a portable payload, so the provider is mocked and no network is
+//! needed.
+
+use alloy::{
+    consensus::{
+        transaction::SignerRecoverable, SignableTransaction, Transaction, TxEnvelope,
+        TypedTransaction,
+    },
+    eips::{Decodable2718, Encodable2718},
+    network::{Ethereum, EthereumWallet, NetworkWallet, TransactionBuilder},
+    primitives::{hex, Address, Bytes, TxHash, U256},
+    providers::{Provider, ProviderBuilder},
+    rpc::types::{FeeHistory, TransactionRequest},
+    signers::local::PrivateKeySigner,
+    transports::mock::Asserter,
+};
+use eyre::{ensure, Result};
+use serde::{Deserialize, Serialize};
+
+/// An unsigned transaction together with the account expected to sign it.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct UnsignedPayload {
+    /// The account that must sign the transaction.
+    pub from: Address,
+    /// The EIP-2718 encoding of the unsigned transaction, as it is hashed for signing.
+    pub unsigned_tx: Bytes,
+}
+
+impl UnsignedPayload {
+    /// Online stage: fills the nonce, fees, gas limit and chain id of `tx` from the provider.
+    pub async fn prepare<P: Provider>(
+        provider: &P,
+        from: Address,
+        mut tx: TransactionRequest,
+    ) -> Result<Self> {
+        tx.set_from(from);
+        if tx.chain_id.is_none() {
+            tx.set_chain_id(provider.get_chain_id().await?);
+        }
+        if tx.nonce.is_none() {
+            tx.set_nonce(provider.get_transaction_count(from).await?);
+        }
+        if tx.gas.is_none() {
+            tx.set_gas_limit(provider.estimate_gas(tx.clone()).await?);
+        }
+        if tx.gas_price.is_none() && tx.max_fee_per_gas.is_none() {
+            let fees = provider.estimate_eip1559_fees().await?;
+            tx.set_max_fee_per_gas(fees.max_fee_per_gas);
+            tx.set_max_priority_fee_per_gas(fees.max_priority_fee_per_gas);
+        }
+
+        let tx = tx.build_unsigned()?;
+        let mut unsigned_tx = Vec::new();
+        tx.encode_for_signing(&mut unsigned_tx);
+
+        Ok(Self { from, unsigned_tx: unsigned_tx.into() })
+    }
+
+    /// Decodes the unsigned transaction.
+    pub fn transaction(&self) -> Result<TypedTransaction> {
+        Ok(TypedTransaction::decode_unsigned(&mut self.unsigned_tx.as_ref())?)
+    }
+
+    /// Encodes the payload as upper case hex of `from || unsigned_tx`, which fits the compact
+    /// alphanumeric mode of QR codes.
+    pub fn to_qr_string(&self) -> String {
+        hex::encode_upper([self.from.as_slice(), &self.unsigned_tx].concat())
+    }
+
+    /// Decodes a payload produced by [`UnsignedPayload::to_qr_string`].
+    pub fn from_qr_string(s: &str) -> Result<Self> {
+        let bytes = hex::decode(s)?;
+        ensure!(bytes.len() > Address::len_bytes(), "QR payload is too short");
+        let (from, unsigned_tx) = bytes.split_at(Address::len_bytes());
+        Ok(Self {
+            from: Address::from_slice(from),
+            unsigned_tx: Bytes::copy_from_slice(unsigned_tx),
+        })
+    }
+
+    /// Offline stage: signs the transaction with the wallet signer registered for `from`, and
+    /// returns the EIP-2718 encoded signed transaction.
+    pub async fn sign(&self, wallet: &EthereumWallet) -> Result<Bytes> {
+        let envelope = NetworkWallet::<Ethereum>::sign_transaction_from(
+            wallet,
+            self.from,
+            self.transaction()?,
+        )
+        .await?;
+        Ok(envelope.encoded_2718().into())
+    }
+
+    /// Broadcast stage: checks that `raw_tx` is this transaction signed by `from`.
+    pub fn validate(&self, raw_tx: &[u8]) -> Result<TxEnvelope> {
+        let envelope = TxEnvelope::decode_2718(&mut &raw_tx[..])?;
+
+        let expected = self.transaction()?.signature_hash();
+        ensure!(
+            envelope.signature_hash() == expected,
+            "signed transaction does not match the prepared transaction"
+        );
+
+        let signer = envelope.recover_signer()?;
+        ensure!(signer == self.from, "transaction signed by {signer}, expected {}", self.from);
+
+        Ok(envelope)
+    }
+
+    /// Broadcast stage: validates `raw_tx` and sends it with `eth_sendRawTransaction`.
+    pub async fn broadcast<P: Provider>(&self, provider: &P, raw_tx: &[u8]) -> Result<TxHash> {
+        self.validate(raw_tx)?;
+        let pending_tx = provider.send_raw_transaction(raw_tx).await?;
+        Ok(*pending_tx.tx_hash())
+    }
+}
+
+#[tokio::main]
+async fn main() -> Result<()> {
+    // The offline machine holds Alice's key, the online machine only knows her address.
+    let alice = PrivateKeySigner::random();
+    let bob = PrivateKeySigner::random();
+    let wallet = EthereumWallet::new(alice.clone());
+
+    // Mock the responses of the node the online machine is connected to.
+    let asserter = Asserter::new();
+    let provider = ProviderBuilder::new().connect_mocked_client(asserter.clone());
+
+    // Responses for `eth_chainId`, `eth_getTransactionCount`, `eth_estimateGas` and
+    // `eth_feeHistory`, in the order they are requested.
+    asserter.push_success(&U256::from(1));
+    asserter.push_success(&U256::from(7));
+    asserter.push_success(&U256::from(21_000));
+    asserter.push_success(&FeeHistory {
+        base_fee_per_gas: vec![10_000_000_000, 10_000_000_000],
+        gas_used_ratio: vec![0.5],
+        reward: Some(vec![vec![1_000_000_000]]),
+        ..Default::default()
+    });
+
+    // Online: prepare the transaction and export it.
+    let tx = TransactionRequest::default().with_to(bob.address()).with_value(U256::from(100));
+    let payload = UnsignedPayload::prepare(&provider, alice.address(), tx).await?;
+
+    let json = serde_json::to_string_pretty(&payload)?;
+    let qr = payload.to_qr_string();
+    println!("Unsigned payload:\n{json}");
+    println!("QR payload: {qr}");
+
+    // Offline: import the payload, from either format, and sign it.
+    let imported: UnsignedPayload = serde_json::from_str(&json)?;
+    assert_eq!(imported, UnsignedPayload::from_qr_string(&qr)?);
+
+    let tx = imported.transaction()?;
+    println!("Signing {:?} transaction with nonce {}", tx.tx_type(), tx.nonce());
+    let raw_tx = imported.sign(&wallet).await?;
+
+    // Online: validate the signed transaction and broadcast it.
+    let envelope = payload.validate(&raw_tx)?;
+    asserter.push_success(envelope.tx_hash());
+
+    let tx_hash = payload.broadcast(&provider, &raw_tx).await?;
+    println!("Broadcast transaction {tx_hash}");
+    assert_eq!(tx_hash, *envelope.tx_hash());
+
+    // A transaction signed by anyone else is rejected before it reaches the node.
+    let forged = UnsignedPayload { from: bob.address(), ..payload.clone() }
+        .sign(&EthereumWallet::new(bob))
+        .await?;
+    assert!(payload.validate(&forged).is_err());
+
+    Ok(())
+}
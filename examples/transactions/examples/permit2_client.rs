@@ -0,0 +1,474 @@
+//! Example of a typed [Permit2](https://github.com/Uniswap/permit2) client covering signature
+//! transfers, batch and witness permits, allowance transfers and unordered nonce bitmaps.
+
+use alloy::{
+    contract::{CallBuilder, CallDecoder},
+    network::EthereumWallet,
+    node_bindings::Anvil,
+    primitives::{aliases::U160, keccak256, Address, Bytes, B256, U256},
+    providers::{Provider, ProviderBuilder},
+    rpc::types::TransactionReceipt,
+    signers::{local::PrivateKeySigner, Signer},
+    sol,
+    sol_types::{eip712_domain, Eip712Domain, SolStruct, SolValue},
+};
+use eyre::{ensure, Result};
+
+// Codegen from artifact.
+sol!(
+    #[allow(missing_docs)]
+    #[sol(rpc)]
+    ERC20Example,
+    "examples/artifacts/ERC20Example.json"
+);
+
+// Codegen from artifact.
+sol!(
+    #[allow(missing_docs)]
+    #[sol(rpc)]
+    Permit2,
+    "examples/artifacts/Permit2.json"
+);
+
+// The signed permits of signature transfers include the spender, which is the caller of the
+// contract and not part of the contract input structs.
+sol! {
+    #[allow(missing_docs)]
+    #[derive(Debug)]
+    struct TokenPermissions {
+        address token;
+        uint256 amount;
+    }
+
+    #[allow(missing_docs)]
+    #[derive(Debug)]
+    struct PermitTransferFrom {
+        TokenPermissions permitted;
+        address spender;
+        uint256 nonce;
+        uint256 deadline;
+    }
+
+    #[allow(missing_docs)]
+    #[derive(Debug)]
+    struct PermitBatchTransferFrom {
+        TokenPermissions[] permitted;
+        address spender;
+        uint256 nonce;
+        uint256 deadline;
+    }
+}
+
+// A custom witness, e.g. the order a filler settles with the transferred tokens.
+sol! {
+    #[allow(missing_docs)]
+    #[derive(Debug)]
+    struct Order {
+        address recipient;
+        uint256 minAmountOut;
+    }
+}
+
+/// The start of the type of a witness permit, completed by the witness type string.
+const PERMIT_WITNESS_TRANSFER_FROM_STUB: &str =
+    "PermitWitnessTransferFrom(TokenPermissions permitted,address spender,uint256 nonce,uint256 deadline,";
+
+impl From<TokenPermissions> for ISignatureTransfer::TokenPermissions {
+    fn from(val: TokenPermissions) -> Self {
+        Self { token: val.token, amount: val.amount }
+    }
+}
+
+impl From<PermitTransferFrom> for ISignatureTransfer::PermitTransferFrom {
+    fn from(val: PermitTransferFrom) -> Self {
+        Self { permitted: val.permitted.into(), nonce: val.nonce, deadline: val.deadline }
+    }
+}
+
+impl From<PermitBatchTransferFrom> for ISignatureTransfer::PermitBatchTransferFrom {
+    fn from(val: PermitBatchTransferFrom) -> Self {
+        Self {
+            permitted: val.permitted.into_iter().map(Into::into).collect(),
+            nonce: val.nonce,
+            deadline: val.deadline,
+        }
+    }
+}
+
+/// Returns the witness type string of `W` expected by `permitWitnessTransferFrom`, i.e.
+/// `{W} witness)` followed by `W`, `TokenPermissions` and the types they reference sorted by name.
+pub fn witness_type_string<W: SolStruct>() -> String {
+    let mut types: Vec<String> = W::eip712_components().iter().map(|ty| ty.to_string()).collect();
+    types.push(W::eip712_root_type().into_owned());
+    types.push(TokenPermissions::eip712_root_type().into_owned());
+    types.sort_by(|a, b| a.split('(').next().cmp(&b.split('(').next()));
+    types.dedup();
+    format!("{} witness){}", W::NAME, types.concat())
+}
+
+/// A typed client of a Permit2 deployment.
+#[derive(Debug)]
+pub struct Permit2Client<P> {
+    contract: Permit2::Permit2Instance<P>,
+    domain: Eip712Domain,
+}
+
+impl<P: Provider> Permit2Client<P> {
+    /// Creates a new [`Permit2Client`] for the Permit2 contract at `address`.
+    pub async fn new(address: Address, provider: P) -> Result<Self> {
+        let domain = eip712_domain! {
+            name: "Permit2",
+            chain_id: provider.get_chain_id().await?,
+            verifying_contract: address,
+        };
+        Ok(Self { contract: Permit2::new(address, provider), domain })
+    }
+
+    /// Returns the address of the Permit2 contract.
+    pub const fn address(&self) -> &Address {
+        self.contract.address()
+    }
+
+    /// Returns the EIP-712 domain permits are signed in.
+    pub const fn domain(&self) -> &Eip712Domain {
+        &self.domain
+    }
+
+    /// Signs any Permit2 permit, e.g. [`PermitTransferFrom`], [`PermitBatchTransferFrom`],
+    /// [`IAllowanceTransfer::PermitSingle`] or [`IAllowanceTransfer::PermitBatch`].
+    pub async fn sign<T, S>(&self, signer: &S, permit: &T) -> Result<Bytes>
+    where
+        T: SolStruct + Send + Sync,
+        S: Signer + Sync,
+    {
+        Ok(signer.sign_typed_data(permit, &self.domain).await?.as_bytes().into())
+    }
+
+    /// Returns the EIP-712 signing hash of `permit` with `witness`.
+    ///
+    /// The permit type depends on the witness type, so it cannot be declared once with `sol!`.
+    pub fn witness_signing_hash<W: SolStruct>(
+        &self,
+        permit: &PermitTransferFrom,
+        witness: &W,
+    ) -> B256 {
+        let type_hash =
+            keccak256(format!("{PERMIT_WITNESS_TRANSFER_FROM_STUB}{}", witness_type_string::<W>()));
+        let struct_hash = keccak256(
+            (
+                type_hash,
+                permit.permitted.eip712_hash_struct(),
+                permit.spender,
+                permit.nonce,
+                permit.deadline,
+                witness.eip712_hash_struct(),
+            )
+                .abi_encode(),
+        );
+        keccak256([&[0x19, 0x01], &self.domain.separator()[..], &struct_hash[..]].concat())
+    }
+
+    /// Signs `permit` with `witness`.
+    pub async fn sign_witness<W, S>(
+        &self,
+        signer: &S,
+        permit: &PermitTransferFrom,
+        witness: &W,
+    ) -> Result<Bytes>
+    where
+        W: SolStruct,
+        S: Signer + ?Sized,
+    {
+        let hash = self.witness_signing_hash(permit, witness);
+        Ok(signer.sign_hash(&hash).await?.as_bytes().into())
+    }
+
+    /// Transfers `amount` of the permitted token of `owner` to `to`, sent by the spender.
+    pub async fn permit_transfer_from(
+        &self,
+        permit: PermitTransferFrom,
+        to: Address,
+        amount: U256,
+        owner: Address,
+        signature: Bytes,
+    ) -> Result<TransactionReceipt> {
+        let spender = permit.spender;
+        let details = ISignatureTransfer::SignatureTransferDetails { to, requestedAmount: amount };
+        let call = self.contract.permitTransferFrom_0(permit.into(), details, owner, signature);
+        send(call.from(spender)).await
+    }
+
+    /// Transfers several tokens of `owner` with one signature, sent by the spender.
+    ///
+    /// `transfers` has a `(to, amount)` pair per permitted token, in the same order.
+    pub async fn permit_batch_transfer_from(
+        &self,
+        permit: PermitBatchTransferFrom,
+        transfers: &[(Address, U256)],
+        owner: Address,
+        signature: Bytes,
+    ) -> Result<TransactionReceipt> {
+        ensure!(permit.permitted.len() == transfers.len(), "one transfer per permitted token");
+        let spender = permit.spender;
+        let details = transfers
+            .iter()
+            .map(|&(to, amount)| ISignatureTransfer::SignatureTransferDetails {
+                to,
+                requestedAmount: amount,
+            })
+            .collect();
+        let call = self.contract.permitTransferFrom_1(permit.into(), details, owner, signature);
+        send(call.from(spender)).await
+    }
+
+    /// Transfers `amount` of the permitted token of `owner` to `to` with a permit that also signs
+    /// `witness`, sent by the spender.
+    pub async fn permit_witness_transfer_from<W: SolStruct>(
+        &self,
+        permit: PermitTransferFrom,
+        to: Address,
+        amount: U256,
+        owner: Address,
+        witness: &W,
+        signature: Bytes,
+    ) -> Result<TransactionReceipt> {
+        let spender = permit.spender;
+        let details = ISignatureTransfer::SignatureTransferDetails { to, requestedAmount: amount };
+        let call = self.contract.permitWitnessTransferFrom_0(
+            permit.into(),
+            details,
+            owner,
+            witness.eip712_hash_struct(),
+            witness_type_string::<W>(),
+            signature,
+        );
+        send(call.from(spender)).await
+    }
+
+    /// Returns whether the unordered `nonce` of `owner` has been used or invalidated.
+    pub async fn is_nonce_used(&self, owner: Address, nonce: U256) -> Result<bool> {
+        let bitmap = self.contract.nonceBitmap(owner, nonce >> 8).call().await?;
+        Ok(bitmap.bit((nonce & U256::from(0xff)).to::<usize>()))
+    }
+
+    /// Returns the `count` lowest unused unordered nonces of `owner`, reading one bitmap word of
+    /// 256 nonces at a time.
+    pub async fn unused_nonces(&self, owner: Address, count: usize) -> Result<Vec<U256>> {
+        let mut nonces = Vec::with_capacity(count);
+        let mut word = U256::ZERO;
+        while nonces.len() < count {
+            let bitmap = self.contract.nonceBitmap(owner, word).call().await?;
+            for bit in (0..256).filter(|&bit| !bitmap.bit(bit)).take(count - nonces.len()) {
+                nonces.push((word << 8) | U256::from(bit));
+            }
+            word += U256::from(1);
+        }
+        Ok(nonces)
+    }
+
+    /// Returns the lowest unused unordered nonce of `owner`.
+    pub async fn next_unused_nonce(&self, owner: Address) -> Result<U256> {
+        Ok(self.unused_nonces(owner, 1).await?[0])
+    }
+
+    /// Invalidates unordered nonces of `owner`, e.g. to cancel signed permits, with one
+    /// transaction per bitmap word.
+    pub async fn invalidate_nonces(&self, owner: Address, nonces: &[U256]) -> Result<()> {
+        let mut masks: Vec<(U256, U256)> = Vec::new();
+        for nonce in nonces {
+            let (word, bit) = (*nonce >> 8, (nonce & U256::from(0xff)).to::<usize>());
+            match masks.iter_mut().find(|(w, _)| *w == word) {
+                Some((_, mask)) => mask.set_bit(bit, true),
+                None => {
+                    let mut mask = U256::ZERO;
+                    mask.set_bit(bit, true);
+                    masks.push((word, mask));
+                }
+            }
+        }
+        for (word, mask) in masks {
+            send(self.contract.invalidateUnorderedNonces(word, mask).from(owner)).await?;
+        }
+        Ok(())
+    }
+
+    /// Returns the allowance of `spender` over the `token` of `owner` as `(amount, expiration,
+    /// nonce)`, the nonce being the one the next allowance permit must use.
+    pub async fn allowance(
+        &self,
+        owner: Address,
+        token: Address,
+        spender: Address,
+    ) -> Result<Permit2::allowanceReturn> {
+        Ok(self.contract.allowance(owner, token, spender).call().await?)
+    }
+
+    /// Sets an allowance of `owner` with a signed permit, sent by `sender`.
+    pub async fn permit(
+        &self,
+        owner: Address,
+        permit: IAllowanceTransfer::PermitSingle,
+        signature: Bytes,
+        sender: Address,
+    ) -> Result<TransactionReceipt> {
+        send(self.contract.permit_1(owner, permit, signature).from(sender)).await
+    }
+
+    /// Sets allowances of `owner` over several tokens with one signed permit, sent by `sender`.
+    pub async fn permit_batch(
+        &self,
+        owner: Address,
+        permit: IAllowanceTransfer::PermitBatch,
+        signature: Bytes,
+        sender: Address,
+    ) -> Result<TransactionReceipt> {
+        send(self.contract.permit_0(owner, permit, signature).from(sender)).await
+    }
+
+    /// Transfers `amount` of the `token` of `from` to `to` within the allowance of `spender`.
+    pub async fn transfer_from(
+        &self,
+        from: Address,
+        to: Address,
+        amount: U160,
+        token: Address,
+        spender: Address,
+    ) -> Result<TransactionReceipt> {
+        send(self.contract.transferFrom_1(from, to, amount, token).from(spender)).await
+    }
+}
+
+/// Sends a contract call and waits for its successful receipt.
+async fn send<P: Provider, D: CallDecoder>(call: CallBuilder<&P, D>) -> Result<TransactionReceipt> {
+    let receipt = call.send().await?.get_receipt().await?;
+    ensure!(receipt.status(), "transaction {} reverted", receipt.transaction_hash);
+    Ok(receipt)
+}
+
+#[tokio::main]
+async fn main() -> Result<()> {
+    // Spin up a local Anvil node.
+    // Ensure `anvil` is available in $PATH.
+    let anvil = Anvil::new().try_spawn()?;
+
+    // Alice owns the tokens, Bob is the spender and Carol a recipient.
+    let alice: PrivateKeySigner = anvil.keys()[0].clone().into();
+    let bob: PrivateKeySigner = anvil.keys()[1].clone().into();
+    let carol = anvil.addresses()[2];
+
+    let mut wallet = EthereumWallet::new(alice.clone());
+    wallet.register_signer(bob.clone());
+    let provider = ProviderBuilder::new().wallet(wallet).connect_http(anvil.endpoint_url());
+
+    // Deploy Permit2 and two tokens minted to Alice, and approve Permit2 for both.
+    let permit2 = Permit2::deploy(&provider).await?;
+    let token_a = ERC20Example::deploy(&provider).await?;
+    let token_b = ERC20Example::deploy(&provider).await?;
+    for token in [&token_a, &token_b] {
+        token.approve(*permit2.address(), U256::MAX).send().await?.watch().await?;
+    }
+
+    let client = Permit2Client::new(*permit2.address(), &provider).await?;
+    let deadline = U256::from(u64::MAX);
+    let amount = U256::from(100);
+
+    // A signature transfer with the next unused nonce.
+    let nonce = client.next_unused_nonce(alice.address()).await?;
+    assert_eq!(nonce, U256::ZERO);
+    let permit = PermitTransferFrom {
+        permitted: TokenPermissions { token: *token_a.address(), amount },
+        spender: bob.address(),
+        nonce,
+        deadline,
+    };
+    let signature = client.sign(&alice, &permit).await?;
+    client.permit_transfer_from(permit, carol, amount, alice.address(), signature).await?;
+    assert!(client.is_nonce_used(alice.address(), nonce).await?);
+    assert_eq!(token_a.balanceOf(carol).call().await?, amount);
+    println!("Signature transfer with nonce {nonce}");
+
+    // Reserve two nonces ahead of signing a batch and a witness permit.
+    let nonces = client.unused_nonces(alice.address(), 2).await?;
+    assert_eq!(nonces, [U256::from(1), U256::from(2)]);
+
+    // A batch transfer of both tokens with one signature.
+    let permit = PermitBatchTransferFrom {
+        permitted: vec![
+            TokenPermissions { token: *token_a.address(), amount },
+            TokenPermissions { token: *token_b.address(), amount },
+        ],
+        spender: bob.address(),
+        nonce: nonces[0],
+        deadline,
+    };
+    let signature = client.sign(&alice, &permit).await?;
+    let transfers = [(carol, amount), (bob.address(), amount)];
+    client.permit_batch_transfer_from(permit, &transfers, alice.address(), signature).await?;
+    assert_eq!(token_b.balanceOf(bob.address()).call().await?, amount);
+    println!("Batch transfer with nonce {}", nonces[0]);
+
+    // A transfer that also commits Alice to an order the spender has to honor.
+    let order = Order { recipient: carol, minAmountOut: U256::from(42) };
+    println!("Witness type string: {}", witness_type_string::<Order>());
+    let permit = PermitTransferFrom {
+        permitted: TokenPermissions { token: *token_b.address(), amount },
+        spender: bob.address(),
+        nonce: nonces[1],
+        deadline,
+    };
+    let signature = client.sign_witness(&alice, &permit, &order).await?;
+    client
+        .permit_witness_transfer_from(permit, carol, amount, alice.address(), &order, signature)
+        .await?;
+    assert_eq!(token_b.balanceOf(carol).call().await?, amount);
+    println!("Witness transfer with nonce {}", nonces[1]);
+
+    // Cancel a permit that was signed but not used by invalidating its nonce.
+    client.invalidate_nonces(alice.address(), &[U256::from(3)]).await?;
+    assert_eq!(client.next_unused_nonce(alice.address()).await?, U256::from(4));
+
+    // An allowance permit for Bob, who then transfers within the allowance.
+    let expiration =
+        (std::time::SystemTime::now().duration_since(std::time::UNIX_EPOCH)?.as_secs() + 3600)
+            .try_into()?;
+    let allowance = client.allowance(alice.address(), *token_a.address(), bob.address()).await?;
+    let permit = IAllowanceTransfer::PermitSingle {
+        details: IAllowanceTransfer::PermitDetails {
+            token: *token_a.address(),
+            amount: U160::from(1000),
+            expiration,
+            nonce: allowance.nonce,
+        },
+        spender: bob.address(),
+        sigDeadline: deadline,
+    };
+    let signature = client.sign(&alice, &permit).await?;
+    client.permit(alice.address(), permit, signature, bob.address()).await?;
+    client
+        .transfer_from(alice.address(), carol, U160::from(300), *token_a.address(), bob.address())
+        .await?;
+    let allowance = client.allowance(alice.address(), *token_a.address(), bob.address()).await?;
+    assert_eq!(allowance.amount, U160::from(700));
+    println!("Allowance of Bob after transfer: {}", allowance.amount);
+
+    // Allowances over both tokens with one permit, using the nonce of each pair.
+    let mut details = Vec::new();
+    for token in [*token_a.address(), *token_b.address()] {
+        let nonce = client.allowance(alice.address(), token, bob.address()).await?.nonce;
+        details.push(IAllowanceTransfer::PermitDetails {
+            token,
+            amount: U160::from(500),
+            expiration,
+            nonce,
+        });
+    }
+    let permit =
+        IAllowanceTransfer::PermitBatch { details, spender: bob.address(), sigDeadline: deadline };
+    let signature = client.sign(&alice, &permit).await?;
+    client.permit_batch(alice.address(), permit, signature, bob.address()).await?;
+    let allowance = client.allowance(alice.address(), *token_b.address(), bob.address()).await?;
+    assert_eq!(allowance.amount, U160::from(500));
+    println!("Batch allowance set, next nonce of Bob over token B: {}", allowance.nonce);
+
+    Ok(())
+}
@@ -0,0 +1,521 @@
+//! Example of rendering the `callTracer` output of `debug_traceTransaction` as a Foundry-style
+//! trace, with the calls, arguments, events, return values and revert reasons decoded from
+//! registered ABIs, as colored text or as JSON.
+//!
+//! The traces are read from stored fixtures. Traces of live transactions come from
+//! `debug_trace_transaction` with the `callTracer` and `withLog` enabled, as in
+//! `trace_transaction.rs`, and `GethTrace::try_into_call_frame`.
+
+use std::{collections::HashMap, fmt::Write};
+
+use alloy::{
+    dyn_abi::{DynSolValue, EventExt, FunctionExt, JsonAbiExt},
+    hex,
+    json_abi::{Error, Event, Function, JsonAbi},
+    primitives::{address, utils::format_ether, Address, LogData, Selector, B256, U256},
+    rpc::types::trace::geth::CallFrame,
+    sol,
+    sol_types::decode_revert_reason,
+};
+use eyre::Result;
+use serde::Serialize;
+
+sol! {
+    #[allow(missing_docs)]
+    #[sol(abi)]
+    interface IUniswapV2Router02 {
+        function swapExactTokensForTokens(
+            uint256 amountIn,
+            uint256 amountOutMin,
+            address[] calldata path,
+            address to,
+            uint256 deadline
+        ) external returns (uint256[] memory amounts);
+    }
+
+    #[allow(missing_docs)]
+    #[sol(abi)]
+    interface IUniswapV2Pair {
+        event Swap(
+            address indexed sender,
+            uint256 amount0In,
+            uint256 amount1In,
+            uint256 amount0Out,
+            uint256 amount1Out,
+            address indexed to
+        );
+        event Sync(uint112 reserve0, uint112 reserve1);
+
+        function getReserves() external view returns (uint112 reserve0, uint112 reserve1, uint32 blockTimestampLast);
+        function swap(uint256 amount0Out, uint256 amount1Out, address to, bytes calldata data) external;
+    }
+
+    #[allow(missing_docs)]
+    #[sol(abi)]
+    interface IERC20 {
+        event Transfer(address indexed from, address indexed to, uint256 value);
+        event Approval(address indexed owner, address indexed spender, uint256 value);
+
+        function balanceOf(address account) external view returns (uint256);
+        function transfer(address to, uint256 value) external returns (bool);
+        function transferFrom(address from, address to, uint256 value) external returns (bool);
+        function approve(address spender, uint256 value) external returns (bool);
+    }
+}
+
+/// A decoded parameter of a call, a return value or an event.
+#[derive(Clone, Debug, Serialize)]
+pub struct DecodedParam {
+    /// The name of the parameter, empty if it has none.
+    pub name: String,
+    /// The Solidity type of the parameter.
+    #[serde(rename = "type")]
+    pub ty: String,
+    /// The formatted value, with the labels of known addresses.
+    pub value: String,
+}
+
+/// A decoded event.
+#[derive(Clone, Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DecodedEvent {
+    /// The address of the contract that emitted the event.
+    pub address: Address,
+    /// The name of the event, or `None` if the event is unknown.
+    pub name: Option<String>,
+    /// The decoded parameters, in declaration order.
+    pub params: Vec<DecodedParam>,
+    /// The raw topics and data of unknown events.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub raw: Option<LogData>,
+    /// The number of sub-calls made by the frame before the event was emitted.
+    pub position: u64,
+}
+
+/// A decoded call frame.
+#[derive(Clone, Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DecodedFrame {
+    /// The type of the frame, e.g. `CALL`, `STATICCALL`, `DELEGATECALL` or `CREATE`.
+    pub kind: String,
+    /// The caller.
+    pub from: Address,
+    /// The callee, or the created contract.
+    pub to: Option<Address>,
+    /// The label of the callee.
+    pub label: Option<String>,
+    /// The signature of the called function, or `None` if it is unknown.
+    pub function: Option<String>,
+    /// The decoded arguments.
+    pub args: Vec<DecodedParam>,
+    /// The raw input if the function is unknown.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub raw_input: Option<String>,
+    /// The transferred value, in wei.
+    pub value: U256,
+    /// The gas used by the frame, including its sub-calls.
+    pub gas_used: U256,
+    /// Whether the frame succeeded.
+    pub success: bool,
+    /// The decoded return values.
+    pub returns: Vec<DecodedParam>,
+    /// The decoded revert reason, or the error of the frame, if it failed.
+    pub revert_reason: Option<String>,
+    /// The events emitted by the frame itself.
+    pub events: Vec<DecodedEvent>,
+    /// The sub-calls.
+    pub calls: Vec<Self>,
+}
+
+/// ANSI styles of the colored output.
+mod style {
+    pub(crate) const RESET: &str = "\x1b[0m";
+    pub(crate) const DIM: &str = "\x1b[2m";
+    pub(crate) const RED: &str = "\x1b[31m";
+    pub(crate) const GREEN: &str = "\x1b[32m";
+    pub(crate) const YELLOW: &str = "\x1b[33m";
+    pub(crate) const CYAN: &str = "\x1b[36m";
+}
+
+/// Decodes and renders `callTracer` frames with registered ABIs and address labels.
+#[derive(Debug, Default)]
+pub struct TraceRenderer {
+    labels: HashMap<Address, String>,
+    contracts: HashMap<Address, JsonAbi>,
+    functions: HashMap<Selector, Function>,
+    events: HashMap<B256, Event>,
+    errors: HashMap<Selector, Error>,
+    color: bool,
+}
+
+impl TraceRenderer {
+    /// Creates a renderer that colors its output if `color` is set.
+    pub fn new(color: bool) -> Self {
+        Self { color, ..Default::default() }
+    }
+
+    /// Labels `address` with `label`.
+    pub fn label(&mut self, address: Address, label: &str) {
+        self.labels.insert(address, label.to_string());
+    }
+
+    /// Registers `abi` to decode the calls, events and errors of any contract.
+    pub fn register_abi(&mut self, abi: &JsonAbi) {
+        for function in abi.functions() {
+            self.functions.entry(function.selector()).or_insert_with(|| function.clone());
+        }
+        for event in abi.events().filter(|event| !event.anonymous) {
+            self.events.entry(event.selector()).or_insert_with(|| event.clone());
+        }
+        for error in abi.errors() {
+            self.errors.entry(error.selector()).or_insert_with(|| error.clone());
+        }
+    }
+
+    /// Registers `abi` as the ABI of the contract at `address`, labeled `label`.
+    ///
+    /// The functions of the contract take precedence over those of other ABIs with the same
+    /// selector.
+    pub fn register_contract(&mut self, address: Address, label: &str, abi: &JsonAbi) {
+        self.label(address, label);
+        self.register_abi(abi);
+        self.contracts.insert(address, abi.clone());
+    }
+
+    /// Decodes `frame` and its sub-calls.
+    pub fn decode(&self, frame: &CallFrame) -> DecodedFrame {
+        let selector = frame.selector();
+        let function = selector.and_then(|selector| {
+            frame
+                .to
+                .and_then(|to| self.contracts.get(&to))
+                .and_then(|abi| abi.functions().find(|function| function.selector() == selector))
+                .or_else(|| self.functions.get(&selector))
+        });
+        let is_create = frame.typ.starts_with("CREATE");
+        let success = frame.error.is_none();
+        let output = frame.output.as_ref().map_or(&[][..], |output| &output[..]);
+
+        let args = function.and_then(|function| {
+            let values = function.abi_decode_input(&frame.input[4..]).ok()?;
+            Some(self.params(&function.inputs, &values))
+        });
+        let returns = match function {
+            Some(function) if success => function
+                .abi_decode_output(output)
+                .map(|values| self.params(&function.outputs, &values))
+                .unwrap_or_default(),
+            _ => Vec::new(),
+        };
+
+        DecodedFrame {
+            kind: frame.typ.clone(),
+            from: frame.from,
+            to: frame.to,
+            label: frame.to.and_then(|to| self.labels.get(&to).cloned()),
+            function: function.map(Function::signature),
+            raw_input: (args.is_none() && !is_create && !frame.input.is_empty())
+                .then(|| hex::encode_prefixed(&frame.input)),
+            args: args.unwrap_or_default(),
+            value: frame.value.unwrap_or_default(),
+            gas_used: frame.gas_used,
+            success,
+            returns,
+            revert_reason: (!success).then(|| self.revert_reason(frame)),
+            events: frame
+                .logs
+                .iter()
+                .map(|log| {
+                    let data = LogData::new_unchecked(
+                        log.topics.clone().unwrap_or_default(),
+                        log.data.clone().unwrap_or_default(),
+                    );
+                    self.decode_event(log.address.unwrap_or_default(), data, log.position)
+                })
+                .collect(),
+            calls: frame.calls.iter().map(|call| self.decode(call)).collect(),
+        }
+    }
+
+    /// Decodes a log emitted by `address` before the sub-call at `position`.
+    fn decode_event(&self, address: Address, data: LogData, position: Option<u64>) -> DecodedEvent {
+        let position = position.unwrap_or_default();
+        let event = data.topics().first().and_then(|topic| self.events.get(topic));
+        let decoded = event.and_then(|event| Some((event, event.decode_log(&data).ok()?)));
+        let Some((event, decoded)) = decoded else {
+            return DecodedEvent {
+                address,
+                name: None,
+                params: Vec::new(),
+                raw: Some(data),
+                position,
+            };
+        };
+
+        let (mut indexed, mut body) = (decoded.indexed.into_iter(), decoded.body.into_iter());
+        let params = event
+            .inputs
+            .iter()
+            .filter_map(|param| {
+                let value = if param.indexed { indexed.next() } else { body.next() }?;
+                Some(DecodedParam {
+                    name: param.name.clone(),
+                    ty: param.ty.clone(),
+                    value: self.format_value(&value),
+                })
+            })
+            .collect();
+        DecodedEvent { address, name: Some(event.name.clone()), params, raw: None, position }
+    }
+
+    /// Returns the decoded revert reason of a failed frame.
+    ///
+    /// `Error(string)` and `Panic(uint256)` are decoded, then the errors of the registered ABIs,
+    /// and the reason decoded by the tracer or the error of the frame are used otherwise.
+    fn revert_reason(&self, frame: &CallFrame) -> String {
+        let output = frame.output.as_ref().map_or(&[][..], |output| &output[..]);
+        if let Some(reason) = decode_revert_reason(output) {
+            return reason;
+        }
+        let custom = output.get(..4).and_then(|selector| {
+            let error = self.errors.get(selector)?;
+            let values = error.abi_decode_input(&output[4..]).ok()?;
+            let args: Vec<_> = values.iter().map(|value| self.format_value(value)).collect();
+            Some(format!("{}({})", error.name, args.join(", ")))
+        });
+        custom
+            .or_else(|| frame.revert_reason.clone())
+            .or_else(|| (!output.is_empty()).then(|| hex::encode_prefixed(output)))
+            .or_else(|| frame.error.clone())
+            .unwrap_or_default()
+    }
+
+    /// Returns the decoded `values` of `params`.
+    fn params(
+        &self,
+        params: &[alloy::json_abi::Param],
+        values: &[DynSolValue],
+    ) -> Vec<DecodedParam> {
+        params
+            .iter()
+            .zip(values)
+            .map(|(param, value)| DecodedParam {
+                name: param.name.clone(),
+                ty: param.selector_type().into_owned(),
+                value: self.format_value(value),
+            })
+            .collect()
+    }
+
+    /// Formats `value`, replacing known addresses with their labels.
+    fn format_value(&self, value: &DynSolValue) -> String {
+        let join = |values: &[DynSolValue]| {
+            values.iter().map(|value| self.format_value(value)).collect::<Vec<_>>().join(", ")
+        };
+        match value {
+            DynSolValue::Address(address) => match self.labels.get(address) {
+                Some(label) => format!("{label}: [{address}]"),
+                None => address.to_string(),
+            },
+            DynSolValue::Bool(bool) => bool.to_string(),
+            DynSolValue::Int(int, _) => int.to_string(),
+            DynSolValue::Uint(uint, _) => uint.to_string(),
+            DynSolValue::FixedBytes(word, size) => hex::encode_prefixed(&word[..*size]),
+            DynSolValue::Bytes(bytes) => hex::encode_prefixed(bytes),
+            DynSolValue::String(string) => format!("{string:?}"),
+            DynSolValue::Array(values) | DynSolValue::FixedArray(values) => {
+                format!("[{}]", join(values))
+            }
+            DynSolValue::Tuple(values) => format!("({})", join(values)),
+            value => format!("{value:?}"),
+        }
+    }
+
+    /// Renders `frame` as an indented tree.
+    pub fn render(&self, frame: &CallFrame) -> String {
+        let mut out = String::new();
+        for line in self.render_frame(&self.decode(frame)) {
+            let _ = writeln!(out, "{line}");
+        }
+        out
+    }
+
+    /// Renders `frame` as JSON.
+    pub fn render_json(&self, frame: &CallFrame) -> Result<String> {
+        Ok(serde_json::to_string_pretty(&self.decode(frame))?)
+    }
+
+    /// Returns `text` in `style` if the output is colored.
+    fn paint(&self, style: &str, text: &str) -> String {
+        if self.color {
+            format!("{style}{text}{}", style::RESET)
+        } else {
+            text.to_string()
+        }
+    }
+
+    /// Renders the lines of `frame`, with the events and sub-calls in execution order and the
+    /// result last.
+    fn render_frame(&self, frame: &DecodedFrame) -> Vec<String> {
+        let target =
+            frame.label.clone().unwrap_or_else(|| frame.to.unwrap_or_default().to_string());
+        let call = if frame.kind.starts_with("CREATE") {
+            format!("→ new {target}@{}", frame.to.unwrap_or_default())
+        } else {
+            let function = match (&frame.function, &frame.raw_input) {
+                (Some(signature), _) => {
+                    let name = signature.split('(').next().unwrap_or_default();
+                    let args: Vec<_> = frame.args.iter().map(|arg| arg.value.as_str()).collect();
+                    format!("{name}({})", args.join(", "))
+                }
+                (None, Some(input)) => input.clone(),
+                (None, None) => "fallback()".to_string(),
+            };
+            format!("{target}::{function}")
+        };
+        let mut header = format!(
+            "{} {}",
+            self.paint(style::DIM, &format!("[{}]", frame.gas_used)),
+            self.paint(if frame.success { style::GREEN } else { style::RED }, &call)
+        );
+        if !frame.value.is_zero() {
+            let _ = write!(header, " {{value: {} ETH}}", format_ether(frame.value));
+        }
+        match frame.kind.as_str() {
+            "STATICCALL" => header.push_str(" [staticcall]"),
+            "DELEGATECALL" => header.push_str(" [delegatecall]"),
+            _ => {}
+        }
+
+        // Events are emitted between the sub-calls at their position.
+        let mut children: Vec<Vec<String>> = Vec::new();
+        let mut events = frame.events.iter().peekable();
+        for (i, call) in frame.calls.iter().enumerate() {
+            while let Some(event) = events.next_if(|event| event.position <= i as u64) {
+                children.push(vec![self.render_event(event)]);
+            }
+            children.push(self.render_frame(call));
+        }
+        children.extend(events.map(|event| vec![self.render_event(event)]));
+        children.push(vec![self.render_result(frame)]);
+
+        let mut lines = vec![header];
+        let last = children.len() - 1;
+        for (i, child) in children.into_iter().enumerate() {
+            let (branch, rest) =
+                if i == last { ("└─ ", "    ") } else { ("├─ ", "│   ") };
+            for (j, line) in child.into_iter().enumerate() {
+                lines.push(format!("{}{line}", if j == 0 { branch } else { rest }));
+            }
+        }
+        lines
+    }
+
+    /// Renders `event`.
+    fn render_event(&self, event: &DecodedEvent) -> String {
+        let text = match (&event.name, &event.raw) {
+            (Some(name), _) => {
+                let params: Vec<_> = event
+                    .params
+                    .iter()
+                    .map(|param| format!("{}: {}", param.name, param.value))
+                    .collect();
+                format!("emit {name}({})", params.join(", "))
+            }
+            (None, raw) => {
+                let raw = raw.clone().unwrap_or_default();
+                let topics: Vec<_> = raw.topics().iter().map(ToString::to_string).collect();
+                format!("emit unknown(topics: [{}], data: {})", topics.join(", "), raw.data)
+            }
+        };
+        self.paint(style::CYAN, &text)
+    }
+
+    /// Renders the result of `frame`.
+    fn render_result(&self, frame: &DecodedFrame) -> String {
+        if let Some(reason) = &frame.revert_reason {
+            return self.paint(style::RED, &format!("← [Revert] {reason}"));
+        }
+        let returns: Vec<_> = frame.returns.iter().map(|param| param.value.as_str()).collect();
+        if returns.is_empty() {
+            self.paint(style::YELLOW, "← [Stop]")
+        } else {
+            format!("{} {}", self.paint(style::YELLOW, "← [Return]"), returns.join(", "))
+        }
+    }
+}
+
+/// The expected rendering of `fixtures/call_trace_swap.json`.
+const SWAP_TRACE: &str = "\
+[109372] UniswapV2Router02::swapExactTokensForTokens(1000000000, 328999066264364480, [USDC: [0xA0b86991c6218b36c1d19D4a2e9Eb0cE3606eB48], WETH: [0xC02aaA39b223FE8D0A0e5C4F27eAD9083C756Cc2]], 0x8ba1f109551bD432803012645Ac136ddd64DBA72, 1700000000)
+├─ [2504] UniswapV2Pair USDC/WETH::getReserves() [staticcall]
+│   └─ ← [Return] 30000000000000, 10000000000000000000000, 1699999000
+├─ [16418] USDC::transferFrom(0x8ba1f109551bD432803012645Ac136ddd64DBA72, UniswapV2Pair USDC/WETH: [0xB4e16d0168e52d35CaCD2c6185b44281Ec28C9Dc], 1000000000)
+│   ├─ [8929] FiatTokenV2_2::transferFrom(0x8ba1f109551bD432803012645Ac136ddd64DBA72, UniswapV2Pair USDC/WETH: [0xB4e16d0168e52d35CaCD2c6185b44281Ec28C9Dc], 1000000000) [delegatecall]
+│   │   ├─ emit Transfer(from: 0x8ba1f109551bD432803012645Ac136ddd64DBA72, to: UniswapV2Pair USDC/WETH: [0xB4e16d0168e52d35CaCD2c6185b44281Ec28C9Dc], value: 1000000000)
+│   │   └─ ← [Return] true
+│   └─ ← [Return] true
+├─ [67406] UniswapV2Pair USDC/WETH::swap(0, 332322289155923718, 0x8ba1f109551bD432803012645Ac136ddd64DBA72, 0x)
+│   ├─ [27962] WETH::transfer(0x8ba1f109551bD432803012645Ac136ddd64DBA72, 332322289155923718)
+│   │   ├─ emit Transfer(from: UniswapV2Pair USDC/WETH: [0xB4e16d0168e52d35CaCD2c6185b44281Ec28C9Dc], to: 0x8ba1f109551bD432803012645Ac136ddd64DBA72, value: 332322289155923718)
+│   │   └─ ← [Return] true
+│   ├─ [3812] USDC::balanceOf(UniswapV2Pair USDC/WETH: [0xB4e16d0168e52d35CaCD2c6185b44281Ec28C9Dc]) [staticcall]
+│   │   ├─ [1317] FiatTokenV2_2::balanceOf(UniswapV2Pair USDC/WETH: [0xB4e16d0168e52d35CaCD2c6185b44281Ec28C9Dc]) [delegatecall]
+│   │   │   └─ ← [Return] 30001000000000
+│   │   └─ ← [Return] 30001000000000
+│   ├─ [534] WETH::balanceOf(UniswapV2Pair USDC/WETH: [0xB4e16d0168e52d35CaCD2c6185b44281Ec28C9Dc]) [staticcall]
+│   │   └─ ← [Return] 9999667677710844076282
+│   ├─ emit Sync(reserve0: 30001000000000, reserve1: 9999667677710844076282)
+│   ├─ emit Swap(sender: UniswapV2Router02: [0x7a250d5630B4cF539739dF2C5dAcb4c659F2488D], amount0In: 1000000000, amount1In: 0, amount0Out: 0, amount1Out: 332322289155923718, to: 0x8ba1f109551bD432803012645Ac136ddd64DBA72)
+│   └─ ← [Stop]
+└─ ← [Return] [1000000000, 332322289155923718]
+";
+
+#[tokio::main]
+async fn main() -> Result<()> {
+    let router = address!("7a250d5630B4cF539739dF2C5dAcb4c659F2488D");
+    let pair = address!("B4e16d0168e52d35CaCD2c6185b44281Ec28C9Dc");
+    let usdc = address!("A0b86991c6218b36c1d19D4a2e9Eb0cE3606eB48");
+    let usdc_implementation = address!("43506849D7C04F9138D1A2050bbF3A0c054402dd");
+    let weth = address!("C02aaA39b223FE8D0A0e5C4F27eAD9083C756Cc2");
+
+    let mut renderer = TraceRenderer::new(false);
+    renderer.register_contract(router, "UniswapV2Router02", &IUniswapV2Router02::abi::contract());
+    renderer.register_contract(pair, "UniswapV2Pair USDC/WETH", &IUniswapV2Pair::abi::contract());
+    renderer.register_contract(usdc, "USDC", &IERC20::abi::contract());
+    renderer.register_contract(usdc_implementation, "FiatTokenV2_2", &IERC20::abi::contract());
+    renderer.register_contract(weth, "WETH", &IERC20::abi::contract());
+
+    let fixtures = std::env::current_dir()?.join("examples/transactions/examples/fixtures");
+    let read = |name: &str| -> Result<CallFrame> {
+        Ok(serde_json::from_str(&std::fs::read_to_string(fixtures.join(name))?)?)
+    };
+
+    // A swap through the Uniswap V2 router, with a proxied token.
+    let swap = read("call_trace_swap.json")?;
+    let rendered = renderer.render(&swap);
+    print!("{rendered}");
+    assert_eq!(rendered, SWAP_TRACE);
+
+    // The same swap reverting because of the slippage limit.
+    let reverted = read("call_trace_swap_reverted.json")?;
+    let rendered = renderer.render(&reverted);
+    print!("\n{rendered}");
+    assert!(
+        rendered.ends_with("└─ ← [Revert] revert: UniswapV2Router: INSUFFICIENT_OUTPUT_AMOUNT\n")
+    );
+
+    // The JSON output has the same decoded tree.
+    let json = renderer.render_json(&swap)?;
+    let json: serde_json::Value = serde_json::from_str(&json)?;
+    assert_eq!(json["calls"][2]["events"][1]["name"], "Swap");
+    assert_eq!(json["calls"][2]["calls"][0]["function"], "transfer(address,uint256)");
+    let json = renderer.render_json(&reverted)?;
+    println!("\n{json}");
+
+    // Colored for terminals.
+    let colored = TraceRenderer { color: true, ..renderer };
+    print!("\n{}", colored.render(&reverted));
+
+    Ok(())
+}
@@ -0,0 +1,367 @@
+//! Example of building a bundle of signed transactions, simulating it with `eth_callBundle` and
+//! submitting it with `eth_sendBundle` to a block builder.
+//!
+//! The requests are authenticated with the `X-Flashbots-Signature` header, signed by a reputation
+//! key that is separate from the keys signing the transactions. The example runs against a stand-in
+//! relay mocked as the transport of the provider, connect the provider to e.g.
+//! `https://relay.flashbots.net` to use a real one.
+
+use std::{
+    ops::RangeInclusive,
+    sync::{Arc, Mutex},
+    task::{Context, Poll},
+};
+
+use alloy::{
+    consensus::{transaction::SignerRecoverable, Transaction, TxEnvelope},
+    eips::{BlockNumberOrTag, Decodable2718, Encodable2718},
+    network::{EthereumWallet, TransactionBuilder},
+    primitives::{keccak256, Address, Bytes, TxHash, B256, U256},
+    providers::{
+        ext::{verify_flashbots_signature, MevApi, FLASHBOTS_SIGNATURE_HEADER},
+        Provider, ProviderBuilder,
+    },
+    rpc::{
+        client::RpcClient,
+        json_rpc::{RequestPacket, ResponsePacket, SerializedRequest},
+        types::{
+            mev::{
+                EthCallBundle, EthCallBundleResponse, EthCallBundleTransactionResult, EthSendBundle,
+            },
+            TransactionRequest,
+        },
+    },
+    signers::local::PrivateKeySigner,
+    transports::{TransportError, TransportErrorKind, TransportFut},
+};
+use eyre::{bail, ensure, eyre, OptionExt, Result};
+use serde_json::{json, Value};
+use tower::Service;
+
+/// Collects signed transactions into a bundle that targets a range of blocks.
+#[derive(Clone, Debug)]
+pub struct BundleBuilder {
+    txs: Vec<Bytes>,
+    blocks: RangeInclusive<u64>,
+    min_timestamp: Option<u64>,
+    max_timestamp: Option<u64>,
+    reverting_tx_hashes: Vec<TxHash>,
+}
+
+impl BundleBuilder {
+    /// Creates an empty bundle targeting every block in `blocks`.
+    pub const fn new(blocks: RangeInclusive<u64>) -> Self {
+        Self {
+            txs: Vec::new(),
+            blocks,
+            min_timestamp: None,
+            max_timestamp: None,
+            reverting_tx_hashes: Vec::new(),
+        }
+    }
+
+    /// Restricts the bundle to blocks with a timestamp between `min` and `max`.
+    pub const fn with_timestamps(mut self, min: u64, max: u64) -> Self {
+        self.min_timestamp = Some(min);
+        self.max_timestamp = Some(max);
+        self
+    }
+
+    /// Appends an EIP-2718 encoded signed transaction to the bundle and returns its hash.
+    pub fn push_raw(&mut self, raw_tx: Bytes) -> Result<TxHash> {
+        let envelope = TxEnvelope::decode_2718(&mut raw_tx.as_ref())?;
+        self.txs.push(raw_tx);
+        Ok(*envelope.tx_hash())
+    }
+
+    /// Signs `tx` with the wallet and appends it to the bundle.
+    ///
+    /// The bundle is not sent through a provider, so the nonce, gas and fee fields must be set.
+    pub async fn push_transaction(
+        &mut self,
+        tx: TransactionRequest,
+        wallet: &EthereumWallet,
+    ) -> Result<TxHash> {
+        let envelope = tx.build(wallet).await?;
+        self.push_raw(envelope.encoded_2718().into())
+    }
+
+    /// Appends a transaction that is allowed to revert without invalidating the bundle.
+    pub async fn push_reverting_transaction(
+        &mut self,
+        tx: TransactionRequest,
+        wallet: &EthereumWallet,
+    ) -> Result<TxHash> {
+        let tx_hash = self.push_transaction(tx, wallet).await?;
+        self.reverting_tx_hashes.push(tx_hash);
+        Ok(tx_hash)
+    }
+
+    /// Returns the `eth_callBundle` request simulating the bundle in the first target block, on
+    /// top of the state at `state_block`.
+    pub fn call_bundle(&self, state_block: BlockNumberOrTag) -> Result<EthCallBundle> {
+        ensure!(!self.txs.is_empty(), "cannot simulate an empty bundle");
+        Ok(EthCallBundle {
+            txs: self.txs.clone(),
+            block_number: *self.blocks.start(),
+            state_block_number: state_block,
+            timestamp: self.min_timestamp,
+            ..Default::default()
+        })
+    }
+
+    /// Returns one `eth_sendBundle` request per target block.
+    pub fn build(&self) -> Result<Vec<EthSendBundle>> {
+        ensure!(!self.txs.is_empty(), "cannot send an empty bundle");
+        ensure!(!self.blocks.is_empty(), "the target block range is empty");
+        Ok(self
+            .blocks
+            .clone()
+            .map(|block_number| EthSendBundle {
+                txs: self.txs.clone(),
+                block_number,
+                min_timestamp: self.min_timestamp,
+                max_timestamp: self.max_timestamp,
+                reverting_tx_hashes: self.reverting_tx_hashes.clone(),
+                ..Default::default()
+            })
+            .collect())
+    }
+}
+
+/// Simulates and submits bundles, authenticating every request with a reputation key.
+#[derive(Debug)]
+pub struct BundleClient<P> {
+    provider: P,
+    reputation: PrivateKeySigner,
+}
+
+impl<P: Provider> BundleClient<P> {
+    /// Creates a new [`BundleClient`] for the relay the provider is connected to.
+    pub const fn new(provider: P, reputation: PrivateKeySigner) -> Self {
+        Self { provider, reputation }
+    }
+
+    /// Simulates the bundle with `eth_callBundle` and fails if a transaction reverts that is not
+    /// allowed to.
+    pub async fn simulate(
+        &self,
+        bundle: &BundleBuilder,
+        state_block: BlockNumberOrTag,
+    ) -> Result<EthCallBundleResponse> {
+        let response = self
+            .provider
+            .call_bundle(bundle.call_bundle(state_block)?)
+            .with_auth(self.reputation.clone())
+            .await?
+            .ok_or_eyre("relay returned no simulation result")?;
+
+        for result in &response.results {
+            if result.revert.is_some() && !bundle.reverting_tx_hashes.contains(&result.tx_hash) {
+                bail!("transaction {} reverted in simulation", result.tx_hash);
+            }
+        }
+
+        Ok(response)
+    }
+
+    /// Submits the bundle for every target block with `eth_sendBundle` and returns the bundle
+    /// hashes acknowledged by the relay.
+    pub async fn send(&self, bundle: &BundleBuilder) -> Result<Vec<B256>> {
+        let mut bundle_hashes = Vec::new();
+        for request in bundle.build()? {
+            let response = self
+                .provider
+                .send_bundle(request)
+                .with_auth(self.reputation.clone())
+                .await?
+                .ok_or_eyre("relay did not acknowledge the bundle")?;
+            bundle_hashes.push(response.bundle_hash);
+        }
+        Ok(bundle_hashes)
+    }
+}
+
+/// A stand-in for a relay, used as the transport of a provider, that checks the signature of every
+/// request, records the bundles it receives and simulates transactions as plain transfers.
+#[derive(Clone, Debug, Default)]
+pub struct MockRelay {
+    bundles: Arc<Mutex<Vec<(Address, EthSendBundle)>>>,
+}
+
+impl MockRelay {
+    /// Returns the bundles received so far, along with the reputation address that signed them.
+    pub fn bundles(&self) -> Vec<(Address, EthSendBundle)> {
+        self.bundles.lock().unwrap().clone()
+    }
+
+    /// Returns the JSON-RPC response to a request, checking the signature the Flashbots header
+    /// carries over the serialized request.
+    fn respond(&self, request: &SerializedRequest) -> Value {
+        let body = request.serialized().get();
+        let outcome = request
+            .headers()
+            .and_then(|headers| headers.get(FLASHBOTS_SIGNATURE_HEADER))
+            .ok_or_else(|| eyre!("missing {FLASHBOTS_SIGNATURE_HEADER} header"))
+            .and_then(|signature| {
+                Ok(verify_flashbots_signature(signature.to_str()?, body.as_bytes())?)
+            })
+            .and_then(|searcher| self.handle(searcher, &serde_json::from_str(body)?));
+        match outcome {
+            Ok(result) => json!({ "jsonrpc": "2.0", "id": request.id(), "result": result }),
+            Err(err) => json!({
+                "jsonrpc": "2.0",
+                "id": request.id(),
+                "error": { "code": -32600, "message": err.to_string() },
+            }),
+        }
+    }
+
+    fn handle(&self, searcher: Address, request: &Value) -> Result<Value> {
+        let params = request["params"][0].clone();
+        match request["method"].as_str() {
+            Some("eth_callBundle") => {
+                let bundle: EthCallBundle = serde_json::from_value(params)?;
+                Ok(serde_json::to_value(Self::simulate(&bundle)?)?)
+            }
+            Some("eth_sendBundle") => {
+                let bundle: EthSendBundle = serde_json::from_value(params)?;
+                for tx in &bundle.txs {
+                    TxEnvelope::decode_2718(&mut tx.as_ref())?;
+                }
+                let bundle_hash = bundle.bundle_hash();
+                self.bundles.lock().unwrap().push((searcher, bundle));
+                Ok(json!({ "bundleHash": bundle_hash }))
+            }
+            method => bail!("method {method:?} not supported"),
+        }
+    }
+
+    /// Simulates every transaction as a transfer using 21000 gas that pays its fee cap.
+    fn simulate(bundle: &EthCallBundle) -> Result<EthCallBundleResponse> {
+        let mut results = Vec::with_capacity(bundle.txs.len());
+        for tx in &bundle.txs {
+            let envelope = TxEnvelope::decode_2718(&mut tx.as_ref())?;
+            let gas_used = 21_000;
+            let gas_price = U256::from(envelope.max_fee_per_gas());
+            let gas_fees = gas_price * U256::from(gas_used);
+            results.push(EthCallBundleTransactionResult {
+                coinbase_diff: gas_fees,
+                from_address: envelope.recover_signer()?,
+                gas_fees,
+                gas_price,
+                gas_used,
+                to_address: envelope.to(),
+                tx_hash: *envelope.tx_hash(),
+                value: Some(Bytes::new()),
+                ..Default::default()
+            });
+        }
+
+        let tx_hashes: Vec<u8> = results.iter().flat_map(|r| r.tx_hash.0).collect();
+        let gas_fees: U256 = results.iter().map(|r| r.gas_fees).sum();
+        let total_gas_used: u64 = results.iter().map(|r| r.gas_used).sum();
+        Ok(EthCallBundleResponse {
+            bundle_hash: keccak256(tx_hashes),
+            bundle_gas_price: gas_fees / U256::from(total_gas_used),
+            coinbase_diff: gas_fees,
+            gas_fees,
+            results,
+            state_block_number: bundle.state_block_number.as_number().unwrap_or_default(),
+            total_gas_used,
+            ..Default::default()
+        })
+    }
+}
+
+/// Implement the [`tower::Service`] trait for the [`MockRelay`], which makes it a transport.
+impl Service<RequestPacket> for MockRelay {
+    type Response = ResponsePacket;
+    type Error = TransportError;
+    type Future = TransportFut<'static>;
+
+    fn poll_ready(&mut self, _cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        Poll::Ready(Ok(()))
+    }
+
+    fn call(&mut self, packet: RequestPacket) -> Self::Future {
+        let response = match &packet {
+            RequestPacket::Single(request) => self.respond(request),
+            RequestPacket::Batch(requests) => {
+                requests.iter().map(|request| self.respond(request)).collect()
+            }
+        };
+        Box::pin(async move {
+            serde_json::from_str(&response.to_string()).map_err(TransportErrorKind::custom)
+        })
+    }
+}
+
+#[tokio::main]
+async fn main() -> Result<()> {
+    // Create a provider with the stand-in relay as its transport.
+    let relay = MockRelay::default();
+    let provider = ProviderBuilder::new().connect_client(RpcClient::new(relay.clone(), false));
+
+    // The reputation key only signs requests to the relay, it never holds any funds.
+    let reputation = PrivateKeySigner::random();
+
+    // Create two users, Alice and Bob, and a recipient, Charlie.
+    let alice = PrivateKeySigner::random();
+    let bob = PrivateKeySigner::random();
+    let charlie = PrivateKeySigner::random().address();
+    let mut wallet = EthereumWallet::new(alice.clone());
+    wallet.register_signer(bob.clone());
+
+    let transfer = |from: Address, nonce: u64| {
+        TransactionRequest::default()
+            .with_from(from)
+            .with_to(charlie)
+            .with_nonce(nonce)
+            .with_chain_id(1)
+            .with_value(U256::from(100))
+            .with_gas_limit(21_000)
+            .with_max_priority_fee_per_gas(1_000_000_000)
+            .with_max_fee_per_gas(20_000_000_000)
+    };
+
+    // Bundle two transactions from Alice and one from Bob, which may revert, for the next three
+    // blocks within the next minute.
+    let now = std::time::SystemTime::now().duration_since(std::time::UNIX_EPOCH)?.as_secs();
+    let mut bundle = BundleBuilder::new(20_000_001..=20_000_003).with_timestamps(now, now + 60);
+    bundle.push_transaction(transfer(alice.address(), 0), &wallet).await?;
+    bundle.push_transaction(transfer(alice.address(), 1), &wallet).await?;
+    let bob_tx = bundle.push_reverting_transaction(transfer(bob.address(), 0), &wallet).await?;
+
+    // Simulate the bundle on top of the latest state before submitting it.
+    let client = BundleClient::new(&provider, reputation.clone());
+    let simulation = client.simulate(&bundle, BlockNumberOrTag::Number(20_000_000)).await?;
+    println!(
+        "Simulated bundle {}: {} gas used, {} wei to the builder",
+        simulation.bundle_hash, simulation.total_gas_used, simulation.coinbase_diff
+    );
+    assert_eq!(simulation.results.len(), 3);
+
+    // Submit the bundle once for every target block.
+    let bundle_hashes = client.send(&bundle).await?;
+    for (request, hash) in bundle.build()?.iter().zip(&bundle_hashes) {
+        println!("Bundle {hash} sent for block {}", request.block_number);
+    }
+    assert_eq!(bundle_hashes.len(), 3);
+    assert!(bundle_hashes.iter().all(|hash| *hash == simulation.bundle_hash));
+
+    // The relay attributes every bundle to the reputation key.
+    let received = relay.bundles();
+    assert_eq!(received.len(), 3);
+    for (searcher, sent) in received {
+        assert_eq!(searcher, reputation.address());
+        assert_eq!(sent.reverting_tx_hashes, vec![bob_tx]);
+        assert_eq!(sent.max_timestamp, Some(now + 60));
+    }
+
+    // Requests without the signature header are rejected by the relay.
+    let requests = bundle.build()?;
+    assert!(provider.send_bundle(requests[0].clone()).await.is_err());
+
+    Ok(())
+}
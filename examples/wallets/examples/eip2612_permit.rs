@@ -0,0 +1,506 @@
+//! Example of signing [EIP-2612](https://eips.ethereum.org/EIPS/eip-2612) permits with the EIP-712
+//! domain of the token discovered on-chain, including DAI's non-standard permit.
+
+use std::{borrow::Cow, future::IntoFuture};
+
+use alloy::{
+    contract,
+    network::TransactionBuilder,
+    node_bindings::Anvil,
+    primitives::{address, keccak256, Address, Bytes, Signature, B256, U256},
+    providers::{Provider, ProviderBuilder},
+    rpc::types::TransactionRequest,
+    signers::{local::PrivateKeySigner, Signer},
+    sol,
+    sol_types::{Eip712Domain, SolCall, SolStruct},
+};
+use eyre::{bail, ensure, Result};
+
+sol! {
+    #[allow(missing_docs)]
+    #[sol(rpc)]
+    interface IERC20Permit {
+        function name() external view returns (string);
+        function version() external view returns (string);
+        function nonces(address owner) external view returns (uint256);
+        function allowance(address owner, address spender) external view returns (uint256);
+        function DOMAIN_SEPARATOR() external view returns (bytes32);
+        function PERMIT_TYPEHASH() external view returns (bytes32);
+        function eip712Domain() external view returns (
+            bytes1 fields,
+            string name,
+            string version,
+            uint256 chainId,
+            address verifyingContract,
+            bytes32 salt,
+            uint256[] extensions
+        );
+    }
+
+    // The permits are encoded as calldata for anyone to submit, so no bindings are needed.
+    #[allow(missing_docs)]
+    interface IERC2612 {
+        function permit(
+            address owner,
+            address spender,
+            uint256 value,
+            uint256 deadline,
+            uint8 v,
+            bytes32 r,
+            bytes32 s
+        ) external;
+    }
+
+    #[allow(missing_docs)]
+    #[derive(Debug)]
+    struct Permit {
+        address owner;
+        address spender;
+        uint256 value;
+        uint256 nonce;
+        uint256 deadline;
+    }
+}
+
+sol! {
+    // A minimal token implementing only the EIP-2612 permit and the allowances.
+    #[allow(missing_docs, clippy::too_many_arguments)]
+    // solar v0.2.0; solar PermitToken.sol -Zcodegen --emit bin
+    #[sol(rpc, bytecode = "6108b380600a5f395ff3346108af5736600d576108af565b5f3560e01c806306fdde031461015157806330adf81f1460615780633644e5151461024a57806354fd4d50146101d35780637ecebe00146091578063d505accf146102ff578063dd62ed3e1460df576108af565b5060a06040527f6e71edae12b1b97f4d1f60370fef10105fa2faae0126114a169c64845d6126c960805260206080f35b5060c0604052366004810390506020811290501560ad575b5f80fd5b6004355f1960601c818060a05216811490501560a9575b5f6080526004355f525f60205260405f205460805260206080f35b5060e0604052366004810390506040811290501560fb575b5f80fd5b6004355f1960601c818060a05216811490501560f7575b6024355f1960601c818060c05216811490501560f7575b5f6080526004355f52600160205260405f206024355f5260205260405f205460805260206080f35b506101406040525f6080526040516040818060a05201600c8252602082016b5065726d697420546f6b656e60a01b818060c05252606083018060e05260405281806101005260e051038252600c60e05152608083015f8180610120525260a084019350600c828060c0528280610120525e505080610100528103905061010051f35b506101406040525f6080526040516040818060a052016001825260208201603160f81b818060c05252606083018060e05260405281806101005260e051038252600160e05152608083015f8180610120525260a0840193506001828060c0528280610120525e505080610100528103905061010051f35b506101006040525f608052468060c05260a052306040517f8b73c3c69bb8fe3d512ecc4cf759cc79239f7b179b0ffacaa9a75d522b39400f818060e05252602081017fce9811de3d460752170ab4b750555e0fa501e9f1e07174a522573f3b35aa06be815250604081017fc89efdaa54c0f20c7adf612882df0950f5a951637e0307cdcb4c672f298b8bc68152506060810160c0518060c0528152506080810182815250905060a08120905060805260206080f35b506102606040523660048103905060e0811290501561031d575b5f80fd5b6004355f1960601c818061012052168114905015610319575b6024355f1960601c818061014052168114905015610319575b60843560ff818061016052168114905015610319575b4260643581119050156103c7575b60405160408180610180520160405260078152602081019050666578706972656460c81b81806101a052526308c379a060e01b5f52602060045260076024525f604452600781806101a05260445e5060645ffd5b6004355f525f60205260405f205480610100528061010052600181018060c0528060c05281806101005281101561040f575b634e487b7160e01b5f525050601160045260245ffd5b6004355f525f60205260405f20818060c052815550506040517f6e71edae12b1b97f4d1f60370fef10105fa2faae0126114a169c64845d6126c981806101c0525260208101600435815250604081016024358152506060810160443581525060808101828061010052815250905060a0810160643581525060c081209050806101e0526040516104b2815260a05181602001528060a05260c00160405250610619565b60a0516040015160a05160405260a0516020015160a05260405161190160f01b8180610200525260028101828152509050602281016101e051806101e052815250604281209050604051610538815260a0518160200152818160400152608435816060015260a435816080015260c4358160a001528060a05261024001604052506106c1565b60a05160c0015160a05160405260a0516020015160a0528060e0528060e05215610569575b60043560e051146105c7575b6040516040818061022052016040526011815260208101905070696e76616c6964207369676e617475726560781b818061024052526308c379a060e01b5f52602060045260116024525f604452601181806102405260445e5060645ffd5b6004355f52600160205260405f206024355f5260205260405f206044358155506044355f526024356004357f8c5be1e5ebec7d5bd14f71427d1e84f3dd0314c0f7b2291e5b200ac8c7c3b92560205fa3005b46306040517f8b73c3c69bb8fe3d512ecc4cf759cc79239f7b179b0ffacaa9a75d522b39400f818060a05160a0015252602081017fce9811de3d460752170ab4b750555e0fa501e9f1e07174a522573f3b35aa06be815250604081017fc89efdaa54c0f20c7adf612882df0950f5a951637e0307cdcb4c672f298b8bc68152506060810183815250915060808201818152505060a0812090508060a051604001525060a05151565b60a05160e0015f81525060405160a05160400151818060a0516101800152526020810160a051606001518152506040810160a051608001518152506060810160a05160a00151815250608081016040525a5f5f608060a051610180015160015afa9050905060a0516101000181815250503d601f818060a0516101a0015201601f198116905060208101905060405181818060a0516101c00152019150816040529050818060a0516101a00152815260208101828060a0516101a001525f823e50905060a05161012001818060a0516101c001528152505060a0516101000151156107bb575b60a051610120015151602081149050610825575b6040516040818060a0516101e00152016040526011815260208101905070696e76616c6964207369676e617475726560781b818060a0516102000152526308c379a060e01b5f52602060045260116024525f6044526011818060a051610200015260445e5060645ffd5b60a05161012001518060a05161016001528060a05161016001528060a05161016001525160208110905015610859575b5f5ffd5b602060a051610160015101515f1960601c818060a0516102200152168060a05161014001528060a05161014001528060a05161014001528114905015610855575b60a051610140015160a05160c0015260a05151565b5f5ffd")]
+    contract PermitToken {
+        bytes32 public constant PERMIT_TYPEHASH = keccak256(
+            "Permit(address owner,address spender,uint256 value,uint256 nonce,uint256 deadline)"
+        );
+
+        bytes32 private constant DOMAIN_TYPEHASH = keccak256(
+            "EIP712Domain(string name,string version,uint256 chainId,address verifyingContract)"
+        );
+
+        mapping(address => uint256) public nonces;
+        mapping(address => mapping(address => uint256)) public allowance;
+
+        event Approval(address indexed owner, address indexed spender, uint256 value);
+
+        function name() public pure returns (string memory) {
+            return "Permit Token";
+        }
+
+        function version() public pure returns (string memory) {
+            return "1";
+        }
+
+        function DOMAIN_SEPARATOR() public view returns (bytes32) {
+            uint256 chainId;
+            assembly {
+                chainId := chainid()
+            }
+            return keccak256(
+                abi.encode(
+                    DOMAIN_TYPEHASH, keccak256("Permit Token"), keccak256("1"), chainId, address(this)
+                )
+            );
+        }
+
+        function permit(
+            address owner,
+            address spender,
+            uint256 value,
+            uint256 deadline,
+            uint8 v,
+            bytes32 r,
+            bytes32 s
+        ) external {
+            require(block.timestamp <= deadline, "expired");
+            bytes32 structHash =
+                keccak256(abi.encode(PERMIT_TYPEHASH, owner, spender, value, nonces[owner]++, deadline));
+            bytes32 digest = keccak256(abi.encodePacked("\x19\x01", DOMAIN_SEPARATOR(), structHash));
+            address signer = recover(digest, v, r, s);
+            require(signer != address(0) && signer == owner, "invalid signature");
+            allowance[owner][spender] = value;
+            emit Approval(owner, spender, value);
+        }
+
+        function recover(bytes32 digest, uint8 v, bytes32 r, bytes32 s) internal view returns (address) {
+            (bool success, bytes memory signer) = address(1).staticcall(abi.encode(digest, v, r, s));
+            require(success && signer.length == 32, "invalid signature");
+            return abi.decode(signer, (address));
+        }
+    }
+
+    // A minimal token implementing only DAI's permit and the allowances.
+    #[allow(missing_docs, clippy::too_many_arguments)]
+    // solar v0.2.0; solar DaiPermitToken.sol -Zcodegen --emit bin
+    #[sol(rpc, bytecode = "6109ba80600a5f395ff3346109b65736600d576109b6565b5f3560e01c806306fdde031461015157806330adf81f1460615780633644e5151461024e57806354fd4d50146101d75780637ecebe001460915780638fcbaf0c14610303578063dd62ed3e1460df576109b6565b5060a06040527fea2aa0a1be11a07ed86d755c93467f4f82362b452371d1ba94d1715123511acb60805260206080f35b5060c0604052366004810390506020811290501560ad575b5f80fd5b6004355f1960601c818060a05216811490501560a9575b5f6080526004355f525f60205260405f205460805260206080f35b5060e0604052366004810390506040811290501560fb575b5f80fd5b6004355f1960601c818060a05216811490501560f7575b6024355f1960601c818060c05216811490501560f7575b5f6080526004355f52600160205260405f206024355f5260205260405f205460805260206080f35b506101406040525f6080526040516040818060a0520160108252602082016f446169205065726d697420546f6b656e60801b818060c05252606083018060e05260405281806101005260e051038252601060e05152608083015f8180610120525260a0840193506010828060c0528280610120525e505080610100528103905061010051f35b506101406040525f6080526040516040818060a052016001825260208201603160f81b818060c05252606083018060e05260405281806101005260e051038252600160e05152608083015f8180610120525260a0840193506001828060c0528280610120525e505080610100528103905061010051f35b506101006040525f608052468060c05260a052306040517f8b73c3c69bb8fe3d512ecc4cf759cc79239f7b179b0ffacaa9a75d522b39400f818060e05252602081017f91e91cb353425263235e8ac1ea53415170bf328075879124cf874a89ef675844815250604081017fc89efdaa54c0f20c7adf612882df0950f5a951637e0307cdcb4c672f298b8bc68152506060810160c0518060c0528152506080810182815250905060a08120905060805260206080f35b50610320604052366004810390506101008112905015610322575b5f80fd5b6004355f1960601c81806101205216811490501561031e575b6024355f1960601c81806101405216811490501561031e575b6084358061016052151561016051141561031e575b60a43560ff81806101805216811490501561031e575b6040517fea2aa0a1be11a07ed86d755c93467f4f82362b452371d1ba94d1715123511acb81806101a052526020810160043581525060408101602435815250606081016044358152506080810160643581525060a0810160843581525060c081209050806101c052604051610407815260a05181602001528060a05260c00160405250610720565b60a0516040015160a05160405260a0516020015160a05260405161190160f01b81806101e0525260028101828152509050602281016101c051806101c052815250604281208060c05290508060c05260043590506104be575b6040516040818061020052016040526011815260208101905070696e76616c69642d616464726573732d3060781b818061022052526308c379a060e01b5f52602060045260116024525f604452601181806102205260445e5060645ffd5b6040516104fe815260a051816020015260c051816040015260a435816060015260c435816080015260e4358160a001528060a052610240016040526107c8565b60a05160c0015160a05160405260a0516020015160a05260043514610579575b604051604081806102405201604052600e81526020810190506d696e76616c69642d7065726d697460901b818061026052526308c379a060e01b5f526020600452600e6024525f604452600e81806102605260445e5060645ffd5b606435156105eb575b4260643581119050156105eb575b604051604081806102805201604052600e81526020810190506d7065726d69742d6578706972656460901b81806102a052526308c379a060e01b5f526020600452600e6024525f604452600e81806102a05260445e5060645ffd5b6004355f525f60205260405f205480610100528061010052600181018060e0528060e052818061010052811015610633575b634e487b7160e01b5f525050601160045260245ffd5b6004355f525f60205260405f20818060e052815550508061010052604435146106b1575b604051604081806102c05201604052600d81526020810190506c696e76616c69642d6e6f6e636560981b81806102e052526308c379a060e01b5f526020600452600d6024525f604452600d81806102e05260445e5060645ffd5b608435156106c3575b5f195f526106c7565b5f5f525b5f516004355f52600160205260405f206024355f5260205260405f2081806103005281555080610300525f526024356004357f8c5be1e5ebec7d5bd14f71427d1e84f3dd0314c0f7b2291e5b200ac8c7c3b92560205fa3005b46306040517f8b73c3c69bb8fe3d512ecc4cf759cc79239f7b179b0ffacaa9a75d522b39400f818060a05160a0015252602081017f91e91cb353425263235e8ac1ea53415170bf328075879124cf874a89ef675844815250604081017fc89efdaa54c0f20c7adf612882df0950f5a951637e0307cdcb4c672f298b8bc68152506060810183815250915060808201818152505060a0812090508060a051604001525060a05151565b60a05160e0015f81525060405160a05160400151818060a0516101800152526020810160a051606001518152506040810160a051608001518152506060810160a05160a00151815250608081016040525a5f5f608060a051610180015160015afa9050905060a0516101000181815250503d601f818060a0516101a0015201601f198116905060208101905060405181818060a0516101c00152019150816040529050818060a0516101a00152815260208101828060a0516101a001525f823e50905060a05161012001818060a0516101c001528152505060a0516101000151156108c2575b60a05161012001515160208114905061092c575b6040516040818060a0516101e00152016040526011815260208101905070696e76616c6964207369676e617475726560781b818060a0516102000152526308c379a060e01b5f52602060045260116024525f6044526011818060a051610200015260445e5060645ffd5b60a05161012001518060a05161016001528060a05161016001528060a05161016001525160208110905015610960575b5f5ffd5b602060a051610160015101515f1960601c818060a0516102200152168060a05161014001528060a05161014001528060a0516101400152811490501561095c575b60a051610140015160a05160c0015260a05151565b5f5ffd")]
+    contract DaiPermitToken {
+        bytes32 public constant PERMIT_TYPEHASH = keccak256(
+            "Permit(address holder,address spender,uint256 nonce,uint256 expiry,bool allowed)"
+        );
+
+        bytes32 private constant DOMAIN_TYPEHASH = keccak256(
+            "EIP712Domain(string name,string version,uint256 chainId,address verifyingContract)"
+        );
+
+        mapping(address => uint256) public nonces;
+        mapping(address => mapping(address => uint256)) public allowance;
+
+        event Approval(address indexed owner, address indexed spender, uint256 value);
+
+        function name() public pure returns (string memory) {
+            return "Dai Permit Token";
+        }
+
+        function version() public pure returns (string memory) {
+            return "1";
+        }
+
+        function DOMAIN_SEPARATOR() public view returns (bytes32) {
+            uint256 chainId;
+            assembly {
+                chainId := chainid()
+            }
+            return keccak256(
+                abi.encode(
+                    DOMAIN_TYPEHASH, keccak256("Dai Permit Token"), keccak256("1"), chainId, address(this)
+                )
+            );
+        }
+
+        function permit(
+            address holder,
+            address spender,
+            uint256 nonce,
+            uint256 expiry,
+            bool allowed,
+            uint8 v,
+            bytes32 r,
+            bytes32 s
+        ) external {
+            bytes32 structHash =
+                keccak256(abi.encode(PERMIT_TYPEHASH, holder, spender, nonce, expiry, allowed));
+            bytes32 digest = keccak256(abi.encodePacked("\x19\x01", DOMAIN_SEPARATOR(), structHash));
+            require(holder != address(0), "invalid-address-0");
+            require(holder == recover(digest, v, r, s), "invalid-permit");
+            require(expiry == 0 || block.timestamp <= expiry, "permit-expired");
+            require(nonce == nonces[holder]++, "invalid-nonce");
+            uint256 value = allowed ? type(uint256).max : 0;
+            allowance[holder][spender] = value;
+            emit Approval(holder, spender, value);
+        }
+
+        function recover(bytes32 digest, uint8 v, bytes32 r, bytes32 s) internal view returns (address) {
+            (bool success, bytes memory signer) = address(1).staticcall(abi.encode(digest, v, r, s));
+            require(success && signer.length == 32, "invalid signature");
+            return abi.decode(signer, (address));
+        }
+    }
+}
+
+/// The permit of DAI, which signs an all-or-nothing approval instead of an amount.
+///
+/// The struct has the same EIP-712 name as the EIP-2612 permit, hence the module.
+pub mod dai {
+    alloy::sol! {
+        #[allow(missing_docs)]
+        #[derive(Debug)]
+        struct Permit {
+            address holder;
+            address spender;
+            uint256 nonce;
+            uint256 expiry;
+            bool allowed;
+        }
+
+        #[allow(missing_docs)]
+        interface IDaiPermit {
+            function permit(
+                address holder,
+                address spender,
+                uint256 nonce,
+                uint256 expiry,
+                bool allowed,
+                uint8 v,
+                bytes32 r,
+                bytes32 s
+            ) external;
+        }
+    }
+}
+
+/// The flavor of permit a token implements.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum PermitKind {
+    /// The EIP-2612 permit of an amount until a deadline.
+    Eip2612,
+    /// The permit of DAI, approving an unlimited amount or revoking the approval.
+    Dai,
+}
+
+/// A signed permit, ready to be submitted by anyone.
+#[derive(Debug)]
+pub enum SignedPermit {
+    /// An EIP-2612 permit.
+    Eip2612(Permit, Signature),
+    /// A DAI permit.
+    Dai(dai::Permit, Signature),
+}
+
+impl SignedPermit {
+    /// Returns the calldata of the `permit` call of the token.
+    pub fn calldata(&self) -> Bytes {
+        match self {
+            Self::Eip2612(permit, signature) => IERC2612::permitCall {
+                owner: permit.owner,
+                spender: permit.spender,
+                value: permit.value,
+                deadline: permit.deadline,
+                v: 27 + signature.v() as u8,
+                r: signature.r().into(),
+                s: signature.s().into(),
+            }
+            .abi_encode(),
+            Self::Dai(permit, signature) => dai::IDaiPermit::permitCall {
+                holder: permit.holder,
+                spender: permit.spender,
+                nonce: permit.nonce,
+                expiry: permit.expiry,
+                allowed: permit.allowed,
+                v: 27 + signature.v() as u8,
+                r: signature.r().into(),
+                s: signature.s().into(),
+            }
+            .abi_encode(),
+        }
+        .into()
+    }
+}
+
+/// Returns `None` if an optional view function is not implemented, i.e. the call reverts or
+/// returns nothing, and errors on transport failures.
+fn optional<T>(result: Result<T, contract::Error>) -> Result<Option<T>> {
+    match result {
+        Ok(value) => Ok(Some(value)),
+        Err(contract::Error::TransportError(err)) if !err.is_error_resp() => Err(err.into()),
+        Err(_) => Ok(None),
+    }
+}
+
+/// Signs permits of a token in the EIP-712 domain the token actually uses.
+#[derive(Debug)]
+pub struct PermitSigner<P> {
+    token: IERC20Permit::IERC20PermitInstance<P>,
+    domain: Eip712Domain,
+    kind: PermitKind,
+}
+
+impl<P: Provider> PermitSigner<P> {
+    /// Discovers the EIP-712 domain and the permit flavor of `token`.
+    ///
+    /// The domain is read from EIP-5267 `eip712Domain()` when the token implements it. Otherwise
+    /// it is built from `name()`, `version()` or common versions, the chain id and the token
+    /// address, and the candidate matching `DOMAIN_SEPARATOR()` is kept.
+    pub async fn discover(token: Address, provider: P) -> Result<Self> {
+        let chain_id = provider.get_chain_id().await?;
+        let token = IERC20Permit::new(token, provider);
+        let separator = token.DOMAIN_SEPARATOR().call().await?;
+
+        let domain = match optional(token.eip712Domain().call().await)? {
+            Some(eip5267) => {
+                let has = |bit: u8| eip5267.fields[0] & bit != 0;
+                Eip712Domain::new(
+                    has(0x01).then_some(Cow::Owned(eip5267.name)),
+                    has(0x02).then_some(Cow::Owned(eip5267.version)),
+                    has(0x04).then_some(eip5267.chainId),
+                    has(0x08).then_some(eip5267.verifyingContract),
+                    has(0x10).then_some(eip5267.salt),
+                )
+            }
+            None => {
+                let name = token.name().call().await?;
+                let versions = match optional(token.version().call().await)? {
+                    Some(version) => vec![Some(version)],
+                    None => vec![Some("1".to_string()), Some("2".to_string()), None],
+                };
+                let mut candidates = versions.into_iter().map(|version| {
+                    Eip712Domain::new(
+                        Some(Cow::Owned(name.clone())),
+                        version.map(Cow::Owned),
+                        Some(U256::from(chain_id)),
+                        Some(*token.address()),
+                        None,
+                    )
+                });
+                match candidates.find(|domain| domain.separator() == separator) {
+                    Some(domain) => domain,
+                    None => bail!("no known domain of {} matches its separator", token.address()),
+                }
+            }
+        };
+        ensure!(
+            domain.separator() == separator,
+            "the domain of {} does not match its separator",
+            token.address()
+        );
+
+        let kind = match optional(token.PERMIT_TYPEHASH().call().await)? {
+            Some(type_hash)
+                if type_hash == keccak256(dai::Permit::eip712_encode_type().as_bytes()) =>
+            {
+                PermitKind::Dai
+            }
+            _ => PermitKind::Eip2612,
+        };
+
+        Ok(Self { token, domain, kind })
+    }
+
+    /// Returns the EIP-712 domain of the token.
+    pub const fn domain(&self) -> &Eip712Domain {
+        &self.domain
+    }
+
+    /// Returns the permit flavor of the token.
+    pub const fn kind(&self) -> PermitKind {
+        self.kind
+    }
+
+    /// Signs a permit for `spender` to spend `value` of the tokens of `signer` until `deadline`,
+    /// with the current nonce of the signer.
+    ///
+    /// DAI permits can only approve an unlimited amount or revoke the approval, so `value` must be
+    /// `U256::MAX` or zero for DAI.
+    ///
+    /// The separator is checked against the token again before signing, e.g. in case the chain
+    /// forked to a new chain id since the domain was discovered.
+    pub async fn sign<S: Signer + ?Sized>(
+        &self,
+        signer: &S,
+        spender: Address,
+        value: U256,
+        deadline: U256,
+    ) -> Result<SignedPermit> {
+        let owner = signer.address();
+        let (nonce, separator) = (self.token.nonces(owner), self.token.DOMAIN_SEPARATOR());
+        let (nonce, separator) =
+            tokio::try_join!(nonce.call().into_future(), separator.call().into_future())?;
+        ensure!(self.domain.separator() == separator, "the separator of the token changed");
+
+        match self.kind {
+            PermitKind::Eip2612 => {
+                let permit = Permit { owner, spender, value, nonce, deadline };
+                let signature = self.sign_struct(signer, &permit).await?;
+                Ok(SignedPermit::Eip2612(permit, signature))
+            }
+            PermitKind::Dai => {
+                ensure!(
+                    value.is_zero() || value == U256::MAX,
+                    "DAI permits approve an unlimited amount or nothing"
+                );
+                let permit = dai::Permit {
+                    holder: owner,
+                    spender,
+                    nonce,
+                    expiry: deadline,
+                    allowed: !value.is_zero(),
+                };
+                let signature = self.sign_struct(signer, &permit).await?;
+                Ok(SignedPermit::Dai(permit, signature))
+            }
+        }
+    }
+
+    /// Signs the EIP-712 hash of `permit` in the domain of the token.
+    async fn sign_struct<S: Signer + ?Sized>(
+        &self,
+        signer: &S,
+        permit: &impl SolStruct,
+    ) -> Result<Signature> {
+        let hash: B256 = permit.eip712_signing_hash(&self.domain);
+        Ok(signer.sign_hash(&hash).await?)
+    }
+}
+
+/// Signs a permit of `value` from `owner` to `spender` for the token, submits it and checks the
+/// allowance, returning the calldata of the permit.
+async fn submit_permit<P: Provider>(
+    provider: &P,
+    name: &str,
+    token: Address,
+    owner: &PrivateKeySigner,
+    spender: Address,
+    value: U256,
+) -> Result<Bytes> {
+    let permits = PermitSigner::discover(token, provider).await?;
+    println!(
+        "{name}: {:?} permit, domain name {:?} version {:?}",
+        permits.kind(),
+        permits.domain().name,
+        permits.domain().version
+    );
+
+    let signed = permits.sign(owner, spender, value, U256::from(u64::MAX)).await?;
+    let tx = TransactionRequest::default().with_to(token).with_input(signed.calldata());
+    let receipt = provider.send_transaction(tx).await?.get_receipt().await?;
+    ensure!(receipt.status(), "permit of {name} reverted");
+
+    let token = IERC20Permit::new(token, provider);
+    assert_eq!(token.allowance(owner.address(), spender).call().await?, value);
+    Ok(signed.calldata())
+}
+
+#[tokio::main]
+async fn main() -> Result<()> {
+    // Spin up a local Anvil node.
+    // Ensure `anvil` is available in $PATH.
+    let anvil = Anvil::new().try_spawn()?;
+    let provider =
+        ProviderBuilder::new().wallet(anvil.wallet().unwrap()).connect_http(anvil.endpoint_url());
+
+    // The owner only signs, the permits are submitted by an Anvil account.
+    let owner = PrivateKeySigner::random();
+    let spender = anvil.addresses()[1];
+
+    // Deploy a token with an EIP-2612 permit and one with DAI's permit, which approves an
+    // unlimited amount. Both read their version `1` from `version()`.
+    let permit_token = *PermitToken::deploy(&provider).await?.address();
+    let dai_token = *DaiPermitToken::deploy(&provider).await?.address();
+    let tokens = [
+        ("Permit Token", permit_token, U256::from(1000)),
+        ("Dai Permit Token", dai_token, U256::MAX),
+    ];
+
+    for (name, token, value) in tokens {
+        let calldata = submit_permit(&provider, name, token, &owner, spender, value).await?;
+
+        // The permit consumed the nonce of the owner, so it cannot be submitted again.
+        let token = IERC20Permit::new(token, &provider);
+        assert_eq!(token.nonces(owner.address()).call().await?, U256::from(1));
+        let tx = TransactionRequest::default().with_to(*token.address()).with_input(calldata);
+        assert!(provider.send_transaction(tx).await.is_err());
+    }
+
+    // Sign the permits of mainnet tokens on a fork, if a mainnet RPC URL is set.
+    let Ok(rpc_url) = std::env::var("ETH_RPC_URL") else { return Ok(()) };
+    let anvil = Anvil::new().fork(rpc_url).try_spawn()?;
+    let provider =
+        ProviderBuilder::new().wallet(anvil.wallet().unwrap()).connect_http(anvil.endpoint_url());
+    let spender = anvil.addresses()[1];
+
+    let tokens = [
+        // Version `2` read from `version()`.
+        ("USDC", address!("A0b86991c6218b36c1d19D4a2e9Eb0cE3606eB48"), U256::from(1000)),
+        // No `version()`, version `1` is found by matching the separator.
+        (
+            "Uniswap V2 USDC/WETH",
+            address!("B4e16d0168e52d35CaCD2c6185b44281Ec28C9Dc"),
+            U256::from(1),
+        ),
+        // DAI's permit approves an unlimited amount.
+        ("DAI", address!("6B175474E89094C44Da98b954EedeAC495271d0F"), U256::MAX),
+    ];
+
+    for (name, token, value) in tokens {
+        submit_permit(&provider, name, token, &owner, spender, value).await?;
+    }
+
+    Ok(())
+}
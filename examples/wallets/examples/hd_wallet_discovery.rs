@@ -0,0 +1,198 @@
+//! Example of discovering the used accounts of an HD wallet by scanning its derivation paths for
+//! on-chain activity.
+
+use std::{collections::HashSet, future::IntoFuture};
+
+use alloy::{
+    network::{EthereumWallet, TransactionBuilder},
+    node_bindings::Anvil,
+    primitives::{utils::parse_ether, Address, U256},
+    providers::{Provider, ProviderBuilder},
+    rpc::types::TransactionRequest,
+    signers::local::{coins_bip39::English, MnemonicBuilder, PrivateKeySigner},
+};
+use eyre::{OptionExt, Result};
+
+/// The phrase of the default Anvil mnemonic.
+const ANVIL_MNEMONIC: &str = "test test test test test test test test test test test junk";
+
+/// A layout of derivation paths that wallets use to number their accounts.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum DerivationScheme {
+    /// BIP-44 accounts at `m/44'/60'/0'/0/{index}`, used by most software wallets.
+    Bip44,
+    /// Ledger Live accounts at `m/44'/60'/{index}'/0/0`.
+    LedgerLive,
+}
+
+impl DerivationScheme {
+    /// Returns the derivation path of the account at `index`.
+    pub fn path(&self, index: u32) -> String {
+        match self {
+            Self::Bip44 => format!("m/44'/60'/0'/0/{index}"),
+            Self::LedgerLive => format!("m/44'/60'/{index}'/0/0"),
+        }
+    }
+}
+
+/// An account of the wallet that has been used on-chain.
+#[derive(Clone, Debug)]
+pub struct DiscoveredAccount {
+    /// The derivation path of the account.
+    pub path: String,
+    /// The signer derived at the path.
+    pub signer: PrivateKeySigner,
+    /// The nonce of the account.
+    pub nonce: u64,
+    /// The balance of the account.
+    pub balance: U256,
+}
+
+/// Walks the derivation paths of a mnemonic until `gap_limit` consecutive accounts are unused.
+#[derive(Debug)]
+pub struct AccountDiscovery<P> {
+    provider: P,
+    phrase: String,
+    schemes: Vec<DerivationScheme>,
+    gap_limit: u32,
+}
+
+impl<P: Provider> AccountDiscovery<P> {
+    /// Creates a new [`AccountDiscovery`] scanning the BIP-44 paths of `phrase` with the gap limit
+    /// of 20 recommended by BIP-44.
+    pub fn new(provider: P, phrase: impl Into<String>) -> Self {
+        Self {
+            provider,
+            phrase: phrase.into(),
+            schemes: vec![DerivationScheme::Bip44],
+            gap_limit: 20,
+        }
+    }
+
+    /// Also scans the paths of the given scheme.
+    pub fn with_scheme(mut self, scheme: DerivationScheme) -> Self {
+        if !self.schemes.contains(&scheme) {
+            self.schemes.push(scheme);
+        }
+        self
+    }
+
+    /// Sets the number of consecutive unused accounts after which the scan of a scheme stops.
+    pub const fn with_gap_limit(mut self, gap_limit: u32) -> Self {
+        self.gap_limit = gap_limit;
+        self
+    }
+
+    /// Returns the accounts with a non-zero nonce or balance, in scan order.
+    ///
+    /// Schemes can derive the same path, e.g. the first account of both schemes is
+    /// `m/44'/60'/0'/0/0`, such accounts are only returned once.
+    pub async fn discover(&self) -> Result<Vec<DiscoveredAccount>> {
+        let mut seen = HashSet::new();
+        let mut accounts = Vec::new();
+
+        for scheme in &self.schemes {
+            let mut gap = 0;
+            let mut index = 0;
+            while gap < self.gap_limit {
+                let path = scheme.path(index);
+                let signer = MnemonicBuilder::<English>::default()
+                    .phrase(self.phrase.as_str())
+                    .derivation_path(&path)?
+                    .build()?;
+                let address = signer.address();
+                index += 1;
+
+                let (nonce, balance) = tokio::try_join!(
+                    self.provider.get_transaction_count(address).into_future(),
+                    self.provider.get_balance(address).into_future(),
+                )?;
+                if nonce == 0 && balance.is_zero() {
+                    gap += 1;
+                    continue;
+                }
+
+                gap = 0;
+                if seen.insert(address) {
+                    accounts.push(DiscoveredAccount { path, signer, nonce, balance });
+                }
+            }
+        }
+
+        Ok(accounts)
+    }
+}
+
+/// Builds an [`EthereumWallet`] with all the discovered accounts registered, the first account is
+/// the default signer.
+pub fn build_wallet(accounts: &[DiscoveredAccount]) -> Result<EthereumWallet> {
+    let (first, rest) = accounts.split_first().ok_or_eyre("no accounts discovered")?;
+    let mut wallet = EthereumWallet::new(first.signer.clone());
+    for account in rest {
+        wallet.register_signer(account.signer.clone());
+    }
+    Ok(wallet)
+}
+
+/// Derives the address at `path` of the Anvil mnemonic.
+fn derive(path: &str) -> Result<Address> {
+    Ok(MnemonicBuilder::<English>::default()
+        .phrase(ANVIL_MNEMONIC)
+        .derivation_path(path)?
+        .build()?
+        .address())
+}
+
+#[tokio::main]
+async fn main() -> Result<()> {
+    // Spin up a local Anvil node that only funds the first three accounts of its mnemonic.
+    // Ensure `anvil` is available in $PATH.
+    let anvil = Anvil::new().mnemonic(ANVIL_MNEMONIC).args(["--accounts", "3"]).try_spawn()?;
+    let provider =
+        ProviderBuilder::new().wallet(anvil.wallet().unwrap()).connect_http(anvil.endpoint_url());
+
+    // Fund an account past a gap of unused accounts, and the third Ledger Live account.
+    let bip44_7 = derive(&DerivationScheme::Bip44.path(7))?;
+    let ledger_2 = derive(&DerivationScheme::LedgerLive.path(2))?;
+    for to in [bip44_7, ledger_2] {
+        let tx = TransactionRequest::default().with_to(to).with_value(parse_ether("1")?);
+        provider.send_transaction(tx).await?.get_receipt().await?;
+    }
+
+    // Scan the BIP-44 paths, the gap of four unused accounts is below the gap limit.
+    let discovery = AccountDiscovery::new(&provider, ANVIL_MNEMONIC).with_gap_limit(5);
+    let accounts = discovery.discover().await?;
+    for account in &accounts {
+        println!(
+            "{} {} nonce {} balance {}",
+            account.path,
+            account.signer.address(),
+            account.nonce,
+            account.balance
+        );
+    }
+    let paths: Vec<_> = accounts.iter().map(|account| account.path.as_str()).collect();
+    assert_eq!(
+        paths,
+        ["m/44'/60'/0'/0/0", "m/44'/60'/0'/0/1", "m/44'/60'/0'/0/2", "m/44'/60'/0'/0/7"]
+    );
+
+    // Also scan the Ledger Live paths, whose first account is already known.
+    let accounts = discovery.with_scheme(DerivationScheme::LedgerLive).discover().await?;
+    assert_eq!(accounts.len(), 5);
+    assert_eq!(accounts[4].path, "m/44'/60'/2'/0/0");
+    println!("Discovered {} accounts including Ledger Live paths", accounts.len());
+
+    // Spend from a discovered account with a wallet holding all of them.
+    let wallet = build_wallet(&accounts)?;
+    let provider = ProviderBuilder::new().wallet(wallet).connect_http(anvil.endpoint_url());
+    let tx = TransactionRequest::default()
+        .with_from(ledger_2)
+        .with_to(bip44_7)
+        .with_value(U256::from(100));
+    let receipt = provider.send_transaction(tx).await?.get_receipt().await?;
+    assert_eq!(receipt.from, ledger_2);
+    println!("Sent transaction from {ledger_2}: {}", receipt.transaction_hash);
+
+    Ok(())
+}
@@ -0,0 +1,456 @@
+//! Example of managing a directory of encrypted JSON keystores: listing accounts without
+//! decrypting them, importing keys and mnemonics, rotating passwords and unlocking keys lazily.
+
+use std::{
+    fs,
+    io::Write,
+    path::{Path, PathBuf},
+    sync::{Arc, Mutex},
+    time::{Duration, Instant},
+};
+
+use aes::Aes128;
+use alloy::{
+    primitives::{hex, keccak256, Address, ChainId, Signature, B256},
+    signers::{
+        k256::FieldBytes,
+        local::{coins_bip39::English, LocalSigner, MnemonicBuilder, PrivateKeySigner},
+        Signer, SignerSync,
+    },
+};
+use async_trait::async_trait;
+use ctr::{
+    cipher::{KeyIvInit, StreamCipher},
+    Ctr128BE,
+};
+use eyre::{bail, ensure, eyre, OptionExt, Result};
+use hmac::Hmac;
+use rand::{thread_rng, RngCore};
+use serde::Deserialize;
+use serde_json::json;
+use sha2::Sha256;
+use tempfile::tempdir;
+use zeroize::Zeroizing;
+
+/// The key derivation function used to derive the encryption key from the password.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Kdf {
+    /// Scrypt with cost `2^log_n`, block size `r` and parallelization `p`.
+    Scrypt {
+        /// Log2 of the CPU/memory cost.
+        log_n: u8,
+        /// Block size.
+        r: u32,
+        /// Parallelization.
+        p: u32,
+    },
+    /// PBKDF2 with HMAC-SHA256 and `c` iterations.
+    Pbkdf2 {
+        /// Number of iterations.
+        c: u32,
+    },
+}
+
+impl Kdf {
+    /// The parameters geth uses for its "light" scrypt keystores.
+    pub const SCRYPT_LIGHT: Self = Self::Scrypt { log_n: 12, r: 8, p: 6 };
+
+    /// The parameters geth uses for its "standard" scrypt keystores.
+    pub const SCRYPT_STANDARD: Self = Self::Scrypt { log_n: 18, r: 8, p: 1 };
+
+    /// Derives the 32 byte key, returning it with the `kdfparams` of the keystore.
+    fn derive(
+        &self,
+        password: &[u8],
+        salt: &[u8],
+    ) -> Result<(Zeroizing<[u8; 32]>, serde_json::Value)> {
+        let mut key = Zeroizing::new([0u8; 32]);
+        let params = match *self {
+            Self::Scrypt { log_n, r, p } => {
+                let params = scrypt::Params::new(log_n, r, p)
+                    .map_err(|err| eyre!("invalid scrypt parameters: {err}"))?;
+                scrypt::scrypt(password, salt, &params, key.as_mut())
+                    .map_err(|err| eyre!("scrypt failed: {err}"))?;
+                json!({ "dklen": 32, "n": 1u32 << log_n, "r": r, "p": p, "salt": hex::encode(salt) })
+            }
+            Self::Pbkdf2 { c } => {
+                pbkdf2::pbkdf2::<Hmac<Sha256>>(password, salt, c, key.as_mut());
+                json!({ "dklen": 32, "c": c, "prf": "hmac-sha256", "salt": hex::encode(salt) })
+            }
+        };
+        Ok((key, params))
+    }
+
+    const fn name(&self) -> &'static str {
+        match self {
+            Self::Scrypt { .. } => "scrypt",
+            Self::Pbkdf2 { .. } => "pbkdf2",
+        }
+    }
+}
+
+/// The fields of a keystore file that can be read without the password.
+#[derive(Debug, Deserialize)]
+struct KeystoreHeader {
+    address: Option<String>,
+}
+
+/// An account stored in a [`KeystoreDir`].
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct KeystoreEntry {
+    /// The address of the account, as recorded in the keystore file.
+    pub address: Address,
+    /// The path of the keystore file.
+    pub path: PathBuf,
+}
+
+/// A directory of [Web3 Secret Storage](https://ethereum.org/en/developers/docs/data-structures-and-encoding/web3-secret-storage/)
+/// files, one per account, named like the keystores of geth.
+///
+/// Keystores are written with the [`Kdf`] given on import, with the `address` field of geth so
+/// that they can be listed without the password. They are decrypted with
+/// [`LocalSigner::decrypt_keystore`], which reads the KDF and its parameters from the file, so
+/// scrypt and PBKDF2 keystores written by other tools are supported as well.
+#[derive(Clone, Debug)]
+pub struct KeystoreDir {
+    path: PathBuf,
+}
+
+impl KeystoreDir {
+    /// Opens the keystore directory at `path`, creating it if needed.
+    pub fn open(path: impl AsRef<Path>) -> Result<Self> {
+        fs::create_dir_all(&path)?;
+        Ok(Self { path: path.as_ref().to_path_buf() })
+    }
+
+    /// Lists the accounts in the directory from the `address` field of each keystore, without
+    /// decrypting them.
+    pub fn list(&self) -> Result<Vec<KeystoreEntry>> {
+        let mut entries = Vec::new();
+        for file in fs::read_dir(&self.path)? {
+            let path = file?.path();
+            let is_hidden =
+                path.file_name().is_some_and(|name| name.to_string_lossy().starts_with('.'));
+            if !path.is_file() || is_hidden {
+                continue;
+            }
+
+            let Ok(header) = serde_json::from_slice::<KeystoreHeader>(&fs::read(&path)?) else {
+                continue;
+            };
+            if let Some(address) = header.address.and_then(|address| address.parse().ok()) {
+                entries.push(KeystoreEntry { address, path });
+            }
+        }
+        entries.sort_by_key(|entry| entry.address);
+        Ok(entries)
+    }
+
+    /// Returns the keystore file of `address`.
+    pub fn find(&self, address: Address) -> Result<PathBuf> {
+        self.list()?
+            .into_iter()
+            .find(|entry| entry.address == address)
+            .map(|entry| entry.path)
+            .ok_or_eyre(format!("no keystore for {address}"))
+    }
+
+    /// Encrypts a raw private key with `password` and `kdf`, and stores it in the directory.
+    pub fn import_key(&self, private_key: &[u8], password: &str, kdf: Kdf) -> Result<Address> {
+        let signer = PrivateKeySigner::from_slice(private_key)?;
+        let address = signer.address();
+        ensure!(self.find(address).is_err(), "{address} is already in the keystore");
+
+        let name = format!(
+            "UTC--{}--{}",
+            chrono::Utc::now().format("%Y-%m-%dT%H-%M-%S%.9fZ"),
+            hex::encode(address)
+        );
+        self.write_atomic(&self.path.join(name), &encrypt(&signer, password, kdf)?)?;
+        Ok(address)
+    }
+
+    /// Derives the key at `m/44'/60'/0'/0/{index}` from the mnemonic and stores it in the
+    /// directory, encrypted with `password` and `kdf`.
+    pub fn import_mnemonic(
+        &self,
+        phrase: &str,
+        index: u32,
+        password: &str,
+        kdf: Kdf,
+    ) -> Result<Address> {
+        let signer = MnemonicBuilder::<English>::default().phrase(phrase).index(index)?.build()?;
+        self.import_key(&Zeroizing::new(signer.credential().to_bytes()), password, kdf)
+    }
+
+    /// Decrypts the private key of `address`.
+    pub fn export(&self, address: Address, password: &str) -> Result<Zeroizing<FieldBytes>> {
+        Ok(Zeroizing::new(self.unlock(address, password)?.credential().to_bytes()))
+    }
+
+    /// Decrypts the key of `address` into a signer.
+    pub fn unlock(&self, address: Address, password: &str) -> Result<PrivateKeySigner> {
+        let signer = LocalSigner::decrypt_keystore(self.find(address)?, password)?;
+        ensure!(
+            signer.address() == address,
+            "keystore of {address} holds the key of another account"
+        );
+        Ok(signer)
+    }
+
+    /// Re-encrypts the key of `address` with a new password and `kdf`.
+    ///
+    /// The new keystore is written to a hidden temporary file which then replaces the old one, so
+    /// the key is never lost if the process is interrupted.
+    pub fn change_password(&self, address: Address, old: &str, new: &str, kdf: Kdf) -> Result<()> {
+        let path = self.find(address)?;
+        let signer = self.unlock(address, old)?;
+        self.write_atomic(&path, &encrypt(&signer, new, kdf)?)
+    }
+
+    /// Writes `contents` to a hidden temporary file in the directory and renames it to `path`.
+    fn write_atomic(&self, path: &Path, contents: &[u8]) -> Result<()> {
+        let Some(name) = path.file_name() else {
+            bail!("invalid keystore path {}", path.display());
+        };
+        let tmp = self.path.join(format!(".{}.tmp", name.to_string_lossy()));
+
+        let result = fs::File::create(&tmp).and_then(|mut file| {
+            file.write_all(contents)?;
+            file.sync_all()
+        });
+        if let Err(err) = result.and_then(|()| fs::rename(&tmp, path)) {
+            let _ = fs::remove_file(&tmp);
+            return Err(err.into());
+        }
+        Ok(())
+    }
+}
+
+/// Encrypts the key of the signer into a version 3 keystore, with the KDF `kdf`.
+fn encrypt(signer: &PrivateKeySigner, password: &str, kdf: Kdf) -> Result<Vec<u8>> {
+    let mut rng = thread_rng();
+    let mut salt = [0u8; 32];
+    let mut iv = [0u8; 16];
+    let mut id = [0u8; 16];
+    rng.fill_bytes(&mut salt);
+    rng.fill_bytes(&mut iv);
+    rng.fill_bytes(&mut id);
+
+    let (key, kdfparams) = kdf.derive(password.as_bytes(), &salt)?;
+
+    // Encrypt the private key with AES-128-CTR using the first half of the derived key.
+    let mut ciphertext = Zeroizing::new(signer.credential().to_bytes());
+    Ctr128BE::<Aes128>::new(key[..16].into(), &iv.into()).apply_keystream(&mut ciphertext);
+
+    // The MAC commits to the second half of the derived key and the ciphertext.
+    let mac = keccak256(Zeroizing::new([&key[16..], ciphertext.as_slice()].concat()));
+
+    // A random version 4 UUID.
+    id[6] = (id[6] & 0x0f) | 0x40;
+    id[8] = (id[8] & 0x3f) | 0x80;
+    let id = hex::encode(id);
+
+    let keystore = json!({
+        "address": hex::encode(signer.address()),
+        "crypto": {
+            "cipher": "aes-128-ctr",
+            "cipherparams": { "iv": hex::encode(iv) },
+            "ciphertext": hex::encode(ciphertext.as_slice()),
+            "kdf": kdf.name(),
+            "kdfparams": kdfparams,
+            "mac": hex::encode(mac),
+        },
+        "id": format!("{}-{}-{}-{}-{}", &id[..8], &id[8..12], &id[12..16], &id[16..20], &id[20..]),
+        "version": 3,
+    });
+    Ok(serde_json::to_vec(&keystore)?)
+}
+
+/// A key unlocked by a [`LazyKeystoreSigner`] and the time it is locked again.
+#[derive(Debug)]
+struct Unlocked {
+    signer: PrivateKeySigner,
+    expires_at: Instant,
+}
+
+/// A [`Signer`] that decrypts its key from a [`KeystoreDir`] on first use and drops it again after
+/// `timeout` without use. Dropping the key zeroizes it in memory.
+///
+/// The key is dropped by a background task, so the signer must be used within a Tokio runtime.
+#[derive(Clone, Debug)]
+pub struct LazyKeystoreSigner {
+    keystore: KeystoreDir,
+    address: Address,
+    password: Arc<Zeroizing<String>>,
+    timeout: Duration,
+    chain_id: Option<ChainId>,
+    unlocked: Arc<Mutex<Option<Unlocked>>>,
+}
+
+impl LazyKeystoreSigner {
+    /// Creates a new [`LazyKeystoreSigner`] for `address`, without decrypting the key yet.
+    pub fn new(
+        keystore: KeystoreDir,
+        address: Address,
+        password: String,
+        timeout: Duration,
+    ) -> Self {
+        Self {
+            keystore,
+            address,
+            password: Arc::new(Zeroizing::new(password)),
+            timeout,
+            chain_id: None,
+            unlocked: Arc::default(),
+        }
+    }
+
+    /// Returns whether the key is currently decrypted in memory.
+    pub fn is_unlocked(&self) -> bool {
+        self.unlocked.lock().unwrap().as_ref().is_some_and(|key| key.expires_at > Instant::now())
+    }
+
+    /// Drops the decrypted key, if any.
+    pub fn lock(&self) {
+        self.unlocked.lock().unwrap().take();
+    }
+
+    /// Signs with the decrypted key, unlocking it first if needed.
+    async fn sign_with_key(&self, hash: &B256) -> alloy::signers::Result<Signature> {
+        if let Some(signature) = self.sign_unlocked(hash) {
+            return signature;
+        }
+
+        // The KDF is slow by design, so the key is decrypted on a blocking thread, without holding
+        // the lock.
+        let (keystore, address, password) =
+            (self.keystore.clone(), self.address, self.password.clone());
+        let signer = tokio::task::spawn_blocking(move || keystore.unlock(address, &password))
+            .await
+            .map_err(alloy::signers::Error::other)?
+            .map_err(alloy::signers::Error::message)?;
+        let signature = signer.sign_hash_sync(hash)?;
+
+        let unlocked = Unlocked { signer, expires_at: Instant::now() + self.timeout };
+        if self.unlocked.lock().unwrap().replace(unlocked).is_none() {
+            self.schedule_lock();
+        }
+        Ok(signature)
+    }
+
+    /// Signs with the decrypted key, unless it is locked.
+    fn sign_unlocked(&self, hash: &B256) -> Option<alloy::signers::Result<Signature>> {
+        let mut unlocked = self.unlocked.lock().unwrap();
+        let key = unlocked.as_mut().filter(|key| key.expires_at > Instant::now())?;
+
+        // Every use extends the time the key stays unlocked.
+        key.expires_at = Instant::now() + self.timeout;
+        Some(key.signer.sign_hash_sync(hash))
+    }
+
+    /// Drops the key once it has not been used for `timeout`.
+    fn schedule_lock(&self) {
+        let unlocked = Arc::downgrade(&self.unlocked);
+        let timeout = self.timeout;
+        tokio::spawn(async move {
+            loop {
+                tokio::time::sleep(timeout).await;
+                let Some(unlocked) = unlocked.upgrade() else { return };
+                let mut unlocked = unlocked.lock().unwrap();
+                match unlocked.as_ref() {
+                    Some(key) if key.expires_at <= Instant::now() => {
+                        unlocked.take();
+                        return;
+                    }
+                    Some(_) => {}
+                    None => return,
+                }
+            }
+        });
+    }
+}
+
+#[async_trait]
+impl Signer for LazyKeystoreSigner {
+    async fn sign_hash(&self, hash: &B256) -> alloy::signers::Result<Signature> {
+        self.sign_with_key(hash).await
+    }
+
+    fn address(&self) -> Address {
+        self.address
+    }
+
+    fn chain_id(&self) -> Option<ChainId> {
+        self.chain_id
+    }
+
+    fn set_chain_id(&mut self, chain_id: Option<ChainId>) {
+        self.chain_id = chain_id;
+    }
+}
+
+#[tokio::main]
+async fn main() -> Result<()> {
+    let dir = tempdir()?;
+    let keystore = KeystoreDir::open(dir.path())?;
+
+    // Import the private key of Alice, the first default Anvil account.
+    let alice_key =
+        B256::from(hex!("ac0974bec39a17e36ba4a6b4d238ff944bacb478cbed5efcae784d7bf4f2ff80"));
+    let alice = keystore.import_key(alice_key.as_slice(), "alice", Kdf::SCRYPT_LIGHT)?;
+
+    // Import the second account of the default Anvil mnemonic as Bob, with PBKDF2.
+    let phrase = "test test test test test test test test test test test junk";
+    let bob = keystore.import_mnemonic(phrase, 1, "bob", Kdf::Pbkdf2 { c: 262_144 })?;
+
+    // List the accounts without decrypting them.
+    let entries = keystore.list()?;
+    for entry in &entries {
+        println!("{} -> {}", entry.address, entry.path.display());
+    }
+    assert_eq!(entries.len(), 2);
+    assert!(keystore.import_key(alice_key.as_slice(), "alice", Kdf::SCRYPT_LIGHT).is_err());
+
+    // The keystores record the KDF they were written with.
+    let kdf_of = |address| -> Result<String> {
+        let keystore: serde_json::Value =
+            serde_json::from_slice(&fs::read(keystore.find(address)?)?)?;
+        Ok(keystore["crypto"]["kdf"].as_str().unwrap_or_default().to_string())
+    };
+    assert_eq!((kdf_of(alice)?, kdf_of(bob)?), ("scrypt".into(), "pbkdf2".into()));
+
+    // Export Alice's key back.
+    assert_eq!(keystore.export(alice, "alice")?.as_slice(), alice_key.as_slice());
+
+    // Rotate Bob's password, which re-encrypts his key, here with scrypt.
+    let kdf = Kdf::Scrypt { log_n: 14, r: 8, p: 1 };
+    keystore.change_password(bob, "bob", "correct horse battery staple", kdf)?;
+    assert_eq!(kdf_of(bob)?, "scrypt");
+    assert!(keystore.unlock(bob, "bob").is_err());
+    let bob_signer = keystore.unlock(bob, "correct horse battery staple")?;
+    println!("Rotated the password of {}", bob_signer.address());
+    assert_eq!(keystore.list()?.len(), 2);
+
+    // Sign with a key that is only decrypted while it is in use.
+    let signer =
+        LazyKeystoreSigner::new(keystore, alice, "alice".to_string(), Duration::from_secs(1));
+    assert!(!signer.is_unlocked());
+
+    let signature = signer.sign_message(b"hello").await?;
+    assert_eq!(signature.recover_address_from_msg(b"hello")?, alice);
+    assert!(signer.is_unlocked());
+    println!("Signed with {alice}, key unlocked: {}", signer.is_unlocked());
+
+    // Once the timeout elapses the key is dropped, and unlocked again on the next signature.
+    tokio::time::sleep(Duration::from_secs(2)).await;
+    assert!(!signer.is_unlocked());
+    println!("Key unlocked after timeout: {}", signer.is_unlocked());
+
+    signer.sign_message(b"hello again").await?;
+    assert!(signer.is_unlocked());
+    signer.lock();
+    assert!(!signer.is_unlocked());
+
+    Ok(())
+}
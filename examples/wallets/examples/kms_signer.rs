@@ -0,0 +1,645 @@
+//! Example of a signer over any key management service that signs digests into DER signatures, with
+//! AWS KMS, GCP KMS and a local file-backed stand-in as backends.
+//!
+//! The local backend signs the same way the cloud services do, so the DER-to-recoverable signature
+//! conversion, the key id handling and the wallet registration can be checked without credentials.
+//! The AWS and GCP backends are only exercised if `AWS_KEY_ID` or `GOOGLE_KMS_KEY_VERSION` is set.
+
+use std::{
+    fmt, fs,
+    path::{Path, PathBuf},
+    sync::Arc,
+};
+
+use alloy::{
+    consensus::{transaction::SignerRecoverable, SignableTransaction},
+    network::{Ethereum, EthereumWallet, NetworkWallet, TransactionBuilder, TxSigner},
+    primitives::{address, hex, Address, ChainId, Signature, B256, U256},
+    rpc::types::TransactionRequest,
+    signers::{
+        k256::{
+            ecdsa::{self, signature::hazmat::PrehashSigner, SigningKey, VerifyingKey},
+            pkcs8::{DecodePublicKey, EncodePublicKey},
+        },
+        utils::public_key_to_address,
+        Signer,
+    },
+};
+use async_trait::async_trait;
+use aws_sdk_kms::{
+    primitives::Blob,
+    types::{MessageType, SigningAlgorithmSpec},
+};
+use eyre::{ensure, Result};
+use gcloud_sdk::{
+    google::cloud::kms::v1::{
+        digest, key_management_service_client::KeyManagementServiceClient, AsymmetricSignRequest,
+        Digest, GetPublicKeyRequest,
+    },
+    tonic::Request,
+    GoogleApi, GoogleAuthMiddleware,
+};
+
+/// An error of a key management service or of the checks made on its responses.
+#[derive(Debug)]
+pub enum KmsError {
+    /// The key id is not in a format the service accepts.
+    InvalidKeyId(String),
+    /// The service does not hold the key.
+    KeyNotFound(String),
+    /// The public key is not a secp256k1 key in the expected encoding.
+    InvalidPublicKey(String),
+    /// The signature is not a DER-encoded ECDSA signature.
+    InvalidSignature(ecdsa::Error),
+    /// The signature does not recover to the public key of the key.
+    SignatureRecoveryFailed,
+    /// The local key store could not be read or written.
+    Io(std::io::Error),
+    /// A request to the service failed.
+    Backend(Box<dyn std::error::Error + Send + Sync>),
+}
+
+impl fmt::Display for KmsError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::InvalidKeyId(key_id) => write!(f, "invalid key id {key_id:?}"),
+            Self::KeyNotFound(key_id) => write!(f, "key {key_id:?} not found"),
+            Self::InvalidPublicKey(message) => write!(f, "invalid public key: {message}"),
+            Self::InvalidSignature(err) => write!(f, "invalid DER signature: {err}"),
+            Self::SignatureRecoveryFailed => {
+                f.write_str("the signature does not recover to the public key of the key")
+            }
+            Self::Io(err) => write!(f, "key store error: {err}"),
+            Self::Backend(err) => write!(f, "key management service error: {err}"),
+        }
+    }
+}
+
+impl std::error::Error for KmsError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Self::InvalidSignature(err) => Some(err),
+            Self::Io(err) => Some(err),
+            Self::Backend(err) => Some(err.as_ref()),
+            _ => None,
+        }
+    }
+}
+
+impl From<std::io::Error> for KmsError {
+    fn from(err: std::io::Error) -> Self {
+        Self::Io(err)
+    }
+}
+
+/// Wraps an error of a cloud SDK.
+fn backend(err: impl std::error::Error + Send + Sync + 'static) -> KmsError {
+    KmsError::Backend(Box::new(err))
+}
+
+/// The public key of a key, with the id the service resolved the requested key id to.
+#[derive(Clone, Debug)]
+pub struct KmsPublicKey {
+    /// The canonical id of the key, e.g. the ARN of the key an AWS alias points to.
+    pub key_id: String,
+    /// The secp256k1 public key.
+    pub verifying_key: VerifyingKey,
+}
+
+/// A key management service holding secp256k1 keys that signs digests into DER signatures.
+#[async_trait]
+pub trait KmsBackend: Send + Sync {
+    /// Checks that `key_id` is in a format the service accepts, before any request is made.
+    fn check_key_id(key_id: &str) -> Result<(), KmsError>
+    where
+        Self: Sized;
+
+    /// Returns the public key of `key_id`, and the canonical id of the key it refers to.
+    async fn public_key(&self, key_id: &str) -> Result<KmsPublicKey, KmsError>;
+
+    /// Signs the 32-byte `digest` with `key_id`, returning a DER-encoded ECDSA signature.
+    ///
+    /// Like the cloud services, this does not normalize `s` and does not return the recovery id.
+    async fn sign_digest(&self, key_id: &str, digest: &B256) -> Result<Vec<u8>, KmsError>;
+}
+
+/// Decodes a DER-encoded `SubjectPublicKeyInfo`, as returned by AWS KMS.
+fn decode_public_key_der(der: &[u8]) -> Result<VerifyingKey, KmsError> {
+    VerifyingKey::from_public_key_der(der)
+        .map_err(|err| KmsError::InvalidPublicKey(err.to_string()))
+}
+
+/// Converts a DER-encoded signature of `digest` to a signature with a recovery id.
+///
+/// The signature is normalized to the lower `s`, which Ethereum requires, and the recovery id is
+/// found by trying both parities against the public key of the key.
+pub fn signature_from_der(
+    der: &[u8],
+    digest: &B256,
+    public_key: &VerifyingKey,
+) -> Result<Signature, KmsError> {
+    let signature = ecdsa::Signature::from_der(der).map_err(KmsError::InvalidSignature)?;
+    let signature = signature.normalize_s().unwrap_or(signature);
+
+    [false, true]
+        .into_iter()
+        .map(|parity| Signature::from_signature_and_parity(signature, parity))
+        .find(|candidate| {
+            candidate.recover_from_prehash(digest).is_ok_and(|key| key == *public_key)
+        })
+        .ok_or(KmsError::SignatureRecoveryFailed)
+}
+
+/// Returns whether `id` is the id of an AWS key, either a UUID or a multi-region `mrk-` id.
+fn is_aws_key_uuid(id: &str) -> bool {
+    let is_hex = |s: &str| s.chars().all(|c| c.is_ascii_hexdigit());
+    if let Some(id) = id.strip_prefix("mrk-") {
+        return id.len() == 32 && is_hex(id);
+    }
+    let groups: Vec<&str> = id.split('-').collect();
+    groups.iter().map(|group| group.len()).eq([8, 4, 4, 4, 12]) && groups.into_iter().all(is_hex)
+}
+
+/// Returns whether `name` is the name of an AWS alias, without the `alias/` prefix.
+fn is_aws_alias_name(name: &str) -> bool {
+    !name.is_empty()
+        && name.len() <= 250
+        && name.chars().all(|c| c.is_ascii_alphanumeric() || "/_-".contains(c))
+}
+
+/// An AWS KMS backend.
+///
+/// Key ids are key UUIDs, `alias/<name>` aliases, or the ARNs of either. They are resolved to the
+/// ARN of the key, so a signer keeps its key if the alias is later pointed to another key.
+#[derive(Clone, Debug)]
+pub struct AwsKmsBackend {
+    client: aws_sdk_kms::Client,
+}
+
+impl AwsKmsBackend {
+    /// Creates a new backend with an AWS KMS client.
+    pub const fn new(client: aws_sdk_kms::Client) -> Self {
+        Self { client }
+    }
+}
+
+#[async_trait]
+impl KmsBackend for AwsKmsBackend {
+    fn check_key_id(key_id: &str) -> Result<(), KmsError> {
+        let resource = match key_id.strip_prefix("arn:") {
+            // `arn:<partition>:kms:<region>:<account>:<resource>`
+            Some(arn) => match arn.splitn(5, ':').collect::<Vec<_>>()[..] {
+                [partition, "kms", region, account, resource]
+                    if !partition.is_empty()
+                        && !region.is_empty()
+                        && account.len() == 12
+                        && account.chars().all(|c| c.is_ascii_digit()) =>
+                {
+                    match resource.strip_prefix("key/") {
+                        Some(id) => id,
+                        None if resource.starts_with("alias/") => resource,
+                        None => return Err(KmsError::InvalidKeyId(key_id.to_string())),
+                    }
+                }
+                _ => return Err(KmsError::InvalidKeyId(key_id.to_string())),
+            },
+            None => key_id,
+        };
+
+        let valid = match resource.strip_prefix("alias/") {
+            Some(name) => is_aws_alias_name(name) && !name.starts_with("aws/"),
+            None => is_aws_key_uuid(resource),
+        };
+        if valid {
+            Ok(())
+        } else {
+            Err(KmsError::InvalidKeyId(key_id.to_string()))
+        }
+    }
+
+    async fn public_key(&self, key_id: &str) -> Result<KmsPublicKey, KmsError> {
+        let response = self.client.get_public_key().key_id(key_id).send().await.map_err(backend)?;
+        let der = response
+            .public_key()
+            .ok_or_else(|| KmsError::InvalidPublicKey("missing from the response".to_string()))?;
+        Ok(KmsPublicKey {
+            key_id: response.key_id().unwrap_or(key_id).to_string(),
+            verifying_key: decode_public_key_der(der.as_ref())?,
+        })
+    }
+
+    async fn sign_digest(&self, key_id: &str, digest: &B256) -> Result<Vec<u8>, KmsError> {
+        let response = self
+            .client
+            .sign()
+            .key_id(key_id)
+            .message(Blob::new(digest.as_slice()))
+            .message_type(MessageType::Digest)
+            .signing_algorithm(SigningAlgorithmSpec::EcdsaSha256)
+            .send()
+            .await
+            .map_err(backend)?;
+        Ok(response.signature.map(Blob::into_inner).unwrap_or_default())
+    }
+}
+
+/// A GCP Cloud KMS backend.
+///
+/// Key ids are the resource names of key versions, i.e.
+/// `projects/<project>/locations/<location>/keyRings/<ring>/cryptoKeys/<key>/cryptoKeyVersions/
+/// <n>`, as GCP signs with a version of a key rather than the key itself.
+#[derive(Clone)]
+pub struct GcpKmsBackend {
+    client: GoogleApi<KeyManagementServiceClient<GoogleAuthMiddleware>>,
+}
+
+impl fmt::Debug for GcpKmsBackend {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("GcpKmsBackend").finish_non_exhaustive()
+    }
+}
+
+impl GcpKmsBackend {
+    /// Creates a new backend with a Cloud KMS client.
+    pub const fn new(client: GoogleApi<KeyManagementServiceClient<GoogleAuthMiddleware>>) -> Self {
+        Self { client }
+    }
+
+    /// Returns a request for `key_id` with the metadata Cloud KMS routes requests with.
+    fn request<T>(key_id: &str, message: T) -> Result<Request<T>, KmsError> {
+        let mut request = Request::new(message);
+        let params = format!("name={key_id}")
+            .parse()
+            .map_err(|_| KmsError::InvalidKeyId(key_id.to_string()))?;
+        request.metadata_mut().insert("x-goog-request-params", params);
+        Ok(request)
+    }
+}
+
+#[async_trait]
+impl KmsBackend for GcpKmsBackend {
+    fn check_key_id(key_id: &str) -> Result<(), KmsError> {
+        const COLLECTIONS: [&str; 5] =
+            ["projects", "locations", "keyRings", "cryptoKeys", "cryptoKeyVersions"];
+
+        let segments: Vec<&str> = key_id.split('/').collect();
+        let valid = segments.len() == 2 * COLLECTIONS.len()
+            && segments
+                .chunks(2)
+                .zip(COLLECTIONS)
+                .all(|(segment, collection)| segment[0] == collection && !segment[1].is_empty())
+            && segments[9].parse::<u64>().is_ok();
+        if valid {
+            Ok(())
+        } else {
+            Err(KmsError::InvalidKeyId(key_id.to_string()))
+        }
+    }
+
+    async fn public_key(&self, key_id: &str) -> Result<KmsPublicKey, KmsError> {
+        let request = Self::request(
+            key_id,
+            GetPublicKeyRequest { name: key_id.to_string(), ..Default::default() },
+        )?;
+        let response =
+            self.client.get().get_public_key(request).await.map_err(backend)?.into_inner();
+        let verifying_key = VerifyingKey::from_public_key_pem(&response.pem)
+            .map_err(|err| KmsError::InvalidPublicKey(err.to_string()))?;
+        Ok(KmsPublicKey { key_id: key_id.to_string(), verifying_key })
+    }
+
+    async fn sign_digest(&self, key_id: &str, digest: &B256) -> Result<Vec<u8>, KmsError> {
+        let request = Self::request(
+            key_id,
+            AsymmetricSignRequest {
+                name: key_id.to_string(),
+                digest: Some(Digest { digest: Some(digest::Digest::Sha256(digest.to_vec())) }),
+                ..Default::default()
+            },
+        )?;
+        let response = self.client.get().asymmetric_sign(request).await.map_err(backend)?;
+        Ok(response.into_inner().signature)
+    }
+}
+
+/// A local stand-in for a key management service, with the keys stored in a directory.
+///
+/// Keys are created with random ids, and `alias/<name>` aliases can be pointed to them, as in AWS
+/// KMS. Public keys go through a DER `SubjectPublicKeyInfo` and signatures are DER-encoded with `s`
+/// left unnormalized half of the time, so the responses are handled exactly like the cloud ones.
+#[derive(Clone, Debug)]
+pub struct LocalKmsBackend {
+    dir: PathBuf,
+}
+
+impl LocalKmsBackend {
+    /// Opens the key store in `dir`, creating the directory if it does not exist.
+    pub fn new(dir: impl AsRef<Path>) -> Result<Self, KmsError> {
+        let dir = dir.as_ref().to_path_buf();
+        fs::create_dir_all(dir.join("keys"))?;
+        fs::create_dir_all(dir.join("aliases"))?;
+        Ok(Self { dir })
+    }
+
+    /// Creates a new key and returns its id.
+    pub fn create_key(&self) -> Result<String, KmsError> {
+        let key_id = hex::encode(rand::random::<[u8; 16]>());
+        let key = SigningKey::random(&mut rand::thread_rng());
+        fs::write(self.key_path(&key_id), hex::encode(key.to_bytes()))?;
+        Ok(key_id)
+    }
+
+    /// Points the alias `alias/<name>` to `key_id`, replacing the key it pointed to.
+    pub fn set_alias(&self, alias: &str, key_id: &str) -> Result<(), KmsError> {
+        Self::check_key_id(alias)?;
+        let name = alias
+            .strip_prefix("alias/")
+            .ok_or_else(|| KmsError::InvalidKeyId(alias.to_string()))?;
+        self.signing_key(key_id)?;
+        fs::write(self.dir.join("aliases").join(name), key_id)?;
+        Ok(())
+    }
+
+    /// Resolves `key_id` to the id of a key if it is an alias.
+    fn resolve(&self, key_id: &str) -> Result<String, KmsError> {
+        Self::check_key_id(key_id)?;
+        match key_id.strip_prefix("alias/") {
+            Some(name) => fs::read_to_string(self.dir.join("aliases").join(name))
+                .map_err(|_| KmsError::KeyNotFound(key_id.to_string())),
+            None => Ok(key_id.to_string()),
+        }
+    }
+
+    /// Returns the path of the key `key_id`.
+    fn key_path(&self, key_id: &str) -> PathBuf {
+        self.dir.join("keys").join(key_id)
+    }
+
+    /// Reads the key `key_id`, which must not be an alias.
+    fn signing_key(&self, key_id: &str) -> Result<SigningKey, KmsError> {
+        let not_found = || KmsError::KeyNotFound(key_id.to_string());
+        let encoded = fs::read_to_string(self.key_path(key_id)).map_err(|_| not_found())?;
+        let bytes = hex::decode(encoded.trim()).map_err(|_| not_found())?;
+        SigningKey::from_slice(&bytes).map_err(|_| not_found())
+    }
+}
+
+#[async_trait]
+impl KmsBackend for LocalKmsBackend {
+    fn check_key_id(key_id: &str) -> Result<(), KmsError> {
+        // Ids are file names, so anything that could escape the store is rejected.
+        let name = key_id.strip_prefix("alias/").unwrap_or(key_id);
+        let valid = !name.is_empty()
+            && name.len() <= 64
+            && name.chars().all(|c| c.is_ascii_alphanumeric() || c == '_' || c == '-');
+        if valid {
+            Ok(())
+        } else {
+            Err(KmsError::InvalidKeyId(key_id.to_string()))
+        }
+    }
+
+    async fn public_key(&self, key_id: &str) -> Result<KmsPublicKey, KmsError> {
+        let resolved = self.resolve(key_id)?;
+        let der = self
+            .signing_key(&resolved)?
+            .verifying_key()
+            .to_public_key_der()
+            .map_err(|err| KmsError::InvalidPublicKey(err.to_string()))?;
+        Ok(KmsPublicKey { key_id: resolved, verifying_key: decode_public_key_der(der.as_bytes())? })
+    }
+
+    async fn sign_digest(&self, key_id: &str, digest: &B256) -> Result<Vec<u8>, KmsError> {
+        let key = self.signing_key(&self.resolve(key_id)?)?;
+        let signature: ecdsa::Signature =
+            key.sign_prehash(digest.as_slice()).map_err(KmsError::InvalidSignature)?;
+
+        // `(r, n - s)` is as valid as `(r, s)`, and the services return either.
+        let signature = if rand::random() {
+            ecdsa::Signature::from_scalars(signature.r().to_bytes(), (-*signature.s()).to_bytes())
+                .map_err(KmsError::InvalidSignature)?
+        } else {
+            signature
+        };
+        Ok(signature.to_der().as_bytes().to_vec())
+    }
+}
+
+/// A signer over a key of a key management service.
+pub struct KmsSigner<B> {
+    backend: Arc<B>,
+    key_id: String,
+    public_key: VerifyingKey,
+    address: Address,
+    chain_id: Option<ChainId>,
+}
+
+impl<B> fmt::Debug for KmsSigner<B> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("KmsSigner")
+            .field("key_id", &self.key_id)
+            .field("address", &self.address)
+            .field("chain_id", &self.chain_id)
+            .finish()
+    }
+}
+
+impl<B: KmsBackend> KmsSigner<B> {
+    /// Creates a signer over `key_id`, fetching its public key.
+    ///
+    /// The key id is checked before any request is made, and the signer keeps the canonical id
+    /// the service resolved it to.
+    pub async fn new(
+        backend: Arc<B>,
+        key_id: &str,
+        chain_id: Option<ChainId>,
+    ) -> Result<Self, KmsError> {
+        B::check_key_id(key_id)?;
+        let KmsPublicKey { key_id, verifying_key } = backend.public_key(key_id).await?;
+        let address = public_key_to_address(&verifying_key);
+        Ok(Self { backend, key_id, public_key: verifying_key, address, chain_id })
+    }
+
+    /// Returns the canonical id of the key.
+    pub fn key_id(&self) -> &str {
+        &self.key_id
+    }
+
+    /// Returns the address of the key.
+    pub const fn address(&self) -> Address {
+        self.address
+    }
+
+    /// Signs `digest` with the key, returning a signature with a recovery id.
+    async fn sign_digest(&self, digest: &B256) -> Result<Signature, KmsError> {
+        let der = self.backend.sign_digest(&self.key_id, digest).await?;
+        signature_from_der(&der, digest, &self.public_key)
+    }
+}
+
+#[async_trait]
+impl<B: KmsBackend> Signer for KmsSigner<B> {
+    async fn sign_hash(&self, hash: &B256) -> alloy::signers::Result<Signature> {
+        self.sign_digest(hash).await.map_err(alloy::signers::Error::other)
+    }
+
+    fn address(&self) -> Address {
+        self.address
+    }
+
+    fn chain_id(&self) -> Option<ChainId> {
+        self.chain_id
+    }
+
+    fn set_chain_id(&mut self, chain_id: Option<ChainId>) {
+        self.chain_id = chain_id;
+    }
+}
+
+#[async_trait]
+impl<B: KmsBackend> TxSigner<Signature> for KmsSigner<B> {
+    fn address(&self) -> Address {
+        self.address
+    }
+
+    async fn sign_transaction(
+        &self,
+        tx: &mut dyn SignableTransaction<Signature>,
+    ) -> alloy::signers::Result<Signature> {
+        // Transactions without a chain id get the one of the signer, as with the other signers.
+        if let Some(chain_id) = self.chain_id {
+            if !tx.set_chain_id_checked(chain_id) {
+                return Err(alloy::signers::Error::TransactionChainIdMismatch {
+                    signer: chain_id,
+                    tx: tx.chain_id().unwrap_or_default(),
+                });
+            }
+        }
+        self.sign_hash(&tx.signature_hash()).await
+    }
+}
+
+/// Signs a transfer from `from` with `wallet`, and checks the recovered sender.
+async fn sign_transfer(wallet: &EthereumWallet, from: Address, chain_id: ChainId) -> Result<()> {
+    let tx = TransactionRequest::default()
+        .with_from(from)
+        .with_to(address!("d8dA6BF26964aF9D7eEd9e03E53415D37aA96045"))
+        .with_value(U256::from(100))
+        .with_nonce(0)
+        .with_chain_id(chain_id)
+        .with_gas_limit(21_000)
+        .with_max_fee_per_gas(20_000_000_000)
+        .with_max_priority_fee_per_gas(1_000_000_000);
+    let envelope = NetworkWallet::<Ethereum>::sign_request(wallet, tx).await?;
+    ensure!(envelope.recover_signer()? == from, "the transaction does not recover to {from}");
+    Ok(())
+}
+
+/// Signs messages and a transaction with a key of `backend`.
+async fn check_backend<B: KmsBackend + 'static>(backend: Arc<B>, key_id: &str) -> Result<()> {
+    let signer = KmsSigner::new(backend, key_id, Some(1)).await?;
+    println!("{key_id} resolves to {} with address {}", signer.key_id(), signer.address());
+
+    let message = "Hello, world!";
+    let signature = signer.sign_message(message.as_bytes()).await?;
+    assert_eq!(signature.recover_address_from_msg(message)?, signer.address());
+
+    let address = signer.address();
+    sign_transfer(&EthereumWallet::new(signer), address, 1).await
+}
+
+#[tokio::main]
+async fn main() -> Result<()> {
+    let store = tempfile::tempdir()?;
+    let local = Arc::new(LocalKmsBackend::new(store.path())?);
+
+    // Key ids are checked before any request is made.
+    assert!(AwsKmsBackend::check_key_id("1234abcd-12ab-34cd-56ef-1234567890ab").is_ok());
+    assert!(AwsKmsBackend::check_key_id("mrk-1234abcd12ab34cd56ef1234567890ab").is_ok());
+    assert!(AwsKmsBackend::check_key_id("alias/treasury").is_ok());
+    assert!(AwsKmsBackend::check_key_id(
+        "arn:aws:kms:us-east-2:111122223333:key/1234abcd-12ab-34cd-56ef-1234567890ab"
+    )
+    .is_ok());
+    assert!(
+        AwsKmsBackend::check_key_id("arn:aws:kms:us-east-2:111122223333:alias/treasury").is_ok()
+    );
+    assert!(AwsKmsBackend::check_key_id("alias/aws/ebs").is_err());
+    assert!(AwsKmsBackend::check_key_id("1234abcd").is_err());
+    assert!(AwsKmsBackend::check_key_id("arn:aws:s3:us-east-2:111122223333:key/x").is_err());
+    assert!(GcpKmsBackend::check_key_id(
+        "projects/p/locations/global/keyRings/r/cryptoKeys/k/cryptoKeyVersions/1"
+    )
+    .is_ok());
+    assert!(
+        GcpKmsBackend::check_key_id("projects/p/locations/global/keyRings/r/cryptoKeys/k").is_err()
+    );
+    assert!(LocalKmsBackend::check_key_id("../keys/escape").is_err());
+    assert!(KmsSigner::new(local.clone(), "../escape", Some(1)).await.is_err());
+    assert!(KmsSigner::new(local.clone(), "alias/missing", Some(1)).await.is_err());
+
+    // Signers over an alias are pinned to the key the alias pointed to when they were created.
+    let treasury_key = local.create_key()?;
+    let operations_key = local.create_key()?;
+    local.set_alias("alias/treasury", &treasury_key)?;
+    let treasury = KmsSigner::new(local.clone(), "alias/treasury", Some(1)).await?;
+    assert_eq!(treasury.key_id(), treasury_key);
+    local.set_alias("alias/treasury", &operations_key)?;
+    let operations = KmsSigner::new(local.clone(), "alias/treasury", Some(1)).await?;
+    assert_eq!(operations.key_id(), operations_key);
+    assert_ne!(treasury.address(), operations.address());
+    println!("Treasury: {} ({})", treasury.address(), treasury.key_id());
+    println!("Operations: {} ({})", operations.address(), operations.key_id());
+
+    // Half of the DER signatures have the higher `s`, and all are normalized and recoverable.
+    for i in 0..32u8 {
+        let message = [i; 32];
+        let signature = treasury.sign_message(&message).await?;
+        assert!(
+            signature.normalize_s().is_none(),
+            "the signature was not normalized to the lower `s`"
+        );
+        assert_eq!(signature.recover_address_from_msg(message)?, treasury.address());
+    }
+
+    // A signature of another key or a malformed one is rejected rather than misattributed.
+    let digest = B256::repeat_byte(0x42);
+    let der = local.sign_digest(&operations_key, &digest).await?;
+    assert!(matches!(
+        signature_from_der(&der, &digest, &treasury.public_key),
+        Err(KmsError::SignatureRecoveryFailed)
+    ));
+    assert!(matches!(
+        signature_from_der(&der[1..], &digest, &operations.public_key),
+        Err(KmsError::InvalidSignature(_))
+    ));
+
+    // Both signers are registered in a wallet, which picks the signer by the sender.
+    let (treasury_address, operations_address) = (treasury.address(), operations.address());
+    let mut wallet = EthereumWallet::new(treasury);
+    wallet.register_signer(operations);
+    sign_transfer(&wallet, treasury_address, 1).await?;
+    sign_transfer(&wallet, operations_address, 1).await?;
+
+    // A transaction for another chain than the one of the signer is refused.
+    assert!(sign_transfer(&wallet, treasury_address, 10).await.is_err());
+    println!("Signed transactions from both keys with the wallet");
+
+    // The same checks against the cloud services, if configured.
+    if let Ok(key_id) = std::env::var("AWS_KEY_ID") {
+        let config = aws_config::load_defaults(aws_config::BehaviorVersion::latest()).await;
+        let backend = AwsKmsBackend::new(aws_sdk_kms::Client::new(&config));
+        check_backend(Arc::new(backend), &key_id).await?;
+    }
+    if let Ok(key_id) = std::env::var("GOOGLE_KMS_KEY_VERSION") {
+        let client = GoogleApi::from_function(
+            KeyManagementServiceClient::new,
+            "https://cloudkms.googleapis.com",
+            None,
+        )
+        .await?;
+        check_backend(Arc::new(GcpKmsBackend::new(client)), &key_id).await?;
+    }
+
+    Ok(())
+}
@@ -0,0 +1,225 @@
+//! Example of an M-of-N approval flow for a Safe-style transaction: several heterogeneous signers
+//! sign the same EIP-712 hash, and the signatures are packed and verified against a threshold.
+
+use std::{collections::BTreeMap, path::PathBuf};
+
+use alloy::{
+    primitives::{address, Address, Bytes, Signature, B256, U256},
+    signers::{
+        aws::AwsSigner,
+        local::{coins_bip39::English, LocalSigner, MnemonicBuilder, PrivateKeySigner},
+        Signer,
+    },
+    sol,
+    sol_types::{eip712_domain, Eip712Domain, SolStruct},
+};
+use aws_config::BehaviorVersion;
+use eyre::{ensure, Result};
+
+sol! {
+    #[allow(missing_docs)]
+    #[derive(Debug)]
+    struct SafeTx {
+        address to;
+        uint256 value;
+        bytes data;
+        uint8 operation;
+        uint256 safeTxGas;
+        uint256 baseGas;
+        uint256 gasPrice;
+        address gasToken;
+        address refundReceiver;
+        uint256 nonce;
+    }
+}
+
+/// The length of a packed `r || s || v` signature.
+const SIGNATURE_LEN: usize = 65;
+
+/// Collects the signatures of the owners of a multisig over one EIP-712 hash.
+#[derive(Debug)]
+pub struct MultisigApproval {
+    hash: B256,
+    owners: Vec<Address>,
+    threshold: usize,
+    signatures: BTreeMap<Address, Signature>,
+}
+
+impl MultisigApproval {
+    /// Creates a new approval of `message` in `domain` by `threshold` of the `owners`.
+    pub fn new<T: SolStruct>(
+        message: &T,
+        domain: &Eip712Domain,
+        owners: Vec<Address>,
+        threshold: usize,
+    ) -> Result<Self> {
+        ensure!(
+            threshold > 0 && threshold <= owners.len(),
+            "threshold {threshold} is invalid for {} owners",
+            owners.len()
+        );
+        let hash = message.eip712_signing_hash(domain);
+        Ok(Self { hash, owners, threshold, signatures: BTreeMap::new() })
+    }
+
+    /// Returns the EIP-712 signing hash the owners approve.
+    pub const fn hash(&self) -> B256 {
+        self.hash
+    }
+
+    /// Asks `signer` to sign the hash and records its signature.
+    ///
+    /// Any [`Signer`] that can sign a raw hash works, e.g. local, keystore, AWS or GCP KMS signers.
+    pub async fn collect(&mut self, signer: &(dyn Signer + Send + Sync)) -> Result<Address> {
+        let signature = signer.sign_hash(&self.hash).await?;
+        // The signature is only recorded if it was made by the key of the signer.
+        let recovered = signature.recover_address_from_prehash(&self.hash)?;
+        ensure!(recovered == signer.address(), "{} signed as {recovered}", signer.address());
+        self.add_signature(signature)
+    }
+
+    /// Records a signature collected out of band and returns the owner that made it.
+    pub fn add_signature(&mut self, signature: Signature) -> Result<Address> {
+        let owner = signature.recover_address_from_prehash(&self.hash)?;
+        ensure!(self.owners.contains(&owner), "{owner} is not an owner");
+        self.signatures.insert(owner, signature);
+        Ok(owner)
+    }
+
+    /// Returns whether enough owners have signed.
+    pub fn is_approved(&self) -> bool {
+        self.signatures.len() >= self.threshold
+    }
+
+    /// Packs the signatures as `r || s || v`, sorted by ascending owner address, as expected by
+    /// `checkSignatures` of Safe contracts.
+    pub fn encode(&self) -> Result<Bytes> {
+        ensure!(
+            self.is_approved(),
+            "{} of {} required signatures collected",
+            self.signatures.len(),
+            self.threshold
+        );
+        // The map is ordered by owner address.
+        Ok(self.signatures.values().flat_map(|signature| signature.as_bytes()).collect())
+    }
+}
+
+/// Verifies packed signatures over `hash` off-chain, returning the owners that signed.
+///
+/// Like the contract, this requires distinct owners in ascending order and at least `threshold`
+/// signatures.
+pub fn verify_threshold(
+    hash: &B256,
+    signatures: &[u8],
+    owners: &[Address],
+    threshold: usize,
+) -> Result<Vec<Address>> {
+    ensure!(
+        threshold > 0 && threshold <= owners.len(),
+        "threshold {threshold} is invalid for {} owners",
+        owners.len()
+    );
+    ensure!(signatures.len() % SIGNATURE_LEN == 0, "signatures are not a multiple of 65 bytes");
+    ensure!(signatures.len() / SIGNATURE_LEN >= threshold, "not enough signatures");
+
+    let mut signers: Vec<Address> = Vec::with_capacity(signatures.len() / SIGNATURE_LEN);
+    for chunk in signatures.chunks_exact(SIGNATURE_LEN) {
+        let signer = Signature::from_raw(chunk)?.recover_address_from_prehash(hash)?;
+        ensure!(owners.contains(&signer), "{signer} is not an owner");
+        if let Some(last) = signers.last() {
+            ensure!(signer > *last, "signatures are not sorted by ascending owner address");
+        }
+        signers.push(signer);
+    }
+
+    Ok(signers)
+}
+
+#[tokio::main]
+async fn main() -> Result<()> {
+    // A local key.
+    let local = PrivateKeySigner::random();
+
+    // Alice's key, decrypted from her keystore file.
+    let keystore_file_path =
+        PathBuf::from(std::env::var("CARGO_MANIFEST_DIR")?).join("examples/keystore/alice.json");
+    let keystore = LocalSigner::decrypt_keystore(keystore_file_path, "test")?;
+
+    // A key derived from a mnemonic.
+    let mnemonic = MnemonicBuilder::<English>::default()
+        .phrase("test test test test test test test test test test test junk")
+        .index(1)?
+        .build()?;
+
+    let mut signers: Vec<Box<dyn Signer + Send + Sync>> =
+        vec![Box::new(local), Box::new(keystore), Box::new(mnemonic)];
+
+    // Optionally, a key held in AWS KMS.
+    if let Ok(key_id) = std::env::var("AWS_KEY_ID") {
+        let config = aws_config::load_defaults(BehaviorVersion::latest()).await;
+        let client = aws_sdk_kms::Client::new(&config);
+        signers.push(Box::new(AwsSigner::new(client, key_id, Some(1)).await?));
+    }
+
+    // The signers plus one owner that does not sign, with a threshold of all the signers.
+    let mut owners: Vec<Address> = signers.iter().map(|signer| signer.address()).collect();
+    owners.push(PrivateKeySigner::random().address());
+    let threshold = signers.len();
+    println!("{threshold}-of-{} multisig", owners.len());
+
+    let safe = address!("000000000000000000000000000000000000dEaD");
+    let domain = eip712_domain! {
+        chain_id: 1,
+        verifying_contract: safe,
+    };
+    let tx = SafeTx {
+        to: address!("d8dA6BF26964aF9D7eEd9e03E53415D37aA96045"),
+        value: U256::from(100),
+        data: Bytes::new(),
+        operation: 0,
+        safeTxGas: U256::ZERO,
+        baseGas: U256::ZERO,
+        gasPrice: U256::ZERO,
+        gasToken: Address::ZERO,
+        refundReceiver: Address::ZERO,
+        nonce: U256::ZERO,
+    };
+
+    let mut approval = MultisigApproval::new(&tx, &domain, owners.clone(), threshold)?;
+    println!("Safe transaction hash: {}", approval.hash());
+
+    // Collect the signatures one by one, the transaction is not approved before the last one.
+    for signer in &signers {
+        assert!(!approval.is_approved());
+        assert!(approval.encode().is_err());
+        let owner = approval.collect(signer.as_ref()).await?;
+        println!("Signed by {owner}");
+    }
+    assert!(approval.is_approved());
+
+    // Pack the signatures and verify them as the contract would.
+    let packed = approval.encode()?;
+    println!("Packed signatures: {packed}");
+    let signed_by = verify_threshold(&approval.hash(), &packed, &owners, threshold)?;
+    assert_eq!(signed_by.len(), threshold);
+
+    // Signatures in the wrong order, or below the threshold, are rejected.
+    let mut reversed: Vec<u8> = Vec::with_capacity(packed.len());
+    for chunk in packed.chunks_exact(SIGNATURE_LEN).rev() {
+        reversed.extend_from_slice(chunk);
+    }
+    assert!(verify_threshold(&approval.hash(), &reversed, &owners, threshold).is_err());
+    assert!(
+        verify_threshold(&approval.hash(), &packed[SIGNATURE_LEN..], &owners, threshold).is_err()
+    );
+
+    // A threshold of zero is rejected, as it would accept no signatures at all.
+    assert!(verify_threshold(&approval.hash(), &[], &owners, 0).is_err());
+
+    // A signature from someone who is not an owner is rejected.
+    let outsider = PrivateKeySigner::random().sign_hash(&approval.hash()).await?;
+    assert!(approval.add_signature(outsider).is_err());
+
+    Ok(())
+}
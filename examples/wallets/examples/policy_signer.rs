@@ -0,0 +1,383 @@
+//! Example of wrapping a signer with a policy that is checked before every transaction is signed:
+//! a destination allowlist, a maximum value, daily ETH and ERC-20 spend limits and a fee cap.
+
+use std::{
+    collections::{HashMap, HashSet},
+    fmt,
+    path::Path,
+    sync::Arc,
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+use alloy::{
+    consensus::SignableTransaction,
+    network::{Ethereum, EthereumWallet, TransactionBuilder, TransactionBuilderError, TxSigner},
+    node_bindings::Anvil,
+    primitives::{utils::parse_ether, Address, Signature, U256},
+    providers::{Provider, ProviderBuilder},
+    rpc::types::TransactionRequest,
+    signers::local::PrivateKeySigner,
+    sol,
+    sol_types::SolInterface,
+    transports::RpcError,
+};
+use async_trait::async_trait;
+use eyre::Result;
+use serde::Deserialize;
+use serde_json::json;
+use tempfile::tempdir;
+use tokio::sync::Mutex;
+
+sol! {
+    #[allow(missing_docs)]
+    interface IERC20 {
+        function transfer(address to, uint256 amount) external returns (bool);
+        function approve(address spender, uint256 amount) external returns (bool);
+    }
+}
+
+// Codegen from artifact.
+sol!(
+    #[allow(missing_docs)]
+    #[sol(rpc)]
+    ERC20Example,
+    "../transactions/examples/artifacts/ERC20Example.json"
+);
+
+/// The rules a transaction must satisfy to be signed. Limits that are not set are not enforced.
+#[derive(Clone, Debug, Default, Deserialize)]
+#[serde(rename_all = "camelCase", deny_unknown_fields)]
+pub struct Policy {
+    /// The addresses transactions may be sent to, and ERC-20 tokens transferred or approved to.
+    pub allowed_destinations: Option<HashSet<Address>>,
+    /// The maximum ETH value of a single transaction, in wei.
+    pub max_value_per_tx: Option<U256>,
+    /// The maximum ETH value sent per UTC day, in wei.
+    pub daily_eth_limit: Option<U256>,
+    /// The maximum amount of each ERC-20 token transferred or approved per UTC day.
+    #[serde(default)]
+    pub daily_token_limits: HashMap<Address, U256>,
+    /// The maximum fee per gas of a transaction, in wei.
+    pub max_fee_per_gas: Option<u128>,
+}
+
+impl Policy {
+    /// Loads the policy from a JSON config file.
+    pub fn load(path: impl AsRef<Path>) -> Result<Self> {
+        Ok(serde_json::from_slice(&std::fs::read(path)?)?)
+    }
+
+    fn check_destination(&self, to: Address) -> Result<(), PolicyViolation> {
+        match &self.allowed_destinations {
+            Some(allowed) if !allowed.contains(&to) => {
+                Err(PolicyViolation::DestinationNotAllowed(to))
+            }
+            _ => Ok(()),
+        }
+    }
+}
+
+/// A rule of the [`Policy`] that a transaction breaks.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum PolicyViolation {
+    /// The transaction deploys a contract while destinations are restricted.
+    ContractCreation,
+    /// The transaction, or the token transfer it makes, goes to an address not on the allowlist.
+    DestinationNotAllowed(Address),
+    /// The transaction sends more ETH than allowed per transaction.
+    ValueTooHigh {
+        /// The value of the transaction.
+        value: U256,
+        /// The maximum value per transaction.
+        max: U256,
+    },
+    /// The transaction would exceed the daily ETH limit.
+    DailyEthLimitExceeded {
+        /// The value already sent today.
+        spent: U256,
+        /// The value of the transaction.
+        value: U256,
+        /// The daily limit.
+        limit: U256,
+    },
+    /// The transaction would exceed the daily limit of an ERC-20 token.
+    DailyTokenLimitExceeded {
+        /// The token contract.
+        token: Address,
+        /// The amount already transferred or approved today.
+        spent: U256,
+        /// The amount of the transaction.
+        amount: U256,
+        /// The daily limit.
+        limit: U256,
+    },
+    /// The fee per gas of the transaction is above the cap.
+    FeeTooHigh {
+        /// The maximum fee per gas of the transaction.
+        max_fee_per_gas: u128,
+        /// The fee cap of the policy.
+        cap: u128,
+    },
+}
+
+impl fmt::Display for PolicyViolation {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::ContractCreation => f.write_str("contract creation is not allowed"),
+            Self::DestinationNotAllowed(to) => write!(f, "destination {to} is not allowed"),
+            Self::ValueTooHigh { value, max } => {
+                write!(f, "value {value} exceeds the maximum of {max} per transaction")
+            }
+            Self::DailyEthLimitExceeded { spent, value, limit } => {
+                write!(f, "daily limit of {limit} exceeded: {spent} spent, {value} requested")
+            }
+            Self::DailyTokenLimitExceeded { token, spent, amount, limit } => write!(
+                f,
+                "daily limit of {limit} for {token} exceeded: {spent} spent, {amount} requested"
+            ),
+            Self::FeeTooHigh { max_fee_per_gas, cap } => {
+                write!(f, "max fee per gas {max_fee_per_gas} exceeds the cap of {cap}")
+            }
+        }
+    }
+}
+
+impl std::error::Error for PolicyViolation {}
+
+/// Extracts the [`PolicyViolation`] that made the wallet of a provider refuse to sign.
+pub fn policy_violation<E>(err: &RpcError<E>) -> Option<&PolicyViolation> {
+    let RpcError::LocalUsageError(err) = err else { return None };
+    match err.downcast_ref::<TransactionBuilderError<Ethereum>>()? {
+        TransactionBuilderError::Signer(alloy::signers::Error::Other(err)) => err.downcast_ref(),
+        _ => None,
+    }
+}
+
+/// The ETH and ERC-20 amounts signed during the current UTC day.
+#[derive(Debug, Default)]
+struct SpendLedger {
+    day: u64,
+    eth: U256,
+    tokens: HashMap<Address, U256>,
+}
+
+/// The amounts a transaction adds to the [`SpendLedger`] once it is signed.
+#[derive(Debug)]
+struct Spend {
+    eth: U256,
+    token: Option<(Address, U256)>,
+}
+
+/// A [`TxSigner`] that checks every transaction against a [`Policy`] before the inner signer
+/// signs it.
+///
+/// Spend limits count every signed transaction, whether or not it is eventually included.
+#[derive(Clone, Debug)]
+pub struct PolicySigner<S> {
+    inner: S,
+    policy: Policy,
+    ledger: Arc<Mutex<SpendLedger>>,
+}
+
+impl<S> PolicySigner<S> {
+    /// Creates a new [`PolicySigner`] enforcing `policy` on `inner`.
+    pub fn new(inner: S, policy: Policy) -> Self {
+        Self { inner, policy, ledger: Arc::default() }
+    }
+
+    /// Checks the transaction against the policy and returns what it spends.
+    fn check(
+        &self,
+        ledger: &SpendLedger,
+        tx: &dyn SignableTransaction<Signature>,
+    ) -> Result<Spend, PolicyViolation> {
+        let policy = &self.policy;
+
+        match tx.to() {
+            Some(to) => policy.check_destination(to)?,
+            None if policy.allowed_destinations.is_some() => {
+                return Err(PolicyViolation::ContractCreation)
+            }
+            None => {}
+        }
+
+        let max_fee_per_gas = tx.max_fee_per_gas();
+        if let Some(cap) = policy.max_fee_per_gas.filter(|cap| max_fee_per_gas > *cap) {
+            return Err(PolicyViolation::FeeTooHigh { max_fee_per_gas, cap });
+        }
+
+        let value = tx.value();
+        if let Some(max) = policy.max_value_per_tx.filter(|max| value > *max) {
+            return Err(PolicyViolation::ValueTooHigh { value, max });
+        }
+        if let Some(limit) = policy.daily_eth_limit {
+            if ledger.eth.saturating_add(value) > limit {
+                return Err(PolicyViolation::DailyEthLimitExceeded {
+                    spent: ledger.eth,
+                    value,
+                    limit,
+                });
+            }
+        }
+
+        // An approval counts as spending the approved amount, as the spender can transfer it.
+        let token_call = match (tx.to(), IERC20::IERC20Calls::abi_decode(tx.input())) {
+            (Some(token), Ok(IERC20::IERC20Calls::transfer(call))) => {
+                Some((token, call.to, call.amount))
+            }
+            (Some(token), Ok(IERC20::IERC20Calls::approve(call))) => {
+                Some((token, call.spender, call.amount))
+            }
+            _ => None,
+        };
+
+        let token_spend = if let Some((token, recipient, amount)) = token_call {
+            policy.check_destination(recipient)?;
+            if let Some(&limit) = policy.daily_token_limits.get(&token) {
+                let spent = ledger.tokens.get(&token).copied().unwrap_or_default();
+                if spent.saturating_add(amount) > limit {
+                    return Err(PolicyViolation::DailyTokenLimitExceeded {
+                        token,
+                        spent,
+                        amount,
+                        limit,
+                    });
+                }
+            }
+            Some((token, amount))
+        } else {
+            None
+        };
+
+        Ok(Spend { eth: value, token: token_spend })
+    }
+}
+
+#[async_trait]
+impl<S> TxSigner<Signature> for PolicySigner<S>
+where
+    S: TxSigner<Signature> + Send + Sync,
+{
+    fn address(&self) -> Address {
+        self.inner.address()
+    }
+
+    async fn sign_transaction(
+        &self,
+        tx: &mut dyn SignableTransaction<Signature>,
+    ) -> alloy::signers::Result<Signature> {
+        // Hold the ledger while signing so concurrent transactions cannot both fit in the limits.
+        let mut ledger = self.ledger.lock().await;
+
+        let day =
+            SystemTime::now().duration_since(UNIX_EPOCH).unwrap_or_default().as_secs() / 86_400;
+        if ledger.day != day {
+            *ledger = SpendLedger { day, ..Default::default() };
+        }
+
+        let spend = self.check(&ledger, tx).map_err(alloy::signers::Error::other)?;
+        let signature = self.inner.sign_transaction(tx).await?;
+
+        ledger.eth += spend.eth;
+        if let Some((token, amount)) = spend.token {
+            *ledger.tokens.entry(token).or_default() += amount;
+        }
+
+        Ok(signature)
+    }
+}
+
+#[tokio::main]
+async fn main() -> Result<()> {
+    // Spin up a local Anvil node.
+    // Ensure `anvil` is available in $PATH.
+    let anvil = Anvil::new().try_spawn()?;
+
+    // Create three users, Alice, Bob and Charlie.
+    let alice: PrivateKeySigner = anvil.keys()[0].clone().into();
+    let bob = PrivateKeySigner::random().address();
+    let charlie = PrivateKeySigner::random().address();
+
+    // Deploy a token as Alice, outside of the policy which forbids contract creation.
+    let provider = ProviderBuilder::new().wallet(alice.clone()).connect_http(anvil.endpoint_url());
+    let token = *ERC20Example::deploy(&provider).await?.address();
+
+    // Write the policy of Alice's signer to a config file and load it back.
+    let dir = tempdir()?;
+    let config = dir.path().join("policy.json");
+    std::fs::write(
+        &config,
+        serde_json::to_vec_pretty(&json!({
+            "allowedDestinations": [bob, token],
+            "maxValuePerTx": parse_ether("1")?,
+            "dailyEthLimit": parse_ether("1.5")?,
+            "dailyTokenLimits": { token.to_string(): "1000" },
+            "maxFeePerGas": 100_000_000_000u128,
+        }))?,
+    )?;
+    let policy = Policy::load(&config)?;
+    println!("Loaded policy: {policy:?}");
+
+    // Create a provider whose wallet only signs transactions that satisfy the policy.
+    let signer = PolicySigner::new(alice, policy);
+    let provider = ProviderBuilder::new()
+        .wallet(EthereumWallet::new(signer))
+        .connect_http(anvil.endpoint_url());
+    let erc20 = ERC20Example::new(token, &provider);
+
+    // Sends a transaction and returns the policy violation, if the signer refused to sign it.
+    let send = async |tx: TransactionRequest| -> Result<Option<PolicyViolation>> {
+        match provider.send_transaction(tx).await {
+            Ok(pending_tx) => {
+                pending_tx.get_receipt().await?;
+                Ok(None)
+            }
+            Err(err) => Ok(Some(policy_violation(&err).cloned().ok_or(err)?)),
+        }
+    };
+
+    // Within the limits.
+    let tx = TransactionRequest::default().with_to(bob).with_value(parse_ether("1")?);
+    assert_eq!(send(tx).await?, None);
+
+    // Above the value per transaction.
+    let tx = TransactionRequest::default().with_to(bob).with_value(parse_ether("2")?);
+    let violation = send(tx).await?;
+    println!("Rejected: {}", violation.clone().unwrap());
+    assert!(matches!(violation, Some(PolicyViolation::ValueTooHigh { .. })));
+
+    // Above the daily limit, after the first transfer.
+    let tx = TransactionRequest::default().with_to(bob).with_value(parse_ether("1")?);
+    let violation = send(tx).await?;
+    println!("Rejected: {}", violation.clone().unwrap());
+    assert!(matches!(violation, Some(PolicyViolation::DailyEthLimitExceeded { .. })));
+
+    // Not on the allowlist.
+    let tx = TransactionRequest::default().with_to(charlie).with_value(U256::from(1));
+    assert_eq!(send(tx).await?, Some(PolicyViolation::DestinationNotAllowed(charlie)));
+
+    // Above the fee cap.
+    let tx = TransactionRequest::default()
+        .with_to(bob)
+        .with_max_fee_per_gas(200_000_000_000)
+        .with_max_priority_fee_per_gas(1_000_000_000);
+    let violation = send(tx).await?;
+    println!("Rejected: {}", violation.clone().unwrap());
+    assert!(matches!(violation, Some(PolicyViolation::FeeTooHigh { .. })));
+
+    // Token transfers and approvals share the daily token limit.
+    let tx = erc20.transfer(bob, U256::from(600)).into_transaction_request();
+    assert_eq!(send(tx).await?, None);
+    assert_eq!(erc20.balanceOf(bob).call().await?, U256::from(600));
+
+    let tx = erc20.approve(bob, U256::from(600)).into_transaction_request();
+    let violation = send(tx).await?;
+    println!("Rejected: {}", violation.clone().unwrap());
+    assert!(matches!(violation, Some(PolicyViolation::DailyTokenLimitExceeded { .. })));
+
+    // The token recipient must be on the allowlist too.
+    let tx = erc20.transfer(charlie, U256::from(1)).into_transaction_request();
+    assert_eq!(send(tx).await?, Some(PolicyViolation::DestinationNotAllowed(charlie)));
+
+    Ok(())
+}
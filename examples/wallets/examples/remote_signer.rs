@@ -0,0 +1,449 @@
+//! Example of a signer that delegates signing to a remote, Web3Signer-compatible signing service
+//! over HTTP, with the public keys cached and an allowlist of addresses enforced locally.
+
+use std::{
+    collections::{HashMap, HashSet},
+    fmt,
+    sync::{
+        atomic::{AtomicBool, AtomicUsize, Ordering},
+        Arc,
+    },
+};
+
+use alloy::{
+    consensus::{transaction::SignerRecoverable, SignableTransaction, TxEnvelope},
+    eips::eip2718::{Decodable2718, Encodable2718},
+    network::{Ethereum, EthereumWallet, NetworkWallet, TransactionBuilder, TxSigner},
+    primitives::{eip191_hash_message, hex, Address, Bytes, ChainId, Signature, B256, U256},
+    rpc::{
+        client::RpcClient,
+        types::{TransactionInput, TransactionRequest},
+    },
+    signers::{
+        k256::ecdsa::VerifyingKey, local::PrivateKeySigner, utils::public_key_to_address, Signer,
+        UnsupportedSignerOperation,
+    },
+    transports::TransportError,
+};
+use async_trait::async_trait;
+use axum::{extract::State, routing, Json, Router};
+use eyre::Result;
+use serde_json::{json, Value};
+use tokio::{net::TcpListener, sync::RwLock};
+
+/// The path of the `Web3Signer` endpoint listing the public keys of the service.
+const PUBLIC_KEYS_PATH: &str = "api/v1/eth1/publicKeys";
+
+/// An error of the remote signing service or of the checks made on its responses.
+#[derive(Debug)]
+pub enum RemoteSignerError {
+    /// The address is not in the local allowlist.
+    NotAllowed(Address),
+    /// The service does not hold a key for the address.
+    UnknownKey(Address),
+    /// The service returned a signature that does not verify against the cached public key.
+    InvalidSignature(Address),
+    /// The service returned a malformed response.
+    InvalidResponse(String),
+    /// The public keys could not be fetched.
+    Http(reqwest::Error),
+    /// A JSON-RPC request failed.
+    Rpc(TransportError),
+}
+
+impl fmt::Display for RemoteSignerError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::NotAllowed(address) => write!(f, "{address} is not in the allowlist"),
+            Self::UnknownKey(address) => write!(f, "the signing service has no key for {address}"),
+            Self::InvalidSignature(address) => {
+                write!(f, "the signing service returned an invalid signature for {address}")
+            }
+            Self::InvalidResponse(message) => write!(f, "invalid response: {message}"),
+            Self::Http(err) => write!(f, "failed to fetch the public keys: {err}"),
+            Self::Rpc(err) => write!(f, "signing request failed: {err}"),
+        }
+    }
+}
+
+impl std::error::Error for RemoteSignerError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Self::Http(err) => Some(err),
+            Self::Rpc(err) => Some(err),
+            _ => None,
+        }
+    }
+}
+
+/// A client of a signing service that serves the public keys it holds, and signs with
+/// `eth_sign` and `eth_signTransaction` over JSON-RPC.
+#[derive(Debug)]
+pub struct RemoteSignerClient {
+    url: String,
+    http: reqwest::Client,
+    rpc: RpcClient,
+    allowlist: HashSet<Address>,
+    public_keys: RwLock<HashMap<Address, VerifyingKey>>,
+}
+
+impl RemoteSignerClient {
+    /// Creates a new client of the `Web3Signer` service at `url` that only signs for the
+    /// addresses of `allowlist`, whatever keys the service holds.
+    pub fn new(url: &str, allowlist: impl IntoIterator<Item = Address>) -> Result<Self> {
+        Ok(Self {
+            url: url.trim_end_matches('/').to_string(),
+            http: reqwest::Client::new(),
+            rpc: RpcClient::new_http(url.parse()?),
+            allowlist: allowlist.into_iter().collect(),
+            public_keys: RwLock::default(),
+        })
+    }
+
+    /// Returns the cached public key of `address`, fetching the keys of the service on a miss.
+    pub async fn public_key(&self, address: Address) -> Result<VerifyingKey, RemoteSignerError> {
+        if !self.allowlist.contains(&address) {
+            return Err(RemoteSignerError::NotAllowed(address));
+        }
+        if let Some(key) = self.public_keys.read().await.get(&address) {
+            return Ok(*key);
+        }
+
+        self.refresh().await?;
+        self.public_keys
+            .read()
+            .await
+            .get(&address)
+            .copied()
+            .ok_or(RemoteSignerError::UnknownKey(address))
+    }
+
+    /// Fetches the public keys held by the service, replacing the cache.
+    pub async fn refresh(&self) -> Result<(), RemoteSignerError> {
+        let url = format!("{}/{PUBLIC_KEYS_PATH}", self.url);
+        let response = self.http.get(url).send().await.map_err(RemoteSignerError::Http)?;
+        let keys: Vec<String> = response
+            .error_for_status()
+            .map_err(RemoteSignerError::Http)?
+            .json()
+            .await
+            .map_err(RemoteSignerError::Http)?;
+
+        let mut public_keys = HashMap::with_capacity(keys.len());
+        for key in keys {
+            let invalid =
+                || RemoteSignerError::InvalidResponse(format!("invalid public key {key}"));
+            let mut bytes = hex::decode(&key).map_err(|_| invalid())?;
+            // Web3Signer serves uncompressed keys without the SEC1 tag.
+            if bytes.len() == 64 {
+                bytes.insert(0, 0x04);
+            }
+            let key = VerifyingKey::from_sec1_bytes(&bytes).map_err(|_| invalid())?;
+            public_keys.insert(public_key_to_address(&key), key);
+        }
+        *self.public_keys.write().await = public_keys;
+        Ok(())
+    }
+
+    /// Returns a signer for `address`, which must be allowlisted and held by the service.
+    pub async fn signer(
+        self: &Arc<Self>,
+        address: Address,
+    ) -> Result<RemoteSigner, RemoteSignerError> {
+        let public_key = self.public_key(address).await?;
+        Ok(RemoteSigner { client: self.clone(), address, public_key, chain_id: None })
+    }
+
+    /// Sends a signing request for an allowlisted address.
+    async fn request<P, R>(
+        &self,
+        address: Address,
+        method: &'static str,
+        params: P,
+    ) -> Result<R, RemoteSignerError>
+    where
+        P: serde::Serialize + Clone + Send + Sync + Unpin + fmt::Debug + 'static,
+        R: serde::de::DeserializeOwned + fmt::Debug + Send + Sync + Unpin + 'static,
+    {
+        if !self.allowlist.contains(&address) {
+            return Err(RemoteSignerError::NotAllowed(address));
+        }
+        self.rpc.request(method, params).await.map_err(RemoteSignerError::Rpc)
+    }
+}
+
+/// A [`Signer`] of one address of a remote signing service.
+///
+/// Every signature returned by the service is verified against the cached public key, so a
+/// compromised service cannot make it return a signature of another key or of another payload.
+#[derive(Clone, Debug)]
+pub struct RemoteSigner {
+    client: Arc<RemoteSignerClient>,
+    address: Address,
+    public_key: VerifyingKey,
+    chain_id: Option<ChainId>,
+}
+
+impl RemoteSigner {
+    /// Returns the address of the signer.
+    pub const fn address(&self) -> Address {
+        self.address
+    }
+
+    /// Checks that `signature` over `hash` was made by the key of the signer.
+    fn verify(&self, signature: Signature, hash: &B256) -> Result<Signature, RemoteSignerError> {
+        match signature.recover_from_prehash(hash) {
+            Ok(key) if key == self.public_key => Ok(signature),
+            _ => Err(RemoteSignerError::InvalidSignature(self.address)),
+        }
+    }
+
+    /// Signs a transaction with `eth_signTransaction`.
+    async fn sign_transaction_remote(
+        &self,
+        tx: &dyn SignableTransaction<Signature>,
+    ) -> Result<Signature, RemoteSignerError> {
+        let request = transaction_request(tx, self.address);
+        let raw: Bytes =
+            self.client.request(self.address, "eth_signTransaction", (request,)).await?;
+        let signed = TxEnvelope::decode_2718(&mut raw.as_ref())
+            .map_err(|err| RemoteSignerError::InvalidResponse(err.to_string()))?;
+
+        // The signature must be over the transaction that was asked for, not the one returned.
+        self.verify(*signed.signature(), &tx.signature_hash())
+    }
+}
+
+/// Builds the `eth_signTransaction` request of a transaction to sign.
+fn transaction_request(
+    tx: &dyn SignableTransaction<Signature>,
+    from: Address,
+) -> TransactionRequest {
+    let (gas_price, max_fee_per_gas) = if tx.is_dynamic_fee() {
+        (None, Some(tx.max_fee_per_gas()))
+    } else {
+        (Some(tx.max_fee_per_gas()), None)
+    };
+    TransactionRequest {
+        from: Some(from),
+        to: Some(tx.kind()),
+        gas_price,
+        max_fee_per_gas,
+        max_priority_fee_per_gas: tx.max_priority_fee_per_gas(),
+        gas: Some(tx.gas_limit()),
+        value: Some(tx.value()),
+        input: TransactionInput::new(tx.input().clone()),
+        nonce: Some(tx.nonce()),
+        chain_id: tx.chain_id(),
+        access_list: tx.access_list().cloned(),
+        max_fee_per_blob_gas: tx.max_fee_per_blob_gas(),
+        blob_versioned_hashes: tx.blob_versioned_hashes().map(Vec::from),
+        transaction_type: Some(tx.ty()),
+        sidecar: None,
+        authorization_list: tx.authorization_list().map(Vec::from),
+    }
+}
+
+#[async_trait]
+impl Signer for RemoteSigner {
+    /// Signing service APIs only sign messages and transactions, never raw hashes.
+    async fn sign_hash(&self, _hash: &B256) -> alloy::signers::Result<Signature> {
+        Err(alloy::signers::Error::UnsupportedOperation(UnsupportedSignerOperation::SignHash))
+    }
+
+    async fn sign_message(&self, message: &[u8]) -> alloy::signers::Result<Signature> {
+        let data = Bytes::copy_from_slice(message);
+        let signature: Bytes = self
+            .client
+            .request(self.address, "eth_sign", (self.address, data))
+            .await
+            .map_err(alloy::signers::Error::other)?;
+        let signature = Signature::from_raw(&signature)?;
+        Ok(self
+            .verify(signature, &eip191_hash_message(message))
+            .map_err(alloy::signers::Error::other)?)
+    }
+
+    fn address(&self) -> Address {
+        self.address
+    }
+
+    fn chain_id(&self) -> Option<ChainId> {
+        self.chain_id
+    }
+
+    fn set_chain_id(&mut self, chain_id: Option<ChainId>) {
+        self.chain_id = chain_id;
+    }
+}
+
+#[async_trait]
+impl TxSigner<Signature> for RemoteSigner {
+    fn address(&self) -> Address {
+        self.address
+    }
+
+    async fn sign_transaction(
+        &self,
+        tx: &mut dyn SignableTransaction<Signature>,
+    ) -> alloy::signers::Result<Signature> {
+        if let (Some(signer), Some(tx_chain_id)) = (self.chain_id, tx.chain_id()) {
+            if signer != tx_chain_id {
+                return Err(alloy::signers::Error::TransactionChainIdMismatch {
+                    signer,
+                    tx: tx_chain_id,
+                });
+            }
+        }
+        self.sign_transaction_remote(tx).await.map_err(alloy::signers::Error::other)
+    }
+}
+
+/// A local stand-in for a Web3Signer-compatible signing service.
+#[derive(Clone, Debug, Default)]
+pub struct MockSigningService {
+    keys: Arc<Vec<PrivateKeySigner>>,
+    public_key_requests: Arc<AtomicUsize>,
+    compromised: Arc<AtomicBool>,
+}
+
+impl MockSigningService {
+    /// Creates a new service holding `keys`.
+    pub fn new(keys: Vec<PrivateKeySigner>) -> Self {
+        Self { keys: Arc::new(keys), ..Default::default() }
+    }
+
+    /// Serves the service on a local port, returning its URL.
+    pub async fn serve(self) -> Result<String> {
+        let app = Router::new()
+            .route(&format!("/{PUBLIC_KEYS_PATH}"), routing::get(Self::public_keys))
+            .route("/", routing::post(Self::rpc))
+            .with_state(self);
+        let listener = TcpListener::bind("127.0.0.1:0").await?;
+        let url = format!("http://{}", listener.local_addr()?);
+        tokio::spawn(async move { axum::serve(listener, app).await });
+        Ok(url)
+    }
+
+    /// Returns how many times the public keys were requested.
+    pub fn public_key_requests(&self) -> usize {
+        self.public_key_requests.load(Ordering::Relaxed)
+    }
+
+    /// Makes the service sign with the wrong key from now on.
+    pub fn compromise(&self) {
+        self.compromised.store(true, Ordering::Relaxed);
+    }
+
+    /// Returns the key to sign with for `address`.
+    fn key(&self, address: Address) -> Result<PrivateKeySigner> {
+        let key = self.keys.iter().find(|key| key.address() == address);
+        let key = key.ok_or_else(|| eyre::eyre!("no key for {address}"))?;
+        if self.compromised.load(Ordering::Relaxed) {
+            return Ok(PrivateKeySigner::random());
+        }
+        Ok(key.clone())
+    }
+
+    /// Serves the public keys held by the service, without the SEC1 tag.
+    async fn public_keys(State(service): State<Self>) -> Json<Vec<String>> {
+        service.public_key_requests.fetch_add(1, Ordering::Relaxed);
+        let keys = service.keys.iter().map(|key| {
+            let point = key.credential().verifying_key().to_encoded_point(false);
+            hex::encode_prefixed(&point.as_bytes()[1..])
+        });
+        Json(keys.collect())
+    }
+
+    /// Answers a JSON-RPC request.
+    async fn rpc(State(service): State<Self>, Json(request): Json<Value>) -> Json<Value> {
+        let id = request["id"].clone();
+        let method = request["method"].as_str().unwrap_or_default();
+        Json(match service.handle(method, &request["params"]).await {
+            Ok(result) => json!({ "jsonrpc": "2.0", "id": id, "result": result }),
+            Err(err) => json!({
+                "jsonrpc": "2.0",
+                "id": id,
+                "error": { "code": -32000, "message": err.to_string() },
+            }),
+        })
+    }
+
+    /// Signs the payload of a JSON-RPC request.
+    async fn handle(&self, method: &str, params: &Value) -> Result<Value> {
+        match method {
+            "eth_sign" => {
+                let address: Address = serde_json::from_value(params[0].clone())?;
+                let data: Bytes = serde_json::from_value(params[1].clone())?;
+                let signature = self.key(address)?.sign_message(&data).await?;
+                Ok(json!(hex::encode_prefixed(signature.as_bytes())))
+            }
+            "eth_signTransaction" => {
+                let request: TransactionRequest = serde_json::from_value(params[0].clone())?;
+                let from = request.from.ok_or_else(|| eyre::eyre!("missing from"))?;
+                let wallet = EthereumWallet::new(self.key(from)?);
+                let signed: TxEnvelope =
+                    NetworkWallet::<Ethereum>::sign_request(&wallet, request).await?;
+                Ok(json!(hex::encode_prefixed(signed.encoded_2718())))
+            }
+            method => eyre::bail!("unsupported method {method}"),
+        }
+    }
+}
+
+#[tokio::main]
+async fn main() -> Result<()> {
+    // The service holds the keys of Alice, Bob and Carol, but only Alice and Bob are allowlisted.
+    let alice = PrivateKeySigner::random();
+    let bob = PrivateKeySigner::random();
+    let carol = PrivateKeySigner::random();
+    let service = MockSigningService::new(vec![alice.clone(), bob.clone(), carol.clone()]);
+    let url = service.clone().serve().await?;
+
+    let client = Arc::new(RemoteSignerClient::new(&url, [alice.address(), bob.address()])?);
+
+    // The public keys are fetched once and then served from the cache.
+    let remote_alice = client.signer(alice.address()).await?;
+    let remote_bob = client.signer(bob.address()).await?;
+    assert_eq!(service.public_key_requests(), 1);
+    println!("Remote signers for {} and {}", remote_alice.address(), remote_bob.address());
+
+    // Carol is held by the service but not allowlisted, and Dave is unknown to the service.
+    let result = client.signer(carol.address()).await;
+    assert!(matches!(result, Err(RemoteSignerError::NotAllowed(_))));
+    let dave = PrivateKeySigner::random().address();
+    let result = RemoteSignerClient::new(&url, [dave])?.public_key(dave).await;
+    assert!(matches!(result, Err(RemoteSignerError::UnknownKey(_))));
+
+    // Sign a message.
+    let message = b"hello from a remote signer";
+    let signature = remote_alice.sign_message(message).await?;
+    assert_eq!(signature.recover_address_from_msg(message)?, alice.address());
+    println!("Message signature: {}", hex::encode_prefixed(signature.as_bytes()));
+
+    // Raw hashes are never sent to the service.
+    assert!(remote_alice.sign_hash(&B256::ZERO).await.unwrap_err().is_unsupported());
+
+    // Sign a transaction through a wallet, as a provider would.
+    let wallet = EthereumWallet::new(remote_alice.clone());
+    let tx = TransactionRequest::default()
+        .with_from(alice.address())
+        .with_to(bob.address())
+        .with_value(U256::from(100))
+        .with_nonce(0)
+        .with_chain_id(1)
+        .with_gas_limit(21_000)
+        .with_max_fee_per_gas(20_000_000_000)
+        .with_max_priority_fee_per_gas(1_000_000_000);
+    let signed: TxEnvelope = NetworkWallet::<Ethereum>::sign_request(&wallet, tx.clone()).await?;
+    assert_eq!(signed.recover_signer()?, alice.address());
+    println!("Signed transaction {}", signed.tx_hash());
+
+    // Signatures of a compromised service are rejected.
+    service.compromise();
+    assert!(remote_alice.sign_message(message).await.is_err());
+    assert!(NetworkWallet::<Ethereum>::sign_request(&wallet, tx).await.is_err());
+    println!("Rejected signatures of the compromised service");
+
+    Ok(())
+}
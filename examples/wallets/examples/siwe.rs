@@ -0,0 +1,633 @@
+//! Example of [Sign-In with Ethereum](https://eips.ethereum.org/EIPS/eip-4361) messages: building,
+//! parsing and verifying them for EOAs, [ERC-1271](https://eips.ethereum.org/EIPS/eip-1271)
+//! contract wallets and [ERC-6492](https://eips.ethereum.org/EIPS/eip-6492) counterfactual wallets.
+
+use std::{fmt, str::FromStr};
+
+use alloy::{
+    network::TransactionBuilder,
+    node_bindings::Anvil,
+    primitives::{address, b256, eip191_hash_message, Address, Bytes, Signature, B256},
+    providers::{Provider, ProviderBuilder},
+    rpc::types::{
+        simulate::{SimBlock, SimulatePayload},
+        TransactionRequest,
+    },
+    signers::{local::PrivateKeySigner, Signer},
+    sol,
+    sol_types::{SolCall, SolValue},
+    transports::TransportError,
+};
+use chrono::{DateTime, Duration, FixedOffset, SecondsFormat, Utc};
+use rand::{distributions::Alphanumeric, thread_rng, Rng};
+
+sol! {
+    #[allow(missing_docs)]
+    interface IERC1271 {
+        function isValidSignature(bytes32 hash, bytes signature) external view returns (bytes4 magicValue);
+    }
+
+    // A minimal contract wallet accepting 65-byte signatures by its owner.
+    #[allow(missing_docs)]
+    // solar v0.2.0; solar MinimalWallet.sol -Zcodegen --emit bin
+    #[sol(bytecode = "6140006040526101fb38036101fb6080396080515f1960601c8180611000521681149050602b575b5f80fd5b6080515f556101c080603b5f395ff3346101bc5736600d576101bc565b5f3560e01c80631626ba7e14603c5780638da5cb5b14602b576101bc565b5060a06040525f5460805260206080f35b5061026060405236600481039050604081129050156059575b5f80fd5b5f608052600460243501356041811490506078575b5f60805260206080f35b60246024350135806101805260a05260446024350135806101a05260c052606460243501355f1a806101c05260e05260405160043581806101e05252602081016101c051806101c05281525060408101610180518061018052815250606081016101a051806101a052815250608081016040525a5f5f60806101e05160015afa90509050610100523d601f81806102005201601f1981169050602081019050604051818180610220520191508160405290508180610200528152602081018280610200525f823e5090508061022052610120526101005115606e575b610120518061014052806101405280610140525160208114905015606e575b60206101405101515f1960601c8180610240521680610160528061016052806101605281149050156055575b5f54610160511415606e575b631626ba7e60e01b60805260206080f35b5f5ffd")]
+    contract MinimalWallet {
+        address public owner;
+
+        constructor(address owner_) {
+            owner = owner_;
+        }
+
+        function isValidSignature(bytes32 hash, bytes calldata signature)
+            external
+            view
+            returns (bytes4)
+        {
+            if (signature.length != 65) {
+                return bytes4(0);
+            }
+            bytes32 r;
+            bytes32 s;
+            uint8 v;
+            assembly {
+                r := calldataload(signature.offset)
+                s := calldataload(add(signature.offset, 32))
+                v := byte(0, calldataload(add(signature.offset, 64)))
+            }
+            (bool success, bytes memory signer) = address(1).staticcall(abi.encode(hash, v, r, s));
+            if (success && signer.length == 32 && abi.decode(signer, (address)) == owner) {
+                return this.isValidSignature.selector;
+            }
+            return bytes4(0);
+        }
+    }
+}
+
+/// The suffix of ERC-6492 wrapped signatures.
+const ERC6492_MAGIC: B256 =
+    b256!("6492649264926492649264926492649264926492649264926492649264926492");
+
+/// The deterministic CREATE2 deployer, deployed by default on Anvil.
+const CREATE2_DEPLOYER: Address = address!("4e59b44847b379578588920ca78fbf26c0b4956c");
+
+/// The first line of a message after the domain.
+const PREAMBLE: &str = " wants you to sign in with your Ethereum account:";
+
+/// A Sign-In with Ethereum message.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct SiweMessage {
+    /// The URI scheme of the origin of the request, e.g. `https`.
+    pub scheme: Option<String>,
+    /// The domain requesting the signing.
+    pub domain: String,
+    /// The address signing in.
+    pub address: Address,
+    /// A human-readable assertion the user signs.
+    pub statement: Option<String>,
+    /// The URI of the resource that is the subject of the signing.
+    pub uri: String,
+    /// The version of the message, must be `1`.
+    pub version: String,
+    /// The chain the address is on.
+    pub chain_id: u64,
+    /// A random token chosen by the relying party to prevent replay attacks.
+    pub nonce: String,
+    /// When the message was issued.
+    pub issued_at: DateTime<FixedOffset>,
+    /// When the signed message expires.
+    pub expiration_time: Option<DateTime<FixedOffset>>,
+    /// When the signed message becomes valid.
+    pub not_before: Option<DateTime<FixedOffset>>,
+    /// An identifier of the request chosen by the relying party.
+    pub request_id: Option<String>,
+    /// Resources the user wishes to have resolved as part of the authentication.
+    pub resources: Vec<String>,
+}
+
+impl SiweMessage {
+    /// Creates a new message issued now.
+    pub fn new(
+        domain: impl Into<String>,
+        address: Address,
+        uri: impl Into<String>,
+        chain_id: u64,
+        nonce: impl Into<String>,
+    ) -> Self {
+        Self {
+            scheme: None,
+            domain: domain.into(),
+            address,
+            statement: None,
+            uri: uri.into(),
+            version: "1".to_string(),
+            chain_id,
+            nonce: nonce.into(),
+            issued_at: Utc::now().fixed_offset(),
+            expiration_time: None,
+            not_before: None,
+            request_id: None,
+            resources: Vec::new(),
+        }
+    }
+
+    /// Sets the statement of the message.
+    pub fn with_statement(mut self, statement: impl Into<String>) -> Self {
+        self.statement = Some(statement.into());
+        self
+    }
+
+    /// Sets the time after which the message is no longer valid.
+    pub const fn with_expiration_time(mut self, expiration_time: DateTime<FixedOffset>) -> Self {
+        self.expiration_time = Some(expiration_time);
+        self
+    }
+
+    /// Checks that the message is addressed to `domain` on `chain_id` with the `nonce` the relying
+    /// party issued, and that it is valid at `time`.
+    pub fn validate(
+        &self,
+        domain: &str,
+        chain_id: u64,
+        nonce: &str,
+        time: DateTime<Utc>,
+    ) -> Result<(), SiweError> {
+        if self.domain != domain {
+            return Err(SiweError::DomainMismatch {
+                expected: domain.to_string(),
+                found: self.domain.clone(),
+            });
+        }
+        if self.chain_id != chain_id {
+            return Err(SiweError::ChainIdMismatch { expected: chain_id, found: self.chain_id });
+        }
+        if self.nonce != nonce {
+            return Err(SiweError::NonceMismatch);
+        }
+        if let Some(expiration_time) = self.expiration_time {
+            if time >= expiration_time {
+                return Err(SiweError::Expired(expiration_time));
+            }
+        }
+        if let Some(not_before) = self.not_before {
+            if time < not_before {
+                return Err(SiweError::NotYetValid(not_before));
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Formats a timestamp as RFC 3339, the format of message timestamps.
+fn format_time(time: &DateTime<FixedOffset>) -> String {
+    time.to_rfc3339_opts(SecondsFormat::AutoSi, true)
+}
+
+impl fmt::Display for SiweMessage {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if let Some(scheme) = &self.scheme {
+            write!(f, "{scheme}://")?;
+        }
+        writeln!(f, "{}{PREAMBLE}", self.domain)?;
+        writeln!(f, "{}", self.address.to_checksum(None))?;
+        writeln!(f)?;
+        if let Some(statement) = &self.statement {
+            writeln!(f, "{statement}")?;
+        }
+        writeln!(f)?;
+        writeln!(f, "URI: {}", self.uri)?;
+        writeln!(f, "Version: {}", self.version)?;
+        writeln!(f, "Chain ID: {}", self.chain_id)?;
+        writeln!(f, "Nonce: {}", self.nonce)?;
+        write!(f, "Issued At: {}", format_time(&self.issued_at))?;
+        if let Some(expiration_time) = &self.expiration_time {
+            write!(f, "\nExpiration Time: {}", format_time(expiration_time))?;
+        }
+        if let Some(not_before) = &self.not_before {
+            write!(f, "\nNot Before: {}", format_time(not_before))?;
+        }
+        if let Some(request_id) = &self.request_id {
+            write!(f, "\nRequest ID: {request_id}")?;
+        }
+        if !self.resources.is_empty() {
+            write!(f, "\nResources:")?;
+            for resource in &self.resources {
+                write!(f, "\n- {resource}")?;
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Reads the line `{tag}: {value}`, or nothing if `optional` and the next line has another tag.
+fn tagged<'a>(
+    lines: &mut std::iter::Peekable<std::str::Lines<'a>>,
+    tag: &str,
+    optional: bool,
+) -> Result<Option<&'a str>, SiweError> {
+    let prefix = format!("{tag}: ");
+    match lines.peek().and_then(|line| line.strip_prefix(&prefix)) {
+        Some(value) => {
+            lines.next();
+            Ok(Some(value))
+        }
+        None if optional => Ok(None),
+        None => Err(SiweError::Parse(format!("expected `{tag}`"))),
+    }
+}
+
+/// Parses an RFC 3339 timestamp.
+fn parse_time(value: &str) -> Result<DateTime<FixedOffset>, SiweError> {
+    DateTime::parse_from_rfc3339(value)
+        .map_err(|err| SiweError::Parse(format!("invalid timestamp {value}: {err}")))
+}
+
+impl FromStr for SiweMessage {
+    type Err = SiweError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let parse_error = |message: &str| SiweError::Parse(message.to_string());
+        let mut lines = s.lines().peekable();
+
+        let origin = lines
+            .next()
+            .and_then(|line| line.strip_suffix(PREAMBLE))
+            .ok_or_else(|| parse_error("invalid preamble"))?;
+        let (scheme, domain) = match origin.split_once("://") {
+            Some((scheme, domain)) => (Some(scheme.to_string()), domain),
+            None => (None, origin),
+        };
+        if domain.is_empty() {
+            return Err(parse_error("empty domain"));
+        }
+
+        // The address must be checksummed.
+        let address = lines
+            .next()
+            .and_then(|line| Address::parse_checksummed(line, None).ok())
+            .ok_or_else(|| parse_error("invalid address"))?;
+        if lines.next() != Some("") {
+            return Err(parse_error("expected an empty line after the address"));
+        }
+
+        let statement = match lines.next() {
+            Some("") => None,
+            Some(statement) => {
+                if lines.next() != Some("") {
+                    return Err(parse_error("expected an empty line after the statement"));
+                }
+                Some(statement.to_string())
+            }
+            None => return Err(parse_error("unexpected end of message")),
+        };
+
+        let uri = tagged(&mut lines, "URI", false)?.unwrap_or_default().to_string();
+        let version = tagged(&mut lines, "Version", false)?.unwrap_or_default().to_string();
+        if version != "1" {
+            return Err(SiweError::Parse(format!("unsupported version {version}")));
+        }
+        let chain_id = tagged(&mut lines, "Chain ID", false)?
+            .unwrap_or_default()
+            .parse()
+            .map_err(|_| parse_error("invalid chain ID"))?;
+        let nonce = tagged(&mut lines, "Nonce", false)?.unwrap_or_default().to_string();
+        if nonce.len() < 8 || !nonce.chars().all(|c| c.is_ascii_alphanumeric()) {
+            return Err(parse_error("the nonce must be at least 8 alphanumeric characters"));
+        }
+        let issued_at = parse_time(tagged(&mut lines, "Issued At", false)?.unwrap_or_default())?;
+        let expiration_time =
+            tagged(&mut lines, "Expiration Time", true)?.map(parse_time).transpose()?;
+        let not_before = tagged(&mut lines, "Not Before", true)?.map(parse_time).transpose()?;
+        let request_id = tagged(&mut lines, "Request ID", true)?.map(str::to_string);
+
+        let mut resources = Vec::new();
+        if lines.next_if_eq(&"Resources:").is_some() {
+            while let Some(resource) = lines.next_if(|line| line.starts_with("- ")) {
+                resources.push(resource[2..].to_string());
+            }
+        }
+        if let Some(line) = lines.next() {
+            return Err(SiweError::Parse(format!("unexpected line `{line}`")));
+        }
+
+        Ok(Self {
+            scheme,
+            domain: domain.to_string(),
+            address,
+            statement,
+            uri,
+            version,
+            chain_id,
+            nonce,
+            issued_at,
+            expiration_time,
+            not_before,
+            request_id,
+            resources,
+        })
+    }
+}
+
+/// An error that rejects a sign-in.
+#[derive(Debug)]
+pub enum SiweError {
+    /// The message is not a valid EIP-4361 message.
+    Parse(String),
+    /// The message is for another domain.
+    DomainMismatch {
+        /// The domain of the relying party.
+        expected: String,
+        /// The domain of the message.
+        found: String,
+    },
+    /// The message is for another chain.
+    ChainIdMismatch {
+        /// The chain the relying party expects.
+        expected: u64,
+        /// The chain of the message.
+        found: u64,
+    },
+    /// The nonce was not issued for this sign-in.
+    NonceMismatch,
+    /// The message has expired.
+    Expired(DateTime<FixedOffset>),
+    /// The message is not valid yet.
+    NotYetValid(DateTime<FixedOffset>),
+    /// The signature was not made by the address of the message.
+    InvalidSignature,
+    /// The signature of a contract wallet could not be checked.
+    Transport(TransportError),
+}
+
+impl fmt::Display for SiweError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Parse(message) => write!(f, "invalid message: {message}"),
+            Self::DomainMismatch { expected, found } => {
+                write!(f, "message is for domain {found}, expected {expected}")
+            }
+            Self::ChainIdMismatch { expected, found } => {
+                write!(f, "message is for chain {found}, expected {expected}")
+            }
+            Self::NonceMismatch => f.write_str("nonce mismatch"),
+            Self::Expired(time) => write!(f, "message expired at {time}"),
+            Self::NotYetValid(time) => write!(f, "message is not valid before {time}"),
+            Self::InvalidSignature => f.write_str("invalid signature"),
+            Self::Transport(err) => write!(f, "failed to verify the signature: {err}"),
+        }
+    }
+}
+
+impl std::error::Error for SiweError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Self::Transport(err) => Some(err),
+            _ => None,
+        }
+    }
+}
+
+impl From<TransportError> for SiweError {
+    fn from(err: TransportError) -> Self {
+        Self::Transport(err)
+    }
+}
+
+/// Generates a random nonce to issue for a sign-in.
+pub fn generate_nonce() -> String {
+    thread_rng().sample_iter(&Alphanumeric).take(17).map(char::from).collect()
+}
+
+/// Wraps the signature of a contract wallet that is not deployed yet as an ERC-6492 signature,
+/// with the call to `factory` that deploys it.
+pub fn wrap_erc6492(factory: Address, factory_calldata: Bytes, signature: Bytes) -> Bytes {
+    let mut wrapped = (factory, factory_calldata, signature).abi_encode_params();
+    wrapped.extend_from_slice(ERC6492_MAGIC.as_slice());
+    wrapped.into()
+}
+
+/// Returns the factory, factory calldata and inner signature of an ERC-6492 signature.
+fn unwrap_erc6492(signature: &[u8]) -> Option<(Address, Bytes, Bytes)> {
+    let wrapped = signature.strip_suffix(ERC6492_MAGIC.as_slice())?;
+    <(Address, Bytes, Bytes)>::abi_decode_params(wrapped).ok()
+}
+
+/// Returns whether `output` is the magic value returned by `isValidSignature` on success.
+fn is_magic_value(output: &[u8]) -> bool {
+    IERC1271::isValidSignatureCall::abi_decode_returns(output)
+        .is_ok_and(|magic| magic == IERC1271::isValidSignatureCall::SELECTOR)
+}
+
+/// Verifies sign-ins for the relying party at `domain`.
+#[derive(Debug)]
+pub struct SiweVerifier<P> {
+    provider: P,
+    domain: String,
+    chain_id: u64,
+}
+
+impl<P: Provider> SiweVerifier<P> {
+    /// Creates a new [`SiweVerifier`] for `domain`, accepting sign-ins on `chain_id`.
+    ///
+    /// Contract wallets are checked on the chain the provider is connected to, which must be
+    /// `chain_id`.
+    pub fn new(provider: P, domain: impl Into<String>, chain_id: u64) -> Self {
+        Self { provider, domain: domain.into(), chain_id }
+    }
+
+    /// Verifies a signed message against the nonce issued for the sign-in, and returns the parsed
+    /// message.
+    ///
+    /// The signature is checked against the exact text that was signed.
+    pub async fn verify(
+        &self,
+        message: &str,
+        signature: &[u8],
+        nonce: &str,
+    ) -> Result<SiweMessage, SiweError> {
+        let parsed: SiweMessage = message.parse()?;
+        parsed.validate(&self.domain, self.chain_id, nonce, Utc::now())?;
+
+        let hash = eip191_hash_message(message);
+        if !self.is_valid_signature(parsed.address, hash, signature).await? {
+            return Err(SiweError::InvalidSignature);
+        }
+
+        Ok(parsed)
+    }
+
+    /// Returns whether `signature` over `hash` is valid for `signer`.
+    ///
+    /// Signatures of EOAs are recovered locally. Contract wallets are asked with
+    /// `isValidSignature`, and ERC-6492 signatures of wallets that are not deployed yet are
+    /// checked by simulating the deployment followed by `isValidSignature` with `eth_simulateV1`.
+    pub async fn is_valid_signature(
+        &self,
+        signer: Address,
+        hash: B256,
+        signature: &[u8],
+    ) -> Result<bool, TransportError> {
+        if let Some((factory, factory_calldata, signature)) = unwrap_erc6492(signature) {
+            if self.provider.get_code_at(signer).await?.is_empty() {
+                return self
+                    .erc6492_is_valid(signer, hash, factory, factory_calldata, signature)
+                    .await;
+            }
+            return self.erc1271_is_valid(signer, hash, signature).await;
+        }
+
+        // EOAs, including EOAs with an EIP-7702 delegation, sign with their own key.
+        let recovered = Signature::from_raw(signature)
+            .ok()
+            .and_then(|signature| signature.recover_address_from_prehash(&hash).ok());
+        if recovered == Some(signer) {
+            return Ok(true);
+        }
+
+        if self.provider.get_code_at(signer).await?.is_empty() {
+            return Ok(false);
+        }
+        self.erc1271_is_valid(signer, hash, Bytes::copy_from_slice(signature)).await
+    }
+
+    /// Calls `isValidSignature` on a deployed contract wallet.
+    async fn erc1271_is_valid(
+        &self,
+        wallet: Address,
+        hash: B256,
+        signature: Bytes,
+    ) -> Result<bool, TransportError> {
+        let input = IERC1271::isValidSignatureCall { hash, signature }.abi_encode();
+        let tx = TransactionRequest::default().with_to(wallet).with_input(input);
+        match self.provider.call(tx).await {
+            Ok(output) => Ok(is_magic_value(&output)),
+            // The wallet reverted, which rejects the signature.
+            Err(err) if err.is_error_resp() => Ok(false),
+            Err(err) => Err(err),
+        }
+    }
+
+    /// Simulates the deployment of a counterfactual contract wallet followed by
+    /// `isValidSignature`, without deploying the wallet.
+    async fn erc6492_is_valid(
+        &self,
+        wallet: Address,
+        hash: B256,
+        factory: Address,
+        factory_calldata: Bytes,
+        signature: Bytes,
+    ) -> Result<bool, TransportError> {
+        let deploy = TransactionRequest::default().with_to(factory).with_input(factory_calldata);
+        let validate = TransactionRequest::default()
+            .with_to(wallet)
+            .with_input(IERC1271::isValidSignatureCall { hash, signature }.abi_encode());
+        let payload = SimulatePayload {
+            block_state_calls: vec![SimBlock {
+                block_overrides: None,
+                state_overrides: None,
+                calls: vec![deploy, validate],
+            }],
+            trace_transfers: false,
+            validation: false,
+            return_full_transactions: false,
+        };
+
+        let blocks = self.provider.simulate(&payload).await?;
+        let result = blocks.first().and_then(|block| block.calls.get(1));
+        Ok(result.is_some_and(|result| result.status && is_magic_value(&result.return_data)))
+    }
+}
+
+/// Returns the init code of a [`MinimalWallet`] owned by `owner`.
+fn wallet_init_code(owner: Address) -> Bytes {
+    [MinimalWallet::BYTECODE.as_ref(), &owner.abi_encode()].concat().into()
+}
+
+/// Returns the calldata of the CREATE2 deployer to deploy `init_code` with `salt`.
+fn deployer_calldata(salt: B256, init_code: &[u8]) -> Bytes {
+    [salt.as_slice(), init_code].concat().into()
+}
+
+#[tokio::main]
+async fn main() -> eyre::Result<()> {
+    // Spin up a local Anvil node.
+    // Ensure `anvil` is available in $PATH.
+    let anvil = Anvil::new().try_spawn()?;
+    let provider =
+        ProviderBuilder::new().wallet(anvil.wallet().unwrap()).connect_http(anvil.endpoint_url());
+    let chain_id = provider.get_chain_id().await?;
+
+    let domain = "example.com";
+    let verifier = SiweVerifier::new(&provider, domain, chain_id);
+
+    // The backend issues a nonce, and the user signs a message with it.
+    let user = PrivateKeySigner::random();
+    let nonce = generate_nonce();
+    let message =
+        SiweMessage::new(domain, user.address(), "https://example.com/login", chain_id, &nonce)
+            .with_statement("Sign in to Example.")
+            .with_expiration_time((Utc::now() + Duration::minutes(10)).fixed_offset());
+    let text = message.to_string();
+    println!("{text}\n");
+    assert_eq!(text.parse::<SiweMessage>()?, message);
+
+    let signature = user.sign_message(text.as_bytes()).await?;
+    let signed_in = verifier.verify(&text, &signature.as_bytes(), &nonce).await?;
+    println!("Signed in as EOA {}", signed_in.address);
+
+    // Replays with another nonce, expired messages and messages for other domains are rejected.
+    let result = verifier.verify(&text, &signature.as_bytes(), &generate_nonce()).await;
+    assert!(matches!(result, Err(SiweError::NonceMismatch)));
+
+    let expired = message
+        .clone()
+        .with_expiration_time((Utc::now() - Duration::minutes(1)).fixed_offset())
+        .to_string();
+    let signature = user.sign_message(expired.as_bytes()).await?;
+    let result = verifier.verify(&expired, &signature.as_bytes(), &nonce).await;
+    assert!(matches!(result, Err(SiweError::Expired(_))));
+
+    let phishing = SiweMessage { domain: "example.org".to_string(), ..message.clone() }.to_string();
+    let signature = user.sign_message(phishing.as_bytes()).await?;
+    let result = verifier.verify(&phishing, &signature.as_bytes(), &nonce).await;
+    assert!(matches!(result, Err(SiweError::DomainMismatch { .. })));
+
+    // A contract wallet owned by the user, deployed with the CREATE2 deployer.
+    let init_code = wallet_init_code(user.address());
+    let salt = B256::with_last_byte(1);
+    let wallet = CREATE2_DEPLOYER.create2_from_code(salt, &init_code);
+    let tx = TransactionRequest::default()
+        .with_to(CREATE2_DEPLOYER)
+        .with_input(deployer_calldata(salt, &init_code));
+    provider.send_transaction(tx).await?.get_receipt().await?;
+    assert!(!provider.get_code_at(wallet).await?.is_empty());
+
+    // The user signs in as the wallet, which validates the signature with ERC-1271.
+    let text =
+        SiweMessage::new(domain, wallet, "https://example.com/login", chain_id, &nonce).to_string();
+    let signature = user.sign_message(text.as_bytes()).await?;
+    let signed_in = verifier.verify(&text, &signature.as_bytes(), &nonce).await?;
+    println!("Signed in as contract wallet {}", signed_in.address);
+
+    let stranger = PrivateKeySigner::random().sign_message(text.as_bytes()).await?;
+    let result = verifier.verify(&text, &stranger.as_bytes(), &nonce).await;
+    assert!(matches!(result, Err(SiweError::InvalidSignature)));
+
+    // A wallet that is not deployed yet signs in with an ERC-6492 signature.
+    let salt = B256::with_last_byte(2);
+    let wallet = CREATE2_DEPLOYER.create2_from_code(salt, &init_code);
+    let text =
+        SiweMessage::new(domain, wallet, "https://example.com/login", chain_id, &nonce).to_string();
+    let signature = user.sign_message(text.as_bytes()).await?;
+    let wrapped = wrap_erc6492(
+        CREATE2_DEPLOYER,
+        deployer_calldata(salt, &init_code),
+        signature.as_bytes().into(),
+    );
+    let signed_in = verifier.verify(&text, &wrapped, &nonce).await?;
+    println!("Signed in as counterfactual wallet {}", signed_in.address);
+
+    // The verification only simulated the deployment.
+    assert!(provider.get_code_at(wallet).await?.is_empty());
+
+    Ok(())
+}